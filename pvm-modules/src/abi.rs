@@ -9,11 +9,12 @@
 ///   - uint32   → 32-byte word, value right-aligned
 ///   - bool     → 32-byte word, 0 or 1
 ///   - uint64   → 32-byte word, value right-aligned
+///   - intN     → 32-byte word, two's complement sign-extended to 256 bits
 ///
 /// ethabi mirrors this layout exactly when given the correct ParamType descriptors.
 /// All decode functions must list fields in the identical order as the Solidity struct.
 
-use ethabi::{decode, encode, ParamType, Token};
+use ethabi::{decode, encode, Int, ParamType, Token};
 use crate::yield_optimizer::{OptimizerInput, YieldRecommendation};
 
 // ---------------------------------------------------------------------------
@@ -32,7 +33,57 @@ use crate::yield_optimizer::{OptimizerInput, YieldRecommendation};
 /// Returns None if the byte slice is malformed or any field is out of range.
 /// The precompile returns an error code to Solidity on None, which triggers
 /// a FailedOptimization event and aborts the XCM dispatch.
+///
+/// Calldata may be prefixed with a version tag word (see version_tag); the
+/// layout above is version 0 and needs no tag.
 pub fn decode_optimizer_input(input: &[u8]) -> Option<OptimizerInput> {
+    decode_optimizer_input_versioned(input).map(|(_, decoded)| decoded)
+}
+
+/// First four bytes of a calldata version tag word ("PPLS").
+pub const VERSION_TAG_MAGIC: [u8; 4] = *b"PPLS";
+
+/// The 32-byte word that marks calldata as `version`: VERSION_TAG_MAGIC,
+/// 27 zero bytes, then the version number.
+///
+/// The magic sits in the word's high bytes, so a tag can never be mistaken
+/// for an untagged version-0 principal, which must fit in 128 bits.
+pub fn version_tag(version: u8) -> [u8; 32] {
+    let mut tag = [0u8; 32];
+    tag[..4].copy_from_slice(&VERSION_TAG_MAGIC);
+    tag[31] = version;
+    tag
+}
+
+/// decode_optimizer_input() that also reports the calldata version.
+///
+/// Without a tag word the input is version 0. With one, the version picks the
+/// decoder for the rest of the calldata:
+///   1 = the version-0 field layout, following the tag
+///
+/// Returns None for an unknown version, a tag word with stray bytes between
+/// magic and version, or a body that fails its decoder.
+pub fn decode_optimizer_input_versioned(input: &[u8]) -> Option<(u8, OptimizerInput)> {
+    if !input.starts_with(&VERSION_TAG_MAGIC) {
+        return decode_optimizer_input_at(input, 0).map(|decoded| (0, decoded));
+    }
+    let tag = input.get(..32)?;
+    if tag[4..31].iter().any(|&b| b != 0) {
+        return None;
+    }
+    match tag[31] {
+        1 => decode_optimizer_input_at(input, 32).map(|decoded| (1, decoded)),
+        _ => None,
+    }
+}
+
+/// decode_optimizer_input() starting `offset` bytes into `input`, for calldata
+/// embedded in a larger buffer such as a batched multicall.
+///
+/// An offset past the end of the buffer returns None rather than panicking.
+pub fn decode_optimizer_input_at(input: &[u8], offset: usize) -> Option<OptimizerInput> {
+    let input = input.get(offset..)?;
+
     let types = vec![
         ParamType::Uint(128), // principal
         ParamType::Uint(32),  // hydradx_apy_bps
@@ -50,14 +101,21 @@ pub fn decode_optimizer_input(input: &[u8]) -> Option<OptimizerInput> {
         return None;
     }
 
-    let principal        = tokens[0].clone().into_uint()?.as_u128();
-    let hydradx_apy_bps  = tokens[1].clone().into_uint()?.as_u32();
-    let interlay_apy_bps = tokens[2].clone().into_uint()?.as_u32();
-    let hydradx_fee_bps  = tokens[3].clone().into_uint()?.as_u32();
-    let interlay_fee_bps = tokens[4].clone().into_uint()?.as_u32();
-    let hydradx_risk     = tokens[5].clone().into_uint()?.as_u32();
-    let interlay_risk    = tokens[6].clone().into_uint()?.as_u32();
-    let periods          = tokens[7].clone().into_uint()?.as_u32();
+    // Consume the tokens in field order — no per-field clone. ethabi reads
+    // every uintN slot as a full 256-bit word without checking N, and the
+    // as_uN() casts panic on a wider value, so each word is bounded to its
+    // declared width here and an oversized one rejects the whole input.
+    let mut fields = tokens.into_iter().map(Token::into_uint);
+    let mut next_uint = |bits: usize| fields.next().flatten().filter(|v| v.bits() <= bits);
+
+    let principal        = next_uint(128)?.as_u128();
+    let hydradx_apy_bps  = next_uint(32)?.as_u32();
+    let interlay_apy_bps = next_uint(32)?.as_u32();
+    let hydradx_fee_bps  = next_uint(32)?.as_u32();
+    let interlay_fee_bps = next_uint(32)?.as_u32();
+    let hydradx_risk     = next_uint(32)?.as_u32();
+    let interlay_risk    = next_uint(32)?.as_u32();
+    let periods          = next_uint(32)?.as_u32();
 
     Some(OptimizerInput {
         principal,
@@ -68,6 +126,7 @@ pub fn decode_optimizer_input(input: &[u8]) -> Option<OptimizerInput> {
         hydradx_risk_score: hydradx_risk,
         interlay_risk_score: interlay_risk,
         projection_periods: periods,
+        ..OptimizerInput::default()
     })
 }
 
@@ -82,14 +141,160 @@ pub fn decode_optimizer_input(input: &[u8]) -> Option<OptimizerInput> {
 ///    uint64 hydradx_allocation_pct, uint64 interlay_allocation_pct,
 ///    uint32 projected_net_apy_bps, uint128 expected_yield_dot)
 pub fn encode_yield_recommendation(rec: &YieldRecommendation) -> Vec<u8> {
-    encode(&[
+    encode(&yield_recommendation_tokens(rec))
+}
+
+/// The tokens encode_yield_recommendation encodes, in the same order, for
+/// callers that embed the recommendation in a larger payload. Encoding them
+/// in one call with the surrounding tokens keeps the head/tail offsets right
+/// once any field is dynamic; concatenating separate encodes does not.
+pub fn yield_recommendation_tokens(rec: &YieldRecommendation) -> Vec<Token> {
+    recommendation_tokens(rec, |v| Token::Uint(v.into()))
+}
+
+/// yield_recommendation_tokens() with both allocation percentages passed
+/// through checked_token_u64 — what the optimizer precompile returns. The
+/// plain encoder stays width-faithful for any u64 so layout fixtures can
+/// exercise the full field range.
+pub(crate) fn checked_yield_recommendation_tokens(rec: &YieldRecommendation) -> Vec<Token> {
+    recommendation_tokens(rec, checked_token_u64)
+}
+
+fn recommendation_tokens(rec: &YieldRecommendation, allocation: fn(u64) -> Token) -> Vec<Token> {
+    vec![
         Token::Bool(rec.use_hydradx),
         Token::Bool(rec.use_interlay),
-        Token::Uint(rec.hydradx_allocation_pct.into()),
-        Token::Uint(rec.interlay_allocation_pct.into()),
+        allocation(rec.hydradx_allocation_pct),
+        allocation(rec.interlay_allocation_pct),
         Token::Uint(rec.projected_net_apy_bps.into()),
         Token::Uint(rec.expected_yield_dot.into()),
-    ])
+    ]
+}
+
+/// Largest value an allocation token may carry: 10_000, a full allocation in
+/// BPS. Percentages (≤ 100) sit well inside it.
+pub(crate) const MAX_ALLOCATION_TOKEN: u64 = 10_000;
+
+/// The single conversion for allocation values (percentages or BPS shares)
+/// leaving either precompile.
+///
+/// Invariant: v ≤ MAX_ALLOCATION_TOKEN, so the value fits any width Solidity
+/// declares for an allocation. A larger value is a bug in the split logic:
+/// debug builds panic, release builds saturate to MAX_ALLOCATION_TOKEN rather
+/// than emitting a value the caller would mis-read.
+pub(crate) fn checked_token_u64(v: u64) -> Token {
+    debug_assert!(
+        v <= MAX_ALLOCATION_TOKEN,
+        "allocation token {v} exceeds {MAX_ALLOCATION_TOKEN}"
+    );
+    Token::Uint(v.min(MAX_ALLOCATION_TOKEN).into())
+}
+
+// ---------------------------------------------------------------------------
+// Signed integers: int8 … int128 ↔ i128
+// ---------------------------------------------------------------------------
+
+/// The ABI token for a signed field: `value` as a two's-complement word,
+/// sign-extended to 256 bits as Solidity's abi.encode() writes an intN.
+///
+/// The word is the same whatever N the field declares; it is the caller's
+/// job to keep `value` within that width.
+pub fn int_token(value: i128) -> Token {
+    // For negative values, !value = -value - 1 ≥ 0, and complementing its
+    // 256-bit word gives 2^256 + value — the sign-extended encoding.
+    let word = if value < 0 { !Int::from(!value as u128) } else { Int::from(value as u128) };
+    Token::Int(word)
+}
+
+/// ABI-encode a single signed value as one 32-byte word.
+pub fn encode_int(value: i128) -> Vec<u8> {
+    encode(&[int_token(value)])
+}
+
+/// Read an intN token (N ≤ 128) back into an i128.
+///
+/// ethabi hands back the raw 256-bit word without checking N, so the word is
+/// bounded here: every bit above the low N - 1 must repeat the sign bit.
+/// Returns None for a non-Int token, an N outside 1..=128, or a word that is
+/// not a valid sign extension of an N-bit value.
+pub fn token_to_int(token: Token, bits: usize) -> Option<i128> {
+    if bits == 0 || bits > 128 {
+        return None;
+    }
+    let word = token.into_int()?;
+    if word.bit(255) {
+        // Negative: the complement holds -value - 1, which must fit N - 1 bits.
+        let magnitude = !word;
+        (magnitude.bits() < bits).then(|| !(magnitude.low_u128() as i128))
+    } else {
+        (word.bits() < bits).then(|| word.low_u128() as i128)
+    }
+}
+
+/// Decode exactly one intN word (N ≤ 128). Returns None under the same
+/// conditions as token_to_int, or if `bytes` is not a single word.
+pub fn decode_int(bytes: &[u8], bits: usize) -> Option<i128> {
+    if bytes.len() != 32 {
+        return None;
+    }
+    let mut tokens = decode(&[ParamType::Int(bits)], bytes).ok()?;
+    token_to_int(tokens.pop()?, bits)
+}
+
+// ---------------------------------------------------------------------------
+// Error codes
+// ---------------------------------------------------------------------------
+
+/// The single definition of every error code both precompiles return to
+/// AtomicYieldExecutor.sol. The Solidity side must use the same numbers.
+pub mod error_codes {
+    pub const ERR_INVALID_INPUT: u32         = 1;
+    pub const ERR_OVERFLOW: u32              = 2;
+    pub const ERR_UNDERFLOW: u32             = 3;
+    pub const ERR_DIVISION_BY_ZERO: u32      = 4;
+    pub const ERR_UNKNOWN_SELECTOR: u32      = 5;
+    pub const ERR_DECODE_FAILED: u32         = 6;
+    pub const ERR_LENGTH_MISMATCH: u32       = 7;
+    pub const ERR_NO_VIABLE_DESTINATION: u32 = 8;
+
+    /// Every code with its name, in code order.
+    pub const ALL: [(u32, &str); 8] = [
+        (ERR_INVALID_INPUT,         "InvalidInput"),
+        (ERR_OVERFLOW,              "Overflow"),
+        (ERR_UNDERFLOW,             "Underflow"),
+        (ERR_DIVISION_BY_ZERO,      "DivisionByZero"),
+        (ERR_UNKNOWN_SELECTOR,      "UnknownSelector"),
+        (ERR_DECODE_FAILED,         "DecodeFailed"),
+        (ERR_LENGTH_MISMATCH,       "LengthMismatch"),
+        (ERR_NO_VIABLE_DESTINATION, "NoViableDestination"),
+    ];
+
+    // Checked at compile time: codes run 1, 2, 3, ... with no gaps or
+    // duplicates, so 0 never reads as an error and no two names collide.
+    const _: () = {
+        let mut i = 0;
+        while i < ALL.len() {
+            assert!(ALL[i].0 == i as u32 + 1);
+            i += 1;
+        }
+    };
+}
+
+/// Canonical human-readable message for an error code, for client-side
+/// display. Codes outside error_codes map to "unknown error".
+pub fn error_message(code: u32) -> &'static str {
+    use error_codes::*;
+    match code {
+        ERR_INVALID_INPUT         => "invalid input",
+        ERR_OVERFLOW              => "arithmetic overflow",
+        ERR_UNDERFLOW             => "arithmetic underflow",
+        ERR_DIVISION_BY_ZERO      => "division by zero",
+        ERR_UNKNOWN_SELECTOR      => "unknown function selector",
+        ERR_DECODE_FAILED         => "calldata decode failed",
+        ERR_LENGTH_MISMATCH       => "array length mismatch",
+        ERR_NO_VIABLE_DESTINATION => "no viable destination",
+        _                         => "unknown error",
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -102,11 +307,15 @@ pub fn encode_yield_recommendation(rec: &YieldRecommendation) -> Vec<u8> {
 /// the rest of the payload.
 ///
 /// Layout: (bool success, uint32 error_code)
-/// Error codes:
+/// Error codes (see error_codes):
 ///   1 = InvalidInput
 ///   2 = Overflow
 ///   3 = Underflow
 ///   4 = DivisionByZero
+///   5 = UnknownSelector
+///   6 = DecodeFailed
+///   7 = LengthMismatch
+///   8 = NoViableDestination
 pub fn encode_error(error_code: u32) -> Vec<u8> {
     encode(&[
         Token::Bool(false),
@@ -114,6 +323,73 @@ pub fn encode_error(error_code: u32) -> Vec<u8> {
     ])
 }
 
+// ---------------------------------------------------------------------------
+// Decode: precompile output → success tokens or error code
+// ---------------------------------------------------------------------------
+
+/// Split a precompile return blob on its leading success flag.
+///
+/// On success, every word after the flag is returned as a Token::Uint(256) in
+/// order — all precompile outputs are static words, so bools come back as 0/1
+/// and the caller interprets each position. On failure, the u32 error code
+/// from the second word is returned as Err.
+///
+/// A blob that is not a whole number of words, is empty, or is a failure
+/// without an error code word returns Err(6) (DecodeFailed).
+pub fn decode_call_result(bytes: &[u8]) -> Result<Vec<Token>, u32> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(32) {
+        return Err(error_codes::ERR_DECODE_FAILED);
+    }
+    let (flag_word, rest) = bytes.split_at(32);
+
+    let success = decode(&[ParamType::Bool], flag_word)
+        .ok()
+        .and_then(|mut t| t.pop())
+        .and_then(Token::into_bool)
+        .ok_or(error_codes::ERR_DECODE_FAILED)?;
+
+    if !success {
+        let code = decode(&[ParamType::Uint(32)], rest)
+            .ok()
+            .and_then(|mut t| t.pop())
+            .and_then(Token::into_uint)
+            .ok_or(error_codes::ERR_DECODE_FAILED)?;
+        if code.bits() > 32 {
+            return Err(error_codes::ERR_DECODE_FAILED);
+        }
+        return Err(code.as_u32());
+    }
+
+    let types = vec![ParamType::Uint(256); rest.len() / 32];
+    decode(&types, rest).map_err(|_| error_codes::ERR_DECODE_FAILED)
+}
+
+/// Inverse of encode_yield_recommendation: decode the six recommendation
+/// words (the success payload after the flag) back into a YieldRecommendation.
+///
+/// Only the fields on the wire are recovered; every off-chain field is left
+/// at its YieldRecommendation::default() value. Returns None if the payload
+/// is not exactly six words or any word is wider than its declared type,
+/// including a bool that is neither 0 nor 1.
+pub fn decode_yield_recommendation(bytes: &[u8]) -> Option<YieldRecommendation> {
+    if bytes.len() != 6 * 32 {
+        return None;
+    }
+    let tokens = decode(&vec![ParamType::Uint(256); 6], bytes).ok()?;
+    let mut fields = tokens.into_iter().map(Token::into_uint);
+    let mut next_uint = |bits: usize| fields.next().flatten().filter(|v| v.bits() <= bits);
+
+    Some(YieldRecommendation {
+        use_hydradx: !next_uint(1)?.is_zero(),
+        use_interlay: !next_uint(1)?.is_zero(),
+        hydradx_allocation_pct: next_uint(64)?.as_u64(),
+        interlay_allocation_pct: next_uint(64)?.as_u64(),
+        projected_net_apy_bps: next_uint(32)?.as_u32(),
+        expected_yield_dot: next_uint(128)?.as_u128(),
+        ..YieldRecommendation::default()
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Tests — ABI round-trip verification
 // ---------------------------------------------------------------------------
@@ -133,6 +409,7 @@ mod tests {
             hydradx_risk_score: 1_500,
             interlay_risk_score: 2_500,
             projection_periods: 365,
+            ..OptimizerInput::default()
         }
     }
 
@@ -147,12 +424,26 @@ mod tests {
             interlay_allocation_pct: 35,
             projected_net_apy_bps: 1_080,
             expected_yield_dot: 108 * PRECISION,
+            ..YieldRecommendation::default()
         };
         let encoded = encode_yield_recommendation(&rec);
         // 6 ABI words × 32 bytes each
         assert_eq!(encoded.len(), 6 * 32, "Encoded recommendation must be 192 bytes");
     }
 
+    /// Allocation tokens encode as one right-aligned big-endian word at each
+    /// boundary value.
+    #[test]
+    fn test_checked_token_u64_boundaries() {
+        for v in [0u64, 100, MAX_ALLOCATION_TOKEN] {
+            let word = encode(&[checked_token_u64(v)]);
+            let mut expected = [0u8; 32];
+            expected[24..].copy_from_slice(&v.to_be_bytes());
+            assert_eq!(word, expected.to_vec(), "word for {}", v);
+        }
+        assert_eq!(encode(&[checked_token_u64(10_000)])[30..], [0x27, 0x10]);
+    }
+
     /// Encode then decode OptimizerInput — all fields must survive the round-trip.
     #[test]
     fn test_decode_optimizer_input_round_trip() {
@@ -183,6 +474,34 @@ mod tests {
         assert_eq!(decoded.projection_periods, original.projection_periods);
     }
 
+    /// A full 256-bit word in the uint128 principal slot must be rejected,
+    /// not truncated to its low 128 bits or left to panic in the cast.
+    #[test]
+    fn test_decode_oversized_principal_returns_none() {
+        let mut encoded = encode_input(&sample_input());
+        encoded[..32].copy_from_slice(&[0xff; 32]);
+        assert!(decode_optimizer_input(&encoded).is_none());
+
+        // One bit above u128::MAX is already out of range.
+        encoded[..32].copy_from_slice(&[0; 32]);
+        encoded[15] = 0x01;
+        assert!(decode_optimizer_input(&encoded).is_none());
+
+        // u128::MAX itself is a well-formed uint128.
+        encoded[..32].copy_from_slice(&[[0u8; 16], [0xff; 16]].concat());
+        assert_eq!(decode_optimizer_input(&encoded).unwrap().principal, u128::MAX);
+    }
+
+    /// The same bound applies to every uint32 field.
+    #[test]
+    fn test_decode_oversized_uint32_field_returns_none() {
+        for field in 1..8 {
+            let mut encoded = encode_input(&sample_input());
+            encoded[field * 32 + 27] = 0x01; // 2^32 in a uint32 slot
+            assert!(decode_optimizer_input(&encoded).is_none(), "field {field}");
+        }
+    }
+
     /// Empty calldata must return None — not panic.
     #[test]
     fn test_decode_empty_input_returns_none() {
@@ -220,4 +539,458 @@ mod tests {
             "First word of error encoding must be all zeros (false)"
         );
     }
+
+    /// The clone-per-field decoder this module used before switching to
+    /// consuming the token vector. Kept only as a parity reference.
+    fn legacy_decode_optimizer_input(input: &[u8]) -> Option<OptimizerInput> {
+        let types = vec![ParamType::Uint(128), ParamType::Uint(32), ParamType::Uint(32),
+            ParamType::Uint(32), ParamType::Uint(32), ParamType::Uint(32),
+            ParamType::Uint(32), ParamType::Uint(32)];
+        let tokens = decode(&types, input).ok()?;
+        if tokens.len() != 8 {
+            return None;
+        }
+        Some(OptimizerInput {
+            principal: tokens[0].clone().into_uint()?.as_u128(),
+            hydradx_apy_bps: tokens[1].clone().into_uint()?.as_u32(),
+            interlay_apy_bps: tokens[2].clone().into_uint()?.as_u32(),
+            hydradx_fee_bps: tokens[3].clone().into_uint()?.as_u32(),
+            interlay_fee_bps: tokens[4].clone().into_uint()?.as_u32(),
+            hydradx_risk_score: tokens[5].clone().into_uint()?.as_u32(),
+            interlay_risk_score: tokens[6].clone().into_uint()?.as_u32(),
+            projection_periods: tokens[7].clone().into_uint()?.as_u32(),
+            ..OptimizerInput::default()
+        })
+    }
+
+    fn encode_input(i: &OptimizerInput) -> Vec<u8> {
+        encode(&[
+            Token::Uint(i.principal.into()),
+            Token::Uint(i.hydradx_apy_bps.into()),
+            Token::Uint(i.interlay_apy_bps.into()),
+            Token::Uint(i.hydradx_fee_bps.into()),
+            Token::Uint(i.interlay_fee_bps.into()),
+            Token::Uint(i.hydradx_risk_score.into()),
+            Token::Uint(i.interlay_risk_score.into()),
+            Token::Uint(i.projection_periods.into()),
+        ])
+    }
+
+    /// The consuming decoder must match the legacy decoder exactly on every
+    /// round-trip vector — including the malformed ones that return None.
+    #[test]
+    fn test_consuming_decoder_matches_legacy() {
+        let extremes = OptimizerInput {
+            principal: u128::MAX,
+            hydradx_apy_bps: u32::MAX,
+            interlay_apy_bps: u32::MAX,
+            hydradx_fee_bps: u32::MAX,
+            interlay_fee_bps: u32::MAX,
+            hydradx_risk_score: u32::MAX,
+            interlay_risk_score: u32::MAX,
+            projection_periods: u32::MAX,
+            ..OptimizerInput::default()
+        };
+        let mut blobs = vec![
+            encode_input(&sample_input()),
+            encode_input(&OptimizerInput::default()),
+            encode_input(&extremes),
+            vec![],
+            encode(&[Token::Uint(1_000u128.into()), Token::Uint(1_200u32.into())]),
+        ];
+        // Trailing bytes after the eight words are ignored by both decoders.
+        let mut padded = encode_input(&sample_input());
+        padded.extend([0u8; 32]);
+        blobs.push(padded);
+
+        for blob in &blobs {
+            assert_eq!(decode_optimizer_input(blob), legacy_decode_optimizer_input(blob));
+        }
+        assert_eq!(decode_optimizer_input(&blobs[0]), Some(sample_input()));
+    }
+
+    /// Untagged calldata is version 0 with the original layout.
+    #[test]
+    fn test_versioned_decode_untagged_is_v0() {
+        let blob = encode_input(&sample_input());
+        assert_eq!(decode_optimizer_input_versioned(&blob), Some((0, sample_input())));
+        assert_eq!(decode_optimizer_input(&blob), Some(sample_input()));
+    }
+
+    /// A v1 tag word in front of the fields is detected and decoded past.
+    #[test]
+    fn test_versioned_decode_tagged_v1() {
+        let mut blob = version_tag(1).to_vec();
+        blob.extend(encode_input(&sample_input()));
+        assert_eq!(decode_optimizer_input_versioned(&blob), Some((1, sample_input())));
+        assert_eq!(decode_optimizer_input(&blob), Some(sample_input()));
+    }
+
+    /// Unknown versions and corrupted tag words are rejected, not decoded as v0.
+    #[test]
+    fn test_versioned_decode_rejects_unknown_tag() {
+        for version in [0u8, 2, u8::MAX] {
+            let mut blob = version_tag(version).to_vec();
+            blob.extend(encode_input(&sample_input()));
+            assert_eq!(decode_optimizer_input_versioned(&blob), None, "version {}", version);
+        }
+        let mut stray = version_tag(1);
+        stray[10] = 1;
+        let mut blob = stray.to_vec();
+        blob.extend(encode_input(&sample_input()));
+        assert_eq!(decode_optimizer_input_versioned(&blob), None);
+        assert_eq!(decode_optimizer_input_versioned(&VERSION_TAG_MAGIC), None);
+    }
+
+    /// A success blob yields the recommendation words in encode order.
+    #[test]
+    fn test_decode_call_result_success() {
+        let rec = YieldRecommendation {
+            use_hydradx: true,
+            use_interlay: false,
+            hydradx_allocation_pct: 100,
+            interlay_allocation_pct: 0,
+            projected_net_apy_bps: 1_133,
+            expected_yield_dot: 120 * PRECISION,
+            ..YieldRecommendation::default()
+        };
+        let mut blob = encode(&[Token::Bool(true)]);
+        blob.extend(encode_yield_recommendation(&rec));
+
+        let tokens = decode_call_result(&blob).expect("Success blob must decode");
+        let words: Vec<u128> = tokens
+            .into_iter()
+            .map(|t| t.into_uint().unwrap().as_u128())
+            .collect();
+        assert_eq!(words, vec![1, 0, 100, 0, 1_133, 120 * PRECISION]);
+    }
+
+    /// decode_yield_recommendation inverts encode_yield_recommendation on the
+    /// wire fields and rejects words wider than their declared type.
+    #[test]
+    fn test_decode_yield_recommendation_round_trip() {
+        let rec = YieldRecommendation {
+            use_hydradx: true,
+            use_interlay: false,
+            hydradx_allocation_pct: 100,
+            interlay_allocation_pct: 0,
+            projected_net_apy_bps: 1_133,
+            expected_yield_dot: 120 * PRECISION,
+            ..YieldRecommendation::default()
+        };
+        let encoded = encode_yield_recommendation(&rec);
+        assert_eq!(decode_yield_recommendation(&encoded), Some(rec));
+
+        let mut bad_bool = encoded.clone();
+        bad_bool[31] = 2;
+        assert_eq!(decode_yield_recommendation(&bad_bool), None);
+        assert_eq!(decode_yield_recommendation(&encoded[32..]), None);
+    }
+
+    /// An error blob yields its error code.
+    #[test]
+    fn test_decode_call_result_error() {
+        assert_eq!(decode_call_result(&encode_error(1)), Err(1));
+        assert_eq!(decode_call_result(&encode_error(7)), Err(7));
+    }
+
+    /// Malformed blobs report DecodeFailed rather than panicking.
+    #[test]
+    fn test_decode_call_result_malformed() {
+        assert_eq!(decode_call_result(&[]), Err(error_codes::ERR_DECODE_FAILED));
+        assert_eq!(decode_call_result(&[0u8; 31]), Err(error_codes::ERR_DECODE_FAILED));
+        // Failure flag with no error code word.
+        assert_eq!(decode_call_result(&encode(&[Token::Bool(false)])), Err(error_codes::ERR_DECODE_FAILED));
+        // Error code wider than u32.
+        let wide = encode(&[Token::Bool(false), Token::Uint((u32::MAX as u64 + 1).into())]);
+        assert_eq!(decode_call_result(&wide), Err(error_codes::ERR_DECODE_FAILED));
+    }
+
+    // --- Solidity reference blobs ---
+    //
+    // Byte-for-byte abi.encode output for the static tuples AtomicYieldExecutor.sol
+    // sends and decodes. Static tuples are head-only: one 32-byte word per field,
+    // right-aligned, no offsets. These are written out independently of ethabi so
+    // a layout drift on the Rust side cannot hide behind ethabi agreeing with
+    // itself. smart-contracts/test/AbiFixtures.t.sol asserts the same six blobs
+    // against solc 0.8.20's abi.encode (`npx hardhat test solidity`); change
+    // both together.
+
+    /// abi.encode(OptimizerInput) — all zero.
+    const SOL_INPUT_MIN: &str = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+    );
+
+    /// abi.encode(OptimizerInput) — 1000 DOT, 1200/900 APY, 50/100 fee, 1500/2500 risk, 365 periods.
+    const SOL_INPUT_TYPICAL: &str = concat!(
+        "00000000000000000000000000000000000000000000003635c9adc5dea00000",
+        "00000000000000000000000000000000000000000000000000000000000004b0",
+        "0000000000000000000000000000000000000000000000000000000000000384",
+        "0000000000000000000000000000000000000000000000000000000000000032",
+        "0000000000000000000000000000000000000000000000000000000000000064",
+        "00000000000000000000000000000000000000000000000000000000000005dc",
+        "00000000000000000000000000000000000000000000000000000000000009c4",
+        "000000000000000000000000000000000000000000000000000000000000016d",
+    );
+
+    /// abi.encode(OptimizerInput) — uint128 max principal, uint32 max everywhere else.
+    const SOL_INPUT_MAX: &str = concat!(
+        "00000000000000000000000000000000ffffffffffffffffffffffffffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+    );
+
+    /// abi.encode(bool success, bool, bool, uint64, uint64, uint32, uint128) — success, no legs, all zero.
+    const SOL_OUTPUT_MIN: &str = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+    );
+
+    /// abi.encode(bool success, bool, bool, uint64, uint64, uint32, uint128) — success, both legs, 60/40, 1133 BPS, expected yield.
+    const SOL_OUTPUT_TYPICAL: &str = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "000000000000000000000000000000000000000000000000000000000000003c",
+        "0000000000000000000000000000000000000000000000000000000000000028",
+        "000000000000000000000000000000000000000000000000000000000000046d",
+        "00000000000000000000000000000000000000000000000683a44a135bd59485",
+    );
+
+    /// abi.encode(bool success, bool, bool, uint64, uint64, uint32, uint128) — success, both legs, uint64/uint32/uint128 max.
+    const SOL_OUTPUT_MAX: &str = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "000000000000000000000000000000000000000000000000ffffffffffffffff",
+        "000000000000000000000000000000000000000000000000ffffffffffffffff",
+        "00000000000000000000000000000000000000000000000000000000ffffffff",
+        "00000000000000000000000000000000ffffffffffffffffffffffffffffffff",
+    );
+
+    /// Precompile output as Solidity sees it: success flag, then the recommendation.
+    fn success_blob(rec: &YieldRecommendation) -> Vec<u8> {
+        let mut blob = encode(&[Token::Bool(true)]);
+        blob.extend(encode_yield_recommendation(rec));
+        blob
+    }
+
+    /// Solidity-encoded inputs decode to the expected field values.
+    #[test]
+    fn test_solidity_input_fixtures_decode() {
+        let min = decode_optimizer_input(&hex::decode(SOL_INPUT_MIN).unwrap()).unwrap();
+        assert_eq!(min, OptimizerInput::default());
+
+        let typical = decode_optimizer_input(&hex::decode(SOL_INPUT_TYPICAL).unwrap()).unwrap();
+        assert_eq!(typical, sample_input());
+
+        let max = decode_optimizer_input(&hex::decode(SOL_INPUT_MAX).unwrap()).unwrap();
+        assert_eq!(max.principal, u128::MAX);
+        assert_eq!(max.hydradx_apy_bps, u32::MAX);
+        assert_eq!(max.interlay_apy_bps, u32::MAX);
+        assert_eq!(max.hydradx_fee_bps, u32::MAX);
+        assert_eq!(max.interlay_fee_bps, u32::MAX);
+        assert_eq!(max.hydradx_risk_score, u32::MAX);
+        assert_eq!(max.interlay_risk_score, u32::MAX);
+        assert_eq!(max.projection_periods, u32::MAX);
+    }
+
+    /// The Rust encoder must reproduce the Solidity-side bytes exactly.
+    #[test]
+    fn test_solidity_output_fixtures_match() {
+        let min = YieldRecommendation::default();
+        assert_eq!(hex::encode(success_blob(&min)), SOL_OUTPUT_MIN);
+
+        let typical = YieldRecommendation {
+            use_hydradx: true,
+            use_interlay: true,
+            hydradx_allocation_pct: 60,
+            interlay_allocation_pct: 40,
+            projected_net_apy_bps: 1_133,
+            expected_yield_dot: 120_166_252_604_411_974_789,
+            ..YieldRecommendation::default()
+        };
+        assert_eq!(hex::encode(success_blob(&typical)), SOL_OUTPUT_TYPICAL);
+
+        let max = YieldRecommendation {
+            use_hydradx: true,
+            use_interlay: true,
+            hydradx_allocation_pct: u64::MAX,
+            interlay_allocation_pct: u64::MAX,
+            projected_net_apy_bps: u32::MAX,
+            expected_yield_dot: u128::MAX,
+            ..YieldRecommendation::default()
+        };
+        assert_eq!(hex::encode(success_blob(&max)), SOL_OUTPUT_MAX);
+    }
+
+    /// End to end: the typical Solidity calldata through the optimize precompile
+    /// returns exactly the bytes Solidity's abi.decode expects.
+    #[test]
+    fn test_solidity_typical_fixture_through_precompile() {
+        let mut calldata = hex::decode("efc2eb5f").unwrap();
+        calldata.extend(hex::decode(SOL_INPUT_TYPICAL).unwrap());
+        let output = crate::precompiles::yield_optimizer_precompile::call(&calldata);
+        assert_eq!(hex::encode(output), SOL_OUTPUT_TYPICAL);
+    }
+
+    /// Offset 0 is the plain decoder.
+    #[test]
+    fn test_decode_at_offset_zero() {
+        let blob = encode_input(&sample_input());
+        assert_eq!(decode_optimizer_input_at(&blob, 0), Some(sample_input()));
+        assert_eq!(decode_optimizer_input_at(&blob, 0), decode_optimizer_input(&blob));
+    }
+
+    /// Calldata embedded after a multicall header decodes from its offset.
+    #[test]
+    fn test_decode_at_nonzero_offset() {
+        // 4-byte selector plus one unrelated word ahead of the input.
+        let mut buffer = vec![0xab, 0xcd, 0xef, 0x01];
+        buffer.extend(encode(&[Token::Uint(42u32.into())]));
+        let offset = buffer.len();
+        buffer.extend(encode_input(&sample_input()));
+        buffer.extend([0u8; 32]);
+
+        assert_eq!(decode_optimizer_input_at(&buffer, offset), Some(sample_input()));
+    }
+
+    /// Offsets at or past the end of the buffer return None without panicking.
+    #[test]
+    fn test_decode_at_out_of_range_offset() {
+        let blob = encode_input(&sample_input());
+        assert_eq!(decode_optimizer_input_at(&blob, blob.len()), None);
+        assert_eq!(decode_optimizer_input_at(&blob, blob.len() + 1), None);
+        assert_eq!(decode_optimizer_input_at(&blob, usize::MAX), None);
+        // In range but too little left for eight words.
+        assert_eq!(decode_optimizer_input_at(&blob, 32), None);
+    }
+
+    /// Small values are right-aligned big-endian: 31 zero bytes, then the value.
+    #[test]
+    fn test_small_value_is_right_aligned() {
+        let rec = YieldRecommendation {
+            hydradx_allocation_pct: 1,
+            ..YieldRecommendation::default()
+        };
+        let encoded = encode_yield_recommendation(&rec);
+        let word = &encoded[2 * 32..3 * 32]; // hydradx_allocation_pct
+        assert!(word[..31].iter().all(|&b| b == 0), "high 31 bytes must be zero padding");
+        assert_eq!(word[31], 1);
+    }
+
+    /// A uint128 filling its 16 low bytes leaves the 16 high bytes zero, and the
+    /// low half reads back with from_be_bytes as the precompile tests assume.
+    #[test]
+    fn test_full_uint128_occupies_low_half_only() {
+        let value = u128::MAX - 0x0102;
+        let rec = YieldRecommendation {
+            expected_yield_dot: value,
+            ..YieldRecommendation::default()
+        };
+        let encoded = encode_yield_recommendation(&rec);
+        let word = &encoded[5 * 32..6 * 32]; // expected_yield_dot
+        assert!(word[..16].iter().all(|&b| b == 0), "high 16 bytes must be zero");
+        assert_eq!(u128::from_be_bytes(word[16..].try_into().unwrap()), value);
+        assert_eq!(word[31], 0xfd, "least significant byte must be last");
+    }
+
+    /// Bools encode as a full word holding 0 or 1 in the last byte.
+    #[test]
+    fn test_bool_word_layout() {
+        let rec = YieldRecommendation { use_interlay: true, ..YieldRecommendation::default() };
+        let encoded = encode_yield_recommendation(&rec);
+        assert!(encoded[..32].iter().all(|&b| b == 0), "false is an all-zero word");
+        assert!(encoded[32..63].iter().all(|&b| b == 0));
+        assert_eq!(encoded[63], 1);
+    }
+
+    /// A negative int128 round-trips with its sign, as an all-ones-padded word.
+    #[test]
+    fn test_negative_int128_roundtrip() {
+        let value = -100 * PRECISION as i128;
+        let encoded = encode_int(value);
+        assert_eq!(encoded.len(), 32);
+        assert!(encoded[..16].iter().all(|&b| b == 0xff), "sign-extended high half");
+        assert_eq!(i128::from_be_bytes(encoded[16..].try_into().unwrap()), value);
+        assert_eq!(decode_int(&encoded, 128), Some(value));
+    }
+
+    /// The int128 extremes survive the trip.
+    #[test]
+    fn test_int128_bounds_roundtrip() {
+        for value in [i128::MIN, i128::MAX, -1, 0, 1] {
+            assert_eq!(decode_int(&encode_int(value), 128), Some(value), "value {}", value);
+        }
+        assert_eq!(encode_int(-1), vec![0xff; 32]);
+        assert!(encode_int(i128::MIN)[..16].iter().all(|&b| b == 0xff));
+        assert_eq!(encode_int(i128::MIN)[16], 0x80);
+    }
+
+    /// Narrower widths accept exactly their own range.
+    #[test]
+    fn test_int32_width_checks() {
+        for value in [i32::MIN as i128, i32::MAX as i128, -42] {
+            assert_eq!(decode_int(&encode_int(value), 32), Some(value));
+        }
+        assert_eq!(decode_int(&encode_int(i32::MAX as i128 + 1), 32), None);
+        assert_eq!(decode_int(&encode_int(i32::MIN as i128 - 1), 32), None);
+    }
+
+    /// Words that are no valid int128, bad widths and wrong lengths are rejected.
+    #[test]
+    fn test_decode_int_rejects_malformed() {
+        // A positive word above int128's range.
+        let mut word = [0u8; 32];
+        word[15] = 1;
+        assert_eq!(decode_int(&word, 128), None);
+        // A negative word whose sign extension stops short.
+        let mut word = [0xffu8; 32];
+        word[0] = 0x7f;
+        assert_eq!(decode_int(&word, 128), None);
+        let mut word = [0xffu8; 32];
+        word[15] = 0x7f;
+        assert_eq!(decode_int(&word, 128), None);
+
+        assert_eq!(decode_int(&encode_int(1), 0), None);
+        assert_eq!(decode_int(&encode_int(1), 129), None);
+        assert_eq!(decode_int(&[0u8; 31], 128), None);
+        assert_eq!(decode_int(&[0u8; 64], 128), None);
+        assert_eq!(token_to_int(Token::Uint(1u32.into()), 128), None);
+    }
+
+    /// Every defined code has its own non-empty message; anything else gets
+    /// the default.
+    #[test]
+    fn test_error_message_covers_every_code() {
+        let messages: Vec<&str> = error_codes::ALL.iter().map(|&(code, _)| error_message(code)).collect();
+        for (msg, (code, _)) in messages.iter().zip(error_codes::ALL) {
+            assert!(!msg.is_empty() && *msg != "unknown error", "code {code}");
+        }
+        let mut distinct = messages.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), messages.len());
+
+        assert_eq!(error_message(0), "unknown error");
+        assert_eq!(error_message(error_codes::ALL.len() as u32 + 1), "unknown error");
+        assert_eq!(error_message(u32::MAX), "unknown error");
+    }
 }
\ No newline at end of file
@@ -6,6 +6,7 @@ pub mod precompile_set;
 
 pub use precompile_set::{
     PolkaPulsePrecompileSet,
+    registered_addresses,
     MATH_LIB_PRECOMPILE_ADDRESS,
     YIELD_OPTIMIZER_PRECOMPILE_ADDRESS,
 };
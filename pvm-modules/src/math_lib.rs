@@ -32,6 +32,15 @@ pub const SECONDS_PER_YEAR: u128 = 31_536_000;
 /// above this bound is rejected as invalid input.
 pub const MAX_RISK_SCORE: u128 = 10_000;
 
+/// Liquidity spread, in basis points, at which risk_from_spread_bps reaches
+/// MAX_RISK_SCORE: a 10% bid-ask spread. Wider spreads clamp there.
+pub const MAX_RISK_SPREAD_BPS: u128 = 1_000;
+
+/// Upper sanity bound on a single position's principal: 10 billion DOT.
+/// Total DOT issuance is well below this, so any larger value is a unit error
+/// (e.g. a value scaled by PRECISION twice) rather than a real position.
+pub const MAX_PRINCIPAL: u128 = 10_000_000_000 * PRECISION;
+
 // ---------------------------------------------------------------------------
 // Error type
 // ---------------------------------------------------------------------------
@@ -46,10 +55,170 @@ pub enum MathError {
     DivisionByZero,
     /// One or more input arguments are logically invalid (e.g. fee > 100%).
     InvalidInput,
+    /// Paired slice arguments (e.g. values and weights) have different lengths.
+    LengthMismatch,
 }
 
 pub type MathResult<T> = Result<T, MathError>;
 
+// ---------------------------------------------------------------------------
+// Fixed-point context
+// ---------------------------------------------------------------------------
+
+/// Decimal precision of a fixed-point asset amount.
+///
+/// DOT uses 18 decimals (PRECISION), but the same math applies to assets with
+/// other denominations — e.g. a 6-decimal stablecoin. The rate-only primitives
+/// (compound, fee_adjusted_yield, weighted_average, optimal_split) are
+/// scale-invariant and work unchanged on amounts in any FixedPoint; this type
+/// owns the conversions that do depend on the number of decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedPoint {
+    decimals: u8,
+    unit: u128,
+}
+
+impl FixedPoint {
+    /// The 18-decimal DOT context: 1 DOT = PRECISION units.
+    pub const DOT: FixedPoint = FixedPoint { decimals: 18, unit: PRECISION };
+
+    /// 10^38 is the largest power of ten that fits in u128.
+    pub const MAX_DECIMALS: u8 = 38;
+
+    /// Build a context for an asset with `decimals` decimal places.
+    /// Returns MathError::InvalidInput above MAX_DECIMALS.
+    pub fn new(decimals: u8) -> MathResult<Self> {
+        if decimals > Self::MAX_DECIMALS {
+            return Err(MathError::InvalidInput);
+        }
+        let unit = 10u128
+            .checked_pow(decimals as u32)
+            .ok_or(MathError::Overflow)?;
+        Ok(FixedPoint { decimals, unit })
+    }
+
+    /// Number of decimal places.
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Fixed-point units per whole token (10^decimals).
+    pub fn unit(&self) -> u128 {
+        self.unit
+    }
+
+    /// Convert a whole-token amount to fixed-point units.
+    pub fn to_fixed(&self, whole: u128) -> MathResult<u128> {
+        whole.checked_mul(self.unit).ok_or(MathError::Overflow)
+    }
+
+    /// Convert fixed-point units to whole tokens, truncating any fraction.
+    pub fn from_fixed(&self, value: u128) -> u128 {
+        value / self.unit
+    }
+
+    /// Re-express `value` (in this context's units) in `target`'s units.
+    /// Scaling down truncates; scaling up returns Overflow if it does not fit.
+    pub fn rescale(&self, value: u128, target: FixedPoint) -> MathResult<u128> {
+        if target.decimals >= self.decimals {
+            let factor = 10u128
+                .checked_pow((target.decimals - self.decimals) as u32)
+                .ok_or(MathError::Overflow)?;
+            value.checked_mul(factor).ok_or(MathError::Overflow)
+        } else {
+            let factor = 10u128
+                .checked_pow((self.decimals - target.decimals) as u32)
+                .ok_or(MathError::Overflow)?;
+            Ok(value / factor)
+        }
+    }
+}
+
+/// Convert whole DOT to 18-decimal fixed-point units. Thin wrapper over
+/// FixedPoint::DOT.to_fixed.
+pub fn to_fixed(whole_dot: u128) -> MathResult<u128> {
+    FixedPoint::DOT.to_fixed(whole_dot)
+}
+
+/// Convert 18-decimal fixed-point units to whole DOT, truncating. Thin wrapper
+/// over FixedPoint::DOT.from_fixed.
+pub fn from_fixed(value: u128) -> u128 {
+    FixedPoint::DOT.from_fixed(value)
+}
+
+// ---------------------------------------------------------------------------
+// mul_fraction
+// ---------------------------------------------------------------------------
+
+/// Computes ⌊value × numerator / denominator⌋ without intermediate overflow.
+///
+/// The product is formed as a 256-bit (hi, lo) pair, so the call succeeds
+/// whenever the final quotient fits in u128 — `value * numerator` on its own
+/// may exceed u128::MAX. This replaces the `x.checked_mul(n)?.checked_div(d)?`
+/// chains used for percentage splits, which fail for huge values even when
+/// the result is representable.
+///
+/// Returns MathError::DivisionByZero if denominator is 0, and
+/// MathError::Overflow if the quotient does not fit in u128.
+pub fn mul_fraction(value: u128, numerator: u128, denominator: u128) -> MathResult<u128> {
+    if denominator == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    let (hi, lo) = widening_mul(value, numerator);
+    if hi == 0 {
+        return Ok(lo / denominator);
+    }
+    // The quotient fits in 128 bits only if the high word is below the divisor.
+    if hi >= denominator {
+        return Err(MathError::Overflow);
+    }
+    Ok(div_wide(hi, lo, denominator))
+}
+
+/// Full 128 × 128 → 256-bit product, returned as (high, low) words.
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const LOW_MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & LOW_MASK);
+    let (b_hi, b_lo) = (b >> 64, b & LOW_MASK);
+
+    let lo_lo = a_lo * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_lo = a_hi * b_lo;
+    let hi_hi = a_hi * b_hi;
+
+    // Sum of three values below 2^64 each — cannot overflow u128.
+    let mid = (lo_lo >> 64) + (lo_hi & LOW_MASK) + (hi_lo & LOW_MASK);
+    let low = (lo_lo & LOW_MASK) | (mid << 64);
+    let high = hi_hi + (lo_hi >> 64) + (hi_lo >> 64) + (mid >> 64);
+    (high, low)
+}
+
+/// Divides the 256-bit value (hi, lo) by `d` using binary long division.
+/// Caller guarantees hi < d, so the quotient fits in u128.
+fn div_wide(hi: u128, lo: u128, d: u128) -> u128 {
+    let mut remainder = hi;
+    let mut quotient: u128 = 0;
+    for bit in (0..128).rev() {
+        // remainder < d, so 2 × remainder + 1 < 2d: at most one subtraction
+        // is needed, and a carry out of bit 127 means it is certainly ≥ d.
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1;
+        }
+    }
+    quotient
+}
+
+/// Narrow a u128 to u32, saturating at u32::MAX instead of truncating.
+/// For reported BPS figures, where a wrapped value would read as a small
+/// return but a pinned maximum is plainly "off the scale".
+pub fn saturating_u32(value: u128) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
 // ---------------------------------------------------------------------------
 // compound
 // ---------------------------------------------------------------------------
@@ -78,6 +247,10 @@ pub type MathResult<T> = Result<T, MathError>;
 /// Worst case per step: amount × numerator_factor.
 /// For 1B DOT principal (1e27 units) and numerator_factor ≈ 3_651_000 (365 periods,
 /// 1000 bps), intermediate value ≈ 3.65e33. u128 max ≈ 1.7e38. Safe.
+///
+/// periods == 1 takes a single multiply-divide instead of the step loop, with
+/// the same rounding and errors; it runs after the zero-principal and
+/// zero-rate early returns so those never reach the multiply.
 pub fn compound(principal: u128, rate_bps: u32, periods: u32) -> MathResult<u128> {
     if principal == 0 {
         return Ok(0);
@@ -85,6 +258,24 @@ pub fn compound(principal: u128, rate_bps: u32, periods: u32) -> MathResult<u128
     if rate_bps == 0 || periods == 0 {
         return Ok(principal);
     }
+    if periods == 1 {
+        return principal
+            .checked_mul(BPS_DENOMINATOR + rate_bps as u128)
+            .ok_or(MathError::Overflow)
+            .map(|scaled| scaled / BPS_DENOMINATOR);
+    }
+    compound_steps(principal, rate_bps, periods, periods)
+}
+
+/// The first `steps` of compound(principal, rate_bps, periods): each step
+/// earns rate_bps ÷ periods, so with steps == periods this is compound().
+pub(crate) fn compound_steps(principal: u128, rate_bps: u32, periods: u32, steps: u32) -> MathResult<u128> {
+    if principal == 0 {
+        return Ok(0);
+    }
+    if rate_bps == 0 || periods == 0 {
+        return Ok(principal);
+    }
 
     // numerator_factor = BPS_DENOMINATOR * periods + rate_bps
     // denominator_factor = BPS_DENOMINATOR * periods
@@ -99,7 +290,7 @@ pub fn compound(principal: u128, rate_bps: u32, periods: u32) -> MathResult<u128
 
     let mut amount = principal;
 
-    for _ in 0..periods {
+    for _ in 0..steps {
         amount = amount
             .checked_mul(numerator_factor)
             .ok_or(MathError::Overflow)?
@@ -110,6 +301,354 @@ pub fn compound(principal: u128, rate_bps: u32, periods: u32) -> MathResult<u128
     Ok(amount)
 }
 
+// ---------------------------------------------------------------------------
+// simple_interest
+// ---------------------------------------------------------------------------
+
+/// compound() without reinvestment: each of the `periods` steps pays
+/// rate_bps ÷ periods on the original principal only.
+///
+///   A = P + n × ⌊P × r ÷ (BPS_DENOMINATOR × n)⌋
+///
+/// Interest is floored per period, as compound() floors each step, so the
+/// result never exceeds compound() for the same arguments.
+///
+/// Returns MathError::Overflow if an intermediate value exceeds u128.
+pub fn simple_interest(principal: u128, rate_bps: u32, periods: u32) -> MathResult<u128> {
+    if principal == 0 {
+        return Ok(0);
+    }
+    if rate_bps == 0 || periods == 0 {
+        return Ok(principal);
+    }
+
+    let denominator = BPS_DENOMINATOR
+        .checked_mul(periods as u128)
+        .ok_or(MathError::Overflow)?;
+    let interest_per_period = mul_fraction(principal, rate_bps as u128, denominator)?;
+
+    interest_per_period
+        .checked_mul(periods as u128)
+        .and_then(|interest| interest.checked_add(principal))
+        .ok_or(MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// compound_with_fraction
+// ---------------------------------------------------------------------------
+
+/// compound() over `whole_periods`, extended by a fractional final interval.
+///
+/// `fractional_bps` is the length of the trailing partial period as a share
+/// of one period (5_000 = half a period), so a 365.5-day window is
+/// `whole_periods = 365, fractional_bps = 5_000`. The partial period earns
+/// simple interest at the same per-period rate compound() uses:
+///   interest = amount × (rate_bps × fractional_bps ÷ BPS_DENOMINATOR)
+///                     ÷ (BPS_DENOMINATOR × whole_periods)
+///
+/// With `fractional_bps == 0` the result equals compound() exactly.
+///
+/// Returns MathError::InvalidInput if fractional_bps > BPS_DENOMINATOR, or if
+/// a fraction is given with zero whole periods (there is no period length to
+/// take a fraction of).
+pub fn compound_with_fraction(
+    principal: u128,
+    rate_bps: u32,
+    whole_periods: u32,
+    fractional_bps: u32,
+) -> MathResult<u128> {
+    if fractional_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    if fractional_bps > 0 && whole_periods == 0 {
+        return Err(MathError::InvalidInput);
+    }
+
+    let amount = compound(principal, rate_bps, whole_periods)?;
+    if fractional_bps == 0 {
+        return Ok(amount);
+    }
+
+    let partial_rate = (rate_bps as u128)
+        .checked_mul(fractional_bps as u128)
+        .ok_or(MathError::Overflow)?;
+    let denominator = BPS_DENOMINATOR
+        .checked_mul(BPS_DENOMINATOR)
+        .and_then(|d| d.checked_mul(whole_periods as u128))
+        .ok_or(MathError::Overflow)?;
+    let interest = mul_fraction(amount, partial_rate, denominator)?;
+
+    amount.checked_add(interest).ok_or(MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// compound_with_step_fee
+// ---------------------------------------------------------------------------
+
+/// Compound interest where a fee is charged at every compounding event.
+///
+/// Same schedule as compound(), but each step deducts `fee_bps` of the
+/// current balance from that step's interest:
+///   interest = amount × rate_bps ÷ (BPS_DENOMINATOR × periods)
+///   charge   = amount × fee_bps ÷ BPS_DENOMINATOR
+///   amount  += interest - min(charge, interest)
+///
+/// This models a harvest/compound cost that is paid each time the position
+/// compounds, so the more often a destination compounds the more it pays. The
+/// charge is capped at the step's interest — a harvest cannot cost more than
+/// it collects, so the balance never falls below principal.
+///
+/// With `fee_bps == 0` the result equals compound() exactly.
+pub fn compound_with_step_fee(
+    principal: u128,
+    rate_bps: u32,
+    periods: u32,
+    fee_bps: u32,
+) -> MathResult<u128> {
+    if fee_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    if principal == 0 {
+        return Ok(0);
+    }
+    if rate_bps == 0 || periods == 0 {
+        return Ok(principal);
+    }
+
+    let denominator_factor = BPS_DENOMINATOR
+        .checked_mul(periods as u128)
+        .ok_or(MathError::Overflow)?;
+
+    let mut amount = principal;
+
+    for _ in 0..periods {
+        let interest = amount
+            .checked_mul(rate_bps as u128)
+            .ok_or(MathError::Overflow)?
+            .checked_div(denominator_factor)
+            .ok_or(MathError::DivisionByZero)?;
+        let charge = amount
+            .checked_mul(fee_bps as u128)
+            .ok_or(MathError::Overflow)?
+            .checked_div(BPS_DENOMINATOR)
+            .ok_or(MathError::DivisionByZero)?;
+        amount = amount
+            .checked_add(interest.saturating_sub(charge))
+            .ok_or(MathError::Overflow)?;
+    }
+
+    Ok(amount)
+}
+
+// ---------------------------------------------------------------------------
+// optimal_compounding_frequency
+// ---------------------------------------------------------------------------
+
+/// Upper bound on max_periods for optimal_compounding_frequency: daily
+/// compounding over a year. Each candidate count runs its own compounding
+/// loop, so the cap bounds the scan's worst-case cost.
+pub const MAX_COMPOUNDING_PERIODS: u32 = 365;
+
+/// The period count in 1..=max_periods that maximises compound_with_step_fee()
+/// over one window, for a fee of `per_compound_fee_bps` on every compound.
+///
+/// More frequent compounding earns interest on interest but pays the fee
+/// more often, so with any fee the best count is interior: roughly
+/// apy ÷ √(2 × fee) for small fees. Counts are scored on a 1M DOT reference
+/// principal so flooring does not decide between neighbours; ties go to the
+/// fewer compounds. With a zero fee the answer is max_periods.
+///
+/// The scan stops at the first count whose per-step interest no longer
+/// covers the fee — from there on every step is a wash — so its cost is
+/// O(min(max_periods, apy ÷ fee)²) steps, bounded by MAX_COMPOUNDING_PERIODS.
+///
+/// Returns MathError::InvalidInput if max_periods is 0 or above
+/// MAX_COMPOUNDING_PERIODS, or per_compound_fee_bps > BPS_DENOMINATOR.
+pub fn optimal_compounding_frequency(
+    apy_bps: u32,
+    per_compound_fee_bps: u32,
+    max_periods: u32,
+) -> MathResult<u32> {
+    if max_periods == 0
+        || max_periods > MAX_COMPOUNDING_PERIODS
+        || per_compound_fee_bps as u128 > BPS_DENOMINATOR
+    {
+        return Err(MathError::InvalidInput);
+    }
+    if per_compound_fee_bps == 0 {
+        return Ok(max_periods);
+    }
+
+    let principal = 1_000_000 * PRECISION;
+    let mut best_periods = 1;
+    let mut best_amount = compound_with_step_fee(principal, apy_bps, 1, per_compound_fee_bps)?;
+    for periods in 2..=max_periods {
+        let amount = compound_with_step_fee(principal, apy_bps, periods, per_compound_fee_bps)?;
+        if amount == principal {
+            break;
+        }
+        if amount > best_amount {
+            (best_periods, best_amount) = (periods, amount);
+        }
+    }
+    Ok(best_periods)
+}
+
+// ---------------------------------------------------------------------------
+// compound_schedule
+// ---------------------------------------------------------------------------
+
+/// The balance after every step of compound(), for charting.
+///
+/// Element i is the balance after period i + 1, so the schedule has
+/// `periods` entries and its last equals compound(principal, rate_bps,
+/// periods). The principal itself is not included; periods == 0 returns an
+/// empty schedule.
+pub fn compound_schedule(principal: u128, rate_bps: u32, periods: u32) -> MathResult<Vec<u128>> {
+    net_schedule(principal, rate_bps, 0, periods)
+}
+
+// ---------------------------------------------------------------------------
+// net_schedule
+// ---------------------------------------------------------------------------
+
+/// compound_schedule() net of a fee on each period's yield.
+///
+/// Each step earns the same interest compound() would on the current
+/// balance, then keeps fee_adjusted_yield(interest, fee_bps) of it:
+///   interest = amount × rate_bps ÷ (BPS_DENOMINATOR × periods)
+///   amount  += interest - interest × fee_bps ÷ BPS_DENOMINATOR
+///
+/// The fee is charged on yield only, so the curve never falls below
+/// principal. With `fee_bps == 0` this equals compound_schedule().
+///
+/// Returns MathError::InvalidInput if fee_bps > BPS_DENOMINATOR.
+pub fn net_schedule(principal: u128, rate_bps: u32, fee_bps: u32, periods: u32) -> MathResult<Vec<u128>> {
+    if fee_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    let denominator_factor = BPS_DENOMINATOR
+        .checked_mul(periods as u128)
+        .ok_or(MathError::Overflow)?;
+
+    let mut schedule = Vec::with_capacity(periods as usize);
+    let mut amount = principal;
+    for _ in 0..periods {
+        let interest = amount
+            .checked_mul(rate_bps as u128)
+            .ok_or(MathError::Overflow)?
+            .checked_div(denominator_factor)
+            .ok_or(MathError::DivisionByZero)?;
+        amount = amount
+            .checked_add(fee_adjusted_yield(interest, fee_bps)?)
+            .ok_or(MathError::Overflow)?;
+        schedule.push(amount);
+    }
+    Ok(schedule)
+}
+
+// ---------------------------------------------------------------------------
+// cross_compound
+// ---------------------------------------------------------------------------
+
+/// Compound at one rate, then reinvest the whole balance at another.
+///
+/// Both phases use compound()'s period length over the full window — each
+/// step earns its rate ÷ total_periods — so the position earns
+///   (1 + primary/n)^s × (1 + secondary/n)^(n - s)
+/// with n = total_periods and s = min(switch_period, n). Models moving a
+/// position's accumulated balance from one destination to the other part-way
+/// through the window. switch_period == 0 equals compound() at the secondary
+/// rate exactly, and switch_period ≥ total_periods equals it at the primary.
+///
+/// Returns the same errors as compound().
+pub fn cross_compound(
+    principal: u128,
+    primary_rate_bps: u32,
+    secondary_rate_bps: u32,
+    switch_period: u32,
+    total_periods: u32,
+) -> MathResult<u128> {
+    let switch_period = switch_period.min(total_periods);
+    let at_switch = compound_steps(principal, primary_rate_bps, total_periods, switch_period)?;
+    compound_steps(at_switch, secondary_rate_bps, total_periods, total_periods - switch_period)
+}
+
+// ---------------------------------------------------------------------------
+// doubling_periods
+// ---------------------------------------------------------------------------
+
+/// Number of compounding periods until a balance doubles at an annual rate of
+/// `rate_bps` compounded `periods_per_year` times a year.
+///
+/// Uses compound()'s own per-step factor — each step earns rate_bps ÷
+/// periods_per_year — on a 1 DOT balance, so the count agrees with compound()
+/// rather than the rule-of-72 estimate. The count is binary-searched, raising
+/// the factor to each candidate power by squaring, so the cost is O(log² n)
+/// multiplications rather than one per period. Squaring floors per
+/// multiplication rather than per step, so a balance that lands within
+/// rounding of 2 DOT may be counted one period off compound_steps().
+///
+/// A zero rate never doubles and returns u32::MAX, as does a rate too small
+/// to move the balance at PRECISION resolution or a count beyond u32.
+///
+/// Returns MathError::InvalidInput if periods_per_year == 0.
+pub fn doubling_periods(rate_bps: u32, periods_per_year: u32) -> MathResult<u32> {
+    if periods_per_year == 0 {
+        return Err(MathError::InvalidInput);
+    }
+    if rate_bps == 0 {
+        return Ok(u32::MAX);
+    }
+
+    let denominator_factor = BPS_DENOMINATOR
+        .checked_mul(periods_per_year as u128)
+        .ok_or(MathError::Overflow)?;
+    let numerator_factor = denominator_factor
+        .checked_add(rate_bps as u128)
+        .ok_or(MathError::Overflow)?;
+    let factor = mul_fraction(PRECISION, numerator_factor, denominator_factor)?;
+    if factor == PRECISION {
+        return Ok(u32::MAX);
+    }
+
+    // Double the bound until it doubles the balance, then bisect between the
+    // last two bounds: growth(low) < target <= growth(high) throughout.
+    let target = 2 * PRECISION;
+    let mut high = 1u32;
+    while fixed_pow(factor, high)? < target {
+        if high == u32::MAX {
+            return Ok(u32::MAX);
+        }
+        high = high.saturating_mul(2);
+    }
+    let mut low = high / 2;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if fixed_pow(factor, mid)? >= target {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Ok(high)
+}
+
+/// `base` raised to `exp`, both PRECISION-scaled, by repeated squaring.
+fn fixed_pow(mut base: u128, mut exp: u32) -> MathResult<u128> {
+    let mut result = PRECISION;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_fraction(result, base, PRECISION)?;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = mul_fraction(base, base, PRECISION)?;
+        }
+    }
+    Ok(result)
+}
+
 // ---------------------------------------------------------------------------
 // annualize
 // ---------------------------------------------------------------------------
@@ -144,6 +683,75 @@ pub fn annualize(rate_bps: u32, period_seconds: u64) -> MathResult<u32> {
     Ok(annual as u32)
 }
 
+// ---------------------------------------------------------------------------
+// annualize_fixed
+// ---------------------------------------------------------------------------
+
+/// annualize() at PRECISION resolution instead of whole BPS.
+///
+/// `rate_fixed` is the rate over `period_seconds` as a PRECISION-scaled
+/// fraction (PRECISION = 100%), and so is the result:
+///   annual_fixed = rate_fixed × SECONDS_PER_YEAR ÷ period_seconds
+///
+/// A per-block rate on a 6-second window is scaled by about 5.26M, so a true
+/// rate below 1 BPS per block — which annualize() can only see as 0 — can
+/// still be tens of percent a year. Multiply by BPS_DENOMINATOR ÷ PRECISION
+/// to read the result in BPS.
+///
+/// Returns MathError::DivisionByZero if period_seconds is 0, and
+/// MathError::Overflow if the annual figure exceeds u128::MAX.
+pub fn annualize_fixed(rate_fixed: u128, period_seconds: u64) -> MathResult<u128> {
+    if period_seconds == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    mul_fraction(rate_fixed, SECONDS_PER_YEAR, period_seconds as u128)
+}
+
+// ---------------------------------------------------------------------------
+// realized_apy_bps
+// ---------------------------------------------------------------------------
+
+/// The annual rate a position actually earned, from what went in, what came
+/// out and how long it was held, for backtesting.
+///
+/// The holding-period return is annualised exactly as annualize() does —
+/// simply, without compounding — but at PRECISION resolution via
+/// annualize_fixed(), so short holds keep their sub-BPS returns:
+///   apy_bps = (withdrawn - deposited) ÷ deposited
+///             × SECONDS_PER_YEAR ÷ holding_seconds × BPS_DENOMINATOR
+///
+/// 10% over a year is 1_000 BPS; 10% over six months is 2_000.
+///
+/// Returns MathError::DivisionByZero if deposited or holding_seconds is 0,
+/// MathError::Underflow if withdrawn < deposited (use realized_apy_signed_bps
+/// for losses), and MathError::Overflow if the rate exceeds u32::MAX BPS.
+pub fn realized_apy_bps(deposited: u128, withdrawn: u128, holding_seconds: u64) -> MathResult<u32> {
+    let gain = withdrawn.checked_sub(deposited).ok_or(MathError::Underflow)?;
+    let apy_bps = annualized_return_bps(deposited, gain, holding_seconds)?;
+    u32::try_from(apy_bps).map_err(|_| MathError::Overflow)
+}
+
+/// realized_apy_bps() for a position that may have lost value: negative for
+/// withdrawn < deposited.
+///
+/// Returns MathError::DivisionByZero if deposited or holding_seconds is 0, and
+/// MathError::Overflow if the rate does not fit an i64.
+pub fn realized_apy_signed_bps(deposited: u128, withdrawn: u128, holding_seconds: u64) -> MathResult<i64> {
+    let change = annualized_return_bps(deposited, withdrawn.abs_diff(deposited), holding_seconds)?;
+    let change = i64::try_from(change).map_err(|_| MathError::Overflow)?;
+    Ok(if withdrawn < deposited { -change } else { change })
+}
+
+/// |change| ÷ deposited, annualised over holding_seconds, in BPS.
+fn annualized_return_bps(deposited: u128, change: u128, holding_seconds: u64) -> MathResult<u128> {
+    if deposited == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    let period_return = mul_fraction(change, PRECISION, deposited)?;
+    let annual = annualize_fixed(period_return, holding_seconds)?;
+    mul_fraction(annual, BPS_DENOMINATOR, PRECISION)
+}
+
 // ---------------------------------------------------------------------------
 // fee_adjusted_yield
 // ---------------------------------------------------------------------------
@@ -177,49 +785,290 @@ pub fn fee_adjusted_yield(gross_yield: u128, fee_bps: u32) -> MathResult<u128> {
 }
 
 // ---------------------------------------------------------------------------
-// weighted_average
+// gross_from_net_yield
 // ---------------------------------------------------------------------------
 
-/// Capital-weighted average across multiple yield positions.
-///
-/// Used to compute the blended APY of a split allocation — e.g. if 60% of
-/// capital goes to HydraDX at 1200 BPS and 40% to Interlay at 900 BPS, the
-/// weighted average APY is:
-///   (1200 × 60 + 900 × 40) ÷ (60 + 40) = 1080 BPS
+/// Reconstruct the gross yield implied by a net figure and a known fee — the
+/// inverse of fee_adjusted_yield.
 ///
-/// `values` and `weights` must be the same length and non-empty.
-/// All weights must be non-zero or the function returns DivisionByZero.
+/// Formula: gross_yield = net_yield × BPS_DENOMINATOR ÷ (BPS_DENOMINATOR - fee_bps)
 ///
-/// Returns MathError::InvalidInput if slice lengths differ or either is empty.
-pub fn weighted_average(values: &[u128], weights: &[u128]) -> MathResult<u128> {
-    if values.is_empty() || values.len() != weights.len() {
+/// Both directions round down, so a gross figure sent through
+/// fee_adjusted_yield and back is recovered to within
+/// ⌈BPS_DENOMINATOR / (BPS_DENOMINATOR - fee_bps)⌉ wei. A 100% fee leaves no
+/// net yield to invert and returns MathError::DivisionByZero; a fee above
+/// 100% returns MathError::InvalidInput.
+pub fn gross_from_net_yield(net_yield: u128, fee_bps: u32) -> MathResult<u128> {
+    let fee_bps = fee_bps as u128;
+    if fee_bps > BPS_DENOMINATOR {
         return Err(MathError::InvalidInput);
     }
+    mul_fraction(net_yield, BPS_DENOMINATOR, BPS_DENOMINATOR - fee_bps)
+}
 
-    let mut weighted_sum: u128 = 0;
-    let mut total_weight: u128 = 0;
+// ---------------------------------------------------------------------------
+// annual_fee_drag_bps
+// ---------------------------------------------------------------------------
 
-    for (v, w) in values.iter().zip(weights.iter()) {
-        let product = v.checked_mul(*w).ok_or(MathError::Overflow)?;
-        weighted_sum = weighted_sum
-            .checked_add(product)
-            .ok_or(MathError::Overflow)?;
-        total_weight = total_weight
-            .checked_add(*w)
-            .ok_or(MathError::Overflow)?;
-    }
+/// What the fee alone costs per year, in basis points of principal.
+///
+/// Compounds a reference principal at `gross_apy_bps` over `periods` (the same
+/// one-year schedule compound() models), deducts `fee_bps` of the resulting
+/// yield via fee_adjusted_yield, and reports the deducted amount:
+///   drag = (gross_yield - net_yield) × BPS_DENOMINATOR ÷ principal
+///
+/// The drag is measured against the compounded gross rate, not the nominal
+/// APY, so a 100% fee gives a drag equal to the whole effective gross yield.
+/// A zero fee, zero rate or zero periods gives 0.
+///
+/// Returns MathError::InvalidInput if fee_bps > BPS_DENOMINATOR, and
+/// MathError::Overflow if the drag does not fit in u32.
+pub fn annual_fee_drag_bps(gross_apy_bps: u32, fee_bps: u32, periods: u32) -> MathResult<u32> {
+    // Fixed reference principal: the drag is a rate, so any size works, and
+    // 1 DOT keeps full 18-decimal precision through the floor divisions.
+    let principal = PRECISION;
 
-    if total_weight == 0 {
-        return Err(MathError::DivisionByZero);
-    }
+    let gross_yield = compound(principal, gross_apy_bps, periods)?
+        .checked_sub(principal)
+        .ok_or(MathError::Underflow)?;
+    let net_yield = fee_adjusted_yield(gross_yield, fee_bps)?;
+    let fee_paid = gross_yield.checked_sub(net_yield).ok_or(MathError::Underflow)?;
 
-    weighted_sum
-        .checked_div(total_weight)
-        .ok_or(MathError::DivisionByZero)
+    let drag = mul_fraction(fee_paid, BPS_DENOMINATOR, principal)?;
+    u32::try_from(drag).map_err(|_| MathError::Overflow)
 }
 
 // ---------------------------------------------------------------------------
-// optimal_split
+// gas_adjusted_apy_bps
+// ---------------------------------------------------------------------------
+
+/// Net APY less a destination's gas and XCM costs, spread over the position.
+///
+/// `gas_cost_dot` is the cost amortised over one year (18 decimal
+/// fixed-point), so as a rate it is gas_cost_dot ÷ principal; it is taken in
+/// whole BPS, floored, and subtracted with a floor at 0:
+///   gas_adjusted = net_apy_bps - gas_cost_dot × BPS_DENOMINATOR ÷ principal
+///
+/// A fixed cost weighs inversely to position size: 1 DOT a year is 1 BPS on
+/// 10_000 DOT but 1_000 BPS on 10 DOT.
+///
+/// Returns MathError::DivisionByZero if principal is 0.
+pub fn gas_adjusted_apy_bps(net_apy_bps: u32, gas_cost_dot: u128, principal: u128) -> MathResult<u32> {
+    if principal == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    // A cost past u128 BPS wipes out any APY, so a saturated rate is exact
+    // enough.
+    let gas_bps = mul_fraction(gas_cost_dot, BPS_DENOMINATOR, principal).unwrap_or(u128::MAX);
+    Ok(u32::try_from(gas_bps).map_or(0, |gas_bps| net_apy_bps.saturating_sub(gas_bps)))
+}
+
+// ---------------------------------------------------------------------------
+// net_yield_two_tier
+// ---------------------------------------------------------------------------
+
+/// Net yield under a two-tier fee schedule: a flat protocol fee on all yield,
+/// then a performance fee only on profit above a hurdle.
+///
+/// Formula:
+///   after_protocol = fee_adjusted_yield(gross_yield, protocol_fee_bps)
+///   excess         = max(after_protocol - hurdle_yield, 0)
+///   net_yield      = after_protocol - excess × perf_fee_bps ÷ BPS_DENOMINATOR
+///
+/// The hurdle is compared against yield after the protocol fee, in the same
+/// fixed-point DOT units as gross_yield. Yield at or below the hurdle pays no
+/// performance fee.
+///
+/// Each fee must be ≤ BPS_DENOMINATOR; a fee above 100% returns
+/// MathError::InvalidInput, as in fee_adjusted_yield.
+pub fn net_yield_two_tier(
+    gross_yield: u128,
+    protocol_fee_bps: u32,
+    perf_fee_bps: u32,
+    hurdle_yield: u128,
+) -> MathResult<u128> {
+    if perf_fee_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    let after_protocol = fee_adjusted_yield(gross_yield, protocol_fee_bps)?;
+
+    let excess = after_protocol.saturating_sub(hurdle_yield);
+    let excess_after_perf = fee_adjusted_yield(excess, perf_fee_bps)?;
+
+    after_protocol
+        .checked_sub(excess)
+        .ok_or(MathError::Underflow)?
+        .checked_add(excess_after_perf)
+        .ok_or(MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// checked_sum
+// ---------------------------------------------------------------------------
+
+/// Sum a slice of u128 values, returning MathError::Overflow rather than a
+/// wrapped total. An empty slice sums to 0.
+pub fn checked_sum(values: &[u128]) -> MathResult<u128> {
+    values
+        .iter()
+        .try_fold(0u128, |acc, v| acc.checked_add(*v))
+        .ok_or(MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// weighted_average
+// ---------------------------------------------------------------------------
+
+/// Capital-weighted average across multiple yield positions.
+///
+/// Used to compute the blended APY of a split allocation — e.g. if 60% of
+/// capital goes to HydraDX at 1200 BPS and 40% to Interlay at 900 BPS, the
+/// weighted average APY is:
+///   (1200 × 60 + 900 × 40) ÷ (60 + 40) = 1080 BPS
+///
+/// `values` and `weights` must be the same length and non-empty.
+/// All weights must be non-zero or the function returns DivisionByZero.
+///
+/// The weighted sum is accumulated in u128. If it outgrows u128 the sum is
+/// redone as a single 256-bit sum. Σ v × w < 2^128 × Σ w, so that sum always
+/// fits once the total weight does, and the average, never above the largest
+/// value, always fits u128. Integer sums are exact in any order, so both
+/// paths return the same value wherever the u128 one succeeds.
+///
+/// Returns MathError::LengthMismatch if slice lengths differ,
+/// MathError::InvalidInput if both are empty, and MathError::Overflow if the
+/// total weight exceeds u128.
+pub fn weighted_average(values: &[u128], weights: &[u128]) -> MathResult<u128> {
+    if values.len() != weights.len() {
+        return Err(MathError::LengthMismatch);
+    }
+    if values.is_empty() {
+        return Err(MathError::InvalidInput);
+    }
+
+    let total_weight = checked_sum(weights)?;
+
+    if total_weight == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+
+    let sequential = values
+        .iter()
+        .zip(weights)
+        .try_fold(0u128, |sum, (v, w)| v.checked_mul(*w)?.checked_add(sum));
+    if let Some(weighted_sum) = sequential {
+        return Ok(weighted_sum / total_weight);
+    }
+
+    let (hi, lo) = values
+        .iter()
+        .zip(weights)
+        .try_fold((0, 0), |sum, (v, w)| wide_add(sum, widening_mul(*v, *w)))
+        .ok_or(MathError::Overflow)?;
+    if hi >= total_weight {
+        return Err(MathError::Overflow);
+    }
+    Ok(div_wide(hi, lo, total_weight))
+}
+
+/// 256-bit addition of (hi, lo) pairs, or None on a carry out of the top.
+fn wide_add(a: (u128, u128), b: (u128, u128)) -> Option<(u128, u128)> {
+    let (lo, carry) = a.1.overflowing_add(b.1);
+    let hi = a.0.checked_add(b.0)?.checked_add(carry as u128)?;
+    Some((hi, lo))
+}
+
+// ---------------------------------------------------------------------------
+// portfolio_apy_bps
+// ---------------------------------------------------------------------------
+
+/// Blended APY across several independent positions, weighted by principal:
+///   Σ apy_i × principal_i ÷ Σ principal_i
+///
+/// weighted_average() over the APYs, so very large principals fall back to
+/// its wide sums rather than overflowing. The blend never exceeds the
+/// largest APY, so the result always fits u32.
+///
+/// Returns MathError::LengthMismatch if the slices differ in length,
+/// MathError::InvalidInput if both are empty, and MathError::DivisionByZero
+/// if every principal is zero.
+pub fn portfolio_apy_bps(principals: &[u128], apys_bps: &[u32]) -> MathResult<u32> {
+    if principals.len() != apys_bps.len() {
+        return Err(MathError::LengthMismatch);
+    }
+    if principals.is_empty() {
+        return Err(MathError::InvalidInput);
+    }
+    let apys: Vec<u128> = apys_bps.iter().map(|&apy| apy as u128).collect();
+    let blended = weighted_average(&apys, principals)?;
+    u32::try_from(blended).map_err(|_| MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// percentile
+// ---------------------------------------------------------------------------
+
+/// Value at the `p_bps` percentile of `values` by the nearest-rank method.
+///
+/// The sample is sorted ascending and the element at rank
+///   rank = max(1, ⌈p_bps × n ÷ BPS_DENOMINATOR⌉)
+/// is returned, so 0 gives the minimum, 5_000 the (lower) median and 10_000
+/// the maximum. The result is always a member of the sample — no
+/// interpolation.
+///
+/// Returns MathError::InvalidInput for an empty slice or p_bps > BPS_DENOMINATOR.
+pub fn percentile(values: &[u128], p_bps: u32) -> MathResult<u128> {
+    if values.is_empty() || p_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+
+    let mut sorted = values.to_vec();
+    sort_u128(&mut sorted);
+
+    let n = sorted.len() as u128;
+    let rank = (p_bps as u128)
+        .checked_mul(n)
+        .ok_or(MathError::Overflow)?
+        .div_ceil(BPS_DENOMINATOR)
+        .max(1);
+    Ok(sorted[(rank - 1) as usize])
+}
+
+/// In-place ascending heapsort.
+///
+/// Deterministic, O(n log n) in the worst case and allocation-free, so the
+/// cost of a statistic over a sample is bounded by its length alone and does
+/// not depend on std's sort implementation.
+fn sort_u128(values: &mut [u128]) {
+    // Restore the max-heap property for the subtree at `root` within `len`.
+    fn sift_down(values: &mut [u128], mut root: usize, len: usize) {
+        loop {
+            let mut largest = root;
+            for child in [2 * root + 1, 2 * root + 2] {
+                if child < len && values[child] > values[largest] {
+                    largest = child;
+                }
+            }
+            if largest == root {
+                return;
+            }
+            values.swap(root, largest);
+            root = largest;
+        }
+    }
+
+    let len = values.len();
+    for root in (0..len / 2).rev() {
+        sift_down(values, root, len);
+    }
+    for end in (1..len).rev() {
+        values.swap(0, end);
+        sift_down(values, 0, end);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// optimal_split
 // ---------------------------------------------------------------------------
 
 /// Compute the optimal capital allocation split between two yield destinations.
@@ -246,31 +1095,76 @@ pub fn optimal_split(
     risk_a: u32,
     risk_b: u32,
 ) -> MathResult<(u64, u64)> {
+    optimal_split_with_tiebreak(yield_a_bps, yield_b_bps, risk_a, risk_b, None)
+}
+
+/// optimal_split() with an opt-in deterministic tie-break.
+///
+/// With `tiebreak_seed` set, an exact tie (equal risk-adjusted yields,
+/// including both zero) no longer splits 50/50: the whole allocation goes to
+/// the side picked by deterministic_tiebreak(seed) — `true` selects a. Seeding
+/// from a per-position fingerprint spreads tied positions across both
+/// destinations while each individual call stays reproducible.
+///
+/// With `None`, or when there is no exact tie, the result equals optimal_split().
+pub fn optimal_split_with_tiebreak(
+    yield_a_bps: u32,
+    yield_b_bps: u32,
+    risk_a: u32,
+    risk_b: u32,
+    tiebreak_seed: Option<[u8; 32]>,
+) -> MathResult<(u64, u64)> {
+    let (adj_a, adj_b) = risk_adjusted_yields(yield_a_bps, yield_b_bps, risk_a, risk_b)?;
+
+    if let Some(seed) = tiebreak_seed.filter(|_| adj_a == adj_b) {
+        return Ok(if deterministic_tiebreak(seed) { (100, 0) } else { (0, 100) });
+    }
+
+    proportional_split(adj_a, adj_b)
+}
+
+/// The two risk-adjusted yields optimal_split() allocates proportionally to,
+/// as (adj_a, adj_b) — the figures that explain its decision:
+///   risk_adjusted = yield × (MAX_RISK_SCORE - risk) ÷ MAX_RISK_SCORE
+///
+/// Returns MathError::InvalidInput if either risk > MAX_RISK_SCORE.
+pub fn risk_adjusted_yields(
+    yield_a_bps: u32,
+    yield_b_bps: u32,
+    risk_a: u32,
+    risk_b: u32,
+) -> MathResult<(u128, u128)> {
     if risk_a as u128 > MAX_RISK_SCORE || risk_b as u128 > MAX_RISK_SCORE {
         return Err(MathError::InvalidInput);
     }
+    Ok((risk_adjusted_yield(yield_a_bps, risk_a)?, risk_adjusted_yield(yield_b_bps, risk_b)?))
+}
 
-    // risk_adjusted = yield × (MAX_RISK - risk) / MAX_RISK
-    let adj_a = (yield_a_bps as u128)
-        .checked_mul(
-            MAX_RISK_SCORE
-                .checked_sub(risk_a as u128)
-                .ok_or(MathError::Underflow)?,
-        )
-        .ok_or(MathError::Overflow)?
-        .checked_div(MAX_RISK_SCORE)
-        .ok_or(MathError::DivisionByZero)?;
-
-    let adj_b = (yield_b_bps as u128)
-        .checked_mul(
-            MAX_RISK_SCORE
-                .checked_sub(risk_b as u128)
-                .ok_or(MathError::Underflow)?,
-        )
-        .ok_or(MathError::Overflow)?
-        .checked_div(MAX_RISK_SCORE)
-        .ok_or(MathError::DivisionByZero)?;
+/// optimal_split() on risk factors precomputed with risk_factor_bps().
+///
+/// Batch callers optimizing many positions against the same two destinations
+/// can compute each destination's factor once and reuse it:
+///   risk_adjusted = yield × factor_bps ÷ BPS_DENOMINATOR
+///
+/// For factors from risk_factor_bps(risk_a) and risk_factor_bps(risk_b) the
+/// result equals optimal_split(yield_a_bps, yield_b_bps, risk_a, risk_b).
+///
+/// Returns MathError::InvalidInput if either factor > BPS_DENOMINATOR.
+pub fn optimal_split_prefactored(
+    yield_a_bps: u32,
+    yield_b_bps: u32,
+    factor_a_bps: u32,
+    factor_b_bps: u32,
+) -> MathResult<(u64, u64)> {
+    proportional_split(
+        prefactored_yield(yield_a_bps, factor_a_bps)?,
+        prefactored_yield(yield_b_bps, factor_b_bps)?,
+    )
+}
 
+/// pct_a = adj_a × 100 ÷ (adj_a + adj_b), with pct_b the remainder and a
+/// 50/50 split when both are zero.
+fn proportional_split(adj_a: u128, adj_b: u128) -> MathResult<(u64, u64)> {
     let total = adj_a.checked_add(adj_b).ok_or(MathError::Overflow)?;
 
     // Edge case: both destinations have zero risk-adjusted yield — split 50/50
@@ -291,4 +1185,526 @@ pub fn optimal_split(
         .ok_or(MathError::Underflow)?;
 
     Ok((pct_a, pct_b))
-}
\ No newline at end of file
+}
+
+/// Derive a reproducible pseudo-random side from a 32-byte seed.
+///
+/// Returns the low bit of keccak256(seed). The same seed always yields the
+/// same side; distinct seeds are spread evenly across both.
+pub fn deterministic_tiebreak(seed: [u8; 32]) -> bool {
+    sp_core::keccak_256(&seed)[31] & 1 == 1
+}
+
+/// The mean-variance penalised yield optimal_split allocates on:
+///   risk_adjusted = yield × (MAX_RISK_SCORE - risk) ÷ MAX_RISK_SCORE
+///
+/// Returns MathError::InvalidInput if risk > MAX_RISK_SCORE.
+pub fn risk_adjusted_yield(yield_bps: u32, risk: u32) -> MathResult<u128> {
+    prefactored_yield(yield_bps, risk_factor_bps(risk)?)
+}
+
+/// The share of yield a destination keeps after the risk penalty, in BPS:
+///   factor = (MAX_RISK_SCORE - risk) × BPS_DENOMINATOR ÷ MAX_RISK_SCORE
+///
+/// 10_000 for a riskless destination, 0 at MAX_RISK_SCORE.
+///
+/// Returns MathError::InvalidInput if risk > MAX_RISK_SCORE.
+pub fn risk_factor_bps(risk: u32) -> MathResult<u32> {
+    let kept = MAX_RISK_SCORE
+        .checked_sub(risk as u128)
+        .ok_or(MathError::InvalidInput)?;
+    Ok(mul_fraction(kept, BPS_DENOMINATOR, MAX_RISK_SCORE)? as u32)
+}
+
+/// The extra yield destination A must offer over B's `base_yield_bps` for
+/// their risk-adjusted yields to tie, so optimal_split splits them 50/50.
+///
+/// risk_adjusted_yield floors, so the answer is the smallest A yield whose
+/// risk-adjusted value reaches B's:
+///   yield_a = ⌈risk_adjusted_yield(base, risk_b) × BPS_DENOMINATOR ÷ factor_a⌉
+/// and that yield ties exactly. The premium is yield_a - base_yield_bps,
+/// which is 0 when A is no riskier than B. If no u32 yield can tie — A is at
+/// MAX_RISK_SCORE against a B that still yields — u32::MAX is returned.
+///
+/// Returns MathError::InvalidInput if either risk > MAX_RISK_SCORE.
+pub fn required_yield_premium_bps(base_yield_bps: u32, risk_a: u32, risk_b: u32) -> MathResult<u32> {
+    let factor_a = risk_factor_bps(risk_a)?;
+    let target = risk_adjusted_yield(base_yield_bps, risk_b)?;
+    if target == 0 {
+        return Ok(0);
+    }
+    if factor_a == 0 {
+        return Ok(u32::MAX);
+    }
+
+    let scaled = target.checked_mul(BPS_DENOMINATOR).ok_or(MathError::Overflow)?;
+    let yield_a = scaled.div_ceil(factor_a as u128);
+    Ok(saturating_u32(yield_a.saturating_sub(base_yield_bps as u128)))
+}
+
+/// yield × factor_bps ÷ BPS_DENOMINATOR, rejecting a factor above 100%.
+fn prefactored_yield(yield_bps: u32, factor_bps: u32) -> MathResult<u128> {
+    if factor_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    mul_fraction(yield_bps as u128, factor_bps as u128, BPS_DENOMINATOR)
+}
+
+/// Scale a risk score by a risk-aversion factor before it reaches the split:
+///   scaled = risk × risk_aversion_bps ÷ BPS_DENOMINATOR, capped at MAX_RISK_SCORE
+///
+/// 10_000 leaves the score unchanged (the standard penalty), 0 removes the
+/// penalty so allocation follows raw yield, and values above 10_000 inflate
+/// it — a leg scaled up to MAX_RISK_SCORE earns nothing risk-adjusted.
+///
+/// Returns MathError::InvalidInput if risk > MAX_RISK_SCORE, whatever the
+/// aversion, so a zero factor cannot hide an out-of-range score.
+pub fn scaled_risk(risk: u32, risk_aversion_bps: u32) -> MathResult<u32> {
+    if risk as u128 > MAX_RISK_SCORE {
+        return Err(MathError::InvalidInput);
+    }
+    let scaled = mul_fraction(risk as u128, risk_aversion_bps as u128, BPS_DENOMINATOR)?;
+    Ok(scaled.min(MAX_RISK_SCORE) as u32)
+}
+
+/// Derive a risk score from a destination's liquidity spread:
+///   risk = spread_bps × MAX_RISK_SCORE ÷ MAX_RISK_SPREAD_BPS, capped at MAX_RISK_SCORE
+///
+/// Linear and non-decreasing: a zero spread is zero risk, each basis point of
+/// spread adds 10 points of risk, and a spread of MAX_RISK_SPREAD_BPS or wider
+/// is maximum risk. The result is always a valid optimal_split input.
+pub fn risk_from_spread_bps(spread_bps: u32) -> MathResult<u32> {
+    let risk = mul_fraction(spread_bps as u128, MAX_RISK_SCORE, MAX_RISK_SPREAD_BPS)?;
+    Ok(risk.min(MAX_RISK_SCORE) as u32)
+}
+
+// ---------------------------------------------------------------------------
+// min_variance_split
+// ---------------------------------------------------------------------------
+
+/// Allocation percentages for the two-asset minimum-variance portfolio,
+/// ignoring expected return entirely.
+///
+/// Closed form:
+///   w_a = (var_b - cov_ab) ÷ (var_a + var_b - 2 × cov_ab)
+///   w_b = 1 - w_a
+///
+/// var_a, var_b and cov_ab may be in any unit as long as all three share it.
+/// Only non-negative covariance is representable. When cov_ab exceeds one
+/// variance the unconstrained optimum would short the other asset, so the
+/// whole allocation goes to the lower-variance side instead. A zero
+/// denominator (identical, perfectly correlated assets — including both
+/// variances zero) splits 50/50. pct_b is the remainder, so the pair always
+/// sums to exactly 100.
+///
+/// Returns MathError::InvalidInput if cov_ab is too large for the variances
+/// (var_a + var_b < 2 × cov_ab, impossible for a real covariance).
+pub fn min_variance_split(var_a: u128, var_b: u128, cov_ab: u128) -> MathResult<(u64, u64)> {
+    let twice_cov = cov_ab.checked_mul(2).ok_or(MathError::Overflow)?;
+    let denominator = var_a
+        .checked_add(var_b)
+        .ok_or(MathError::Overflow)?
+        .checked_sub(twice_cov)
+        .ok_or(MathError::InvalidInput)?;
+
+    if denominator == 0 {
+        return Ok((50, 50));
+    }
+    // Long-only corners: the unconstrained weight would fall outside [0, 1].
+    if cov_ab >= var_b {
+        return Ok((0, 100));
+    }
+    if cov_ab >= var_a {
+        return Ok((100, 0));
+    }
+
+    let pct_a = mul_fraction(var_b - cov_ab, 100, denominator)? as u64;
+    let pct_b = 100u64.checked_sub(pct_a).ok_or(MathError::Underflow)?;
+
+    Ok((pct_a, pct_b))
+}
+
+// ---------------------------------------------------------------------------
+// validate_allocation
+// ---------------------------------------------------------------------------
+
+/// Invariant check for an allocation expressed in whole percentages.
+///
+/// Returns Ok(()) iff every element is ≤ 100 and the slice sums to exactly
+/// 100. Callers that leave capital undeployed must include the unallocated
+/// share as its own element.
+///
+/// Returns MathError::InvalidInput otherwise, including for an empty slice.
+pub fn validate_allocation(pcts: &[u64]) -> MathResult<()> {
+    let mut total: u64 = 0;
+    for &pct in pcts {
+        if pct > 100 {
+            return Err(MathError::InvalidInput);
+        }
+        total = total.checked_add(pct).ok_or(MathError::Overflow)?;
+    }
+    if total != 100 {
+        return Err(MathError::InvalidInput);
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// validate_destination
+// ---------------------------------------------------------------------------
+
+/// Sanity check for one destination's (apy, fee, risk) configuration.
+///
+/// Rejects with MathError::InvalidInput:
+///   - a risk score above MAX_RISK_SCORE
+///   - a fee above 100% (BPS_DENOMINATOR)
+///   - a zero APY behind a 100% fee with a nonzero risk score: the leg can
+///     never yield, so pricing risk on it is a configuration error
+///
+/// A 100% fee on a positive APY is allowed: the leg nets zero and
+/// optimal_split routes around it, which callers may want to surface.
+pub fn validate_destination(apy_bps: u32, fee_bps: u32, risk: u32) -> MathResult<()> {
+    if risk as u128 > MAX_RISK_SCORE || fee_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    if apy_bps == 0 && fee_bps as u128 == BPS_DENOMINATOR && risk > 0 {
+        return Err(MathError::InvalidInput);
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// split_principal_n
+// ---------------------------------------------------------------------------
+
+/// Split `principal` across N legs by whole percentages, with no dust.
+///
+/// Each leg gets ⌊principal × pct ÷ 100⌋ and the whole rounding remainder goes
+/// to the leg with the largest percentage (the first such leg on a tie), so
+/// the amounts always sum to exactly `principal`. The remainder is below N
+/// units, which is negligible for the largest leg.
+///
+/// Returns the same errors as validate_allocation for `pcts`.
+pub fn split_principal_n(principal: u128, pcts: &[u64]) -> MathResult<Vec<u128>> {
+    validate_allocation(pcts)?;
+
+    let mut amounts = pcts
+        .iter()
+        .map(|&pct| mul_fraction(principal, pct as u128, 100))
+        .collect::<MathResult<Vec<u128>>>()?;
+
+    // Each floor is at most the exact share, so the floors never exceed
+    // principal.
+    let dust = principal
+        .checked_sub(checked_sum(&amounts)?)
+        .ok_or(MathError::Underflow)?;
+    let largest = (0..pcts.len()).rev().max_by_key(|&i| pcts[i]).unwrap_or(0);
+    amounts[largest] += dust;
+    Ok(amounts)
+}
+
+// ---------------------------------------------------------------------------
+// projected_drift
+// ---------------------------------------------------------------------------
+
+/// The allocation a two-leg split drifts to when each leg compounds at its
+/// own rate for `periods` without rebalancing.
+///
+/// The principal is split with split_principal_n, each leg runs through
+/// compound() independently, and the HydraDX share of the combined balance
+/// is rounded to the nearest whole percentage; Interlay takes the remainder,
+/// so the result always sums to 100. The faster-growing leg's share can only
+/// rise, and equal rates leave the split of any realistic principal
+/// unchanged — rounding absorbs the unit-level flooring of the two legs. A
+/// zero principal has nothing to drift and returns the split as given.
+///
+/// Returns MathError::InvalidInput if hydradx_pct > 100.
+pub fn projected_drift(
+    principal: u128,
+    hydradx_pct: u64,
+    hydradx_rate_bps: u32,
+    interlay_rate_bps: u32,
+    periods: u32,
+) -> MathResult<(u64, u64)> {
+    let interlay_pct = 100u64.checked_sub(hydradx_pct).ok_or(MathError::InvalidInput)?;
+    let legs = split_principal_n(principal, &[hydradx_pct, interlay_pct])?;
+    let hydradx = compound(legs[0], hydradx_rate_bps, periods)?;
+    let interlay = compound(legs[1], interlay_rate_bps, periods)?;
+
+    let total = hydradx.checked_add(interlay).ok_or(MathError::Overflow)?;
+    if total == 0 {
+        return Ok((hydradx_pct, interlay_pct));
+    }
+    // ⌊(⌊200h ÷ T⌋ + 1) ÷ 2⌋ is 100h ÷ T rounded half up.
+    let drifted = (mul_fraction(hydradx, 200, total)? as u64).div_ceil(2);
+    Ok((drifted, 100 - drifted))
+}
+
+// ---------------------------------------------------------------------------
+// nth_root
+// ---------------------------------------------------------------------------
+
+/// Upper bound on Newton iterations in nth_root. The initial guess is at most
+/// 2× the true root, so the slow (linear) phase takes ≈ n × ln 2 steps and the
+/// quadratic phase a handful more — under 100 for any n that reaches the loop.
+/// The bound exists so the per-call cost is fixed regardless of input.
+const NTH_ROOT_MAX_ITERATIONS: u32 = 256;
+
+/// Integer n-th root: returns ⌊x^(1/n)⌋.
+///
+/// This is the single root primitive shared by CAGR, geometric mean and any
+/// fractional-power helper — it operates on raw integers, so callers working
+/// in fixed point are responsible for pre-scaling `x` (e.g. the cube root of
+/// a PRECISION-scaled value needs `x × PRECISION²` to stay in PRECISION units).
+///
+/// Implementation: Newton's method on integers,
+///   y' = ((n - 1) × y + x ÷ y^(n-1)) ÷ n
+/// starting from the power of two just above the root. Every iterate stays
+/// ≥ ⌊root⌋ (AM–GM) and strictly decreases until it reaches it, so the first
+/// non-decreasing step marks the answer. y^(n-1) overflowing u128 simply means
+/// x ÷ y^(n-1) is 0.
+///
+/// Returns MathError::InvalidInput if n is 0. n == 1 returns x unchanged.
+pub fn nth_root(x: u128, n: u32) -> MathResult<u128> {
+    if n == 0 {
+        return Err(MathError::InvalidInput);
+    }
+    if n == 1 || x < 2 {
+        return Ok(x);
+    }
+
+    // x < 2^bits, so the root is below 2^ceil(bits / n). Once n ≥ bits the
+    // root is below 2 and therefore exactly 1.
+    let bits = 128 - x.leading_zeros();
+    if n >= bits {
+        return Ok(1);
+    }
+    let mut y: u128 = 1u128 << bits.div_ceil(n);
+
+    let n_minus_one = (n - 1) as u128;
+    for _ in 0..NTH_ROOT_MAX_ITERATIONS {
+        let quotient = match y.checked_pow(n - 1) {
+            Some(p) => x / p,
+            None => 0,
+        };
+        let next = n_minus_one
+            .checked_mul(y)
+            .and_then(|v| v.checked_add(quotient))
+            .ok_or(MathError::Overflow)?
+            / n as u128;
+
+        if next >= y {
+            break;
+        }
+        y = next;
+    }
+
+    Ok(y)
+}
+
+// ---------------------------------------------------------------------------
+// ln_fixed
+// ---------------------------------------------------------------------------
+
+/// ln 2 in PRECISION fixed point.
+const LN_2: u128 = 693_147_180_559_945_309;
+
+/// Natural logarithm of a PRECISION fixed-point value x ≥ 1.0.
+///
+/// x is first reduced to y = x ÷ 2^k in [1, 2), then
+///   ln(x) = k × ln 2 + 2 × (z + z³/3 + z⁵/5 + …),  z = (y - 1) ÷ (y + 1)
+/// z < 1/3, so the series gains about one decimal digit per term and stops
+/// once a term rounds to zero. Truncation at each step keeps the result
+/// within 100 units of PRECISION (1e-16) of the true value.
+///
+/// Returns MathError::InvalidInput for x < PRECISION, whose logarithm is
+/// negative.
+pub fn ln_fixed(x: u128) -> MathResult<u128> {
+    if x < PRECISION {
+        return Err(MathError::InvalidInput);
+    }
+
+    let k = (x / PRECISION).ilog2();
+    let y = x >> k;
+    let z = mul_fraction(y - PRECISION, PRECISION, y + PRECISION)?;
+    let z_squared = mul_fraction(z, z, PRECISION)?;
+
+    let mut series = 0u128;
+    let mut power = z;
+    let mut denominator = 1u128;
+    while power > 0 {
+        series += power / denominator;
+        power = mul_fraction(power, z_squared, PRECISION)?;
+        denominator += 2;
+    }
+
+    (k as u128)
+        .checked_mul(LN_2)
+        .and_then(|v| v.checked_add(2 * series))
+        .ok_or(MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// allocation_entropy_bps
+// ---------------------------------------------------------------------------
+
+/// Shannon entropy of an allocation as a diversification score in BPS:
+///   H = Σ p × ln(1 ÷ p) ÷ ln(n)
+/// over the non-zero shares p = pct ÷ 100, where n is the number of entries.
+/// 10_000 is a perfectly even split across all n entries and 0 is the whole
+/// allocation in one; a zero entry counts toward n as an unused destination.
+///
+/// Rounding in ln_fixed can leave an even split a unit or two short of
+/// 10_000; the result never exceeds it.
+///
+/// Returns the same errors as validate_allocation for `pcts`.
+pub fn allocation_entropy_bps(pcts: &[u64]) -> MathResult<u32> {
+    validate_allocation(pcts)?;
+    if pcts.len() < 2 {
+        return Ok(0);
+    }
+
+    let mut entropy = 0u128;
+    for &pct in pcts.iter().filter(|&&pct| pct > 0) {
+        let surprise = ln_fixed(mul_fraction(PRECISION, 100, pct as u128)?)?;
+        entropy = entropy
+            .checked_add(mul_fraction(surprise, pct as u128, 100)?)
+            .ok_or(MathError::Overflow)?;
+    }
+
+    let max_entropy = (pcts.len() as u128)
+        .checked_mul(PRECISION)
+        .ok_or(MathError::Overflow)
+        .and_then(ln_fixed)?;
+    let bps = mul_fraction(entropy, BPS_DENOMINATOR, max_entropy)?;
+    Ok(bps.min(BPS_DENOMINATOR) as u32)
+}
+
+// ---------------------------------------------------------------------------
+// ema_bps
+// ---------------------------------------------------------------------------
+
+/// Exponential moving average of a BPS series:
+///
+///   ema' = previous_ema × (1 - α) + new_value × α
+///
+/// where α = alpha_bps / BPS_DENOMINATOR. A small α smooths heavily (the
+/// previous value dominates); α = 10000 discards history and returns the new
+/// value. Oracles feeding per-block spot APYs should pass them through this
+/// before calling the optimizer so one jittery block does not flip the split.
+///
+/// The result is truncated toward zero and always lies between the two
+/// inputs, so it fits in u32.
+///
+/// Returns MathError::InvalidInput if alpha_bps > BPS_DENOMINATOR.
+pub fn ema_bps(previous_ema: u32, new_value: u32, alpha_bps: u32) -> MathResult<u32> {
+    let alpha = alpha_bps as u128;
+    if alpha > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    let keep = BPS_DENOMINATOR.checked_sub(alpha).ok_or(MathError::Underflow)?;
+
+    let weighted_sum = (previous_ema as u128)
+        .checked_mul(keep)
+        .ok_or(MathError::Overflow)?
+        .checked_add((new_value as u128).checked_mul(alpha).ok_or(MathError::Overflow)?)
+        .ok_or(MathError::Overflow)?;
+
+    let ema = weighted_sum
+        .checked_div(BPS_DENOMINATOR)
+        .ok_or(MathError::DivisionByZero)?;
+
+    u32::try_from(ema).map_err(|_| MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// lerp_bps
+// ---------------------------------------------------------------------------
+
+/// Linear interpolation between two BPS observations:
+///
+///   a + (b - a) × t / BPS_DENOMINATOR
+///
+/// where t_bps is the position between them in [0, 10000]: 0 returns a, 10000
+/// returns b. Used to read an oracle APY at a time between two reported
+/// samples. The difference is taken signed, so a falling APY (b < a) works;
+/// the step is truncated toward zero, keeping the result between a and b.
+///
+/// Returns MathError::InvalidInput if t_bps > BPS_DENOMINATOR.
+pub fn lerp_bps(a_bps: u32, b_bps: u32, t_bps: u32) -> MathResult<u32> {
+    if t_bps as u128 > BPS_DENOMINATOR {
+        return Err(MathError::InvalidInput);
+    }
+    let delta = b_bps as i128 - a_bps as i128;
+    let step = delta * t_bps as i128 / BPS_DENOMINATOR as i128;
+    u32::try_from(a_bps as i128 + step).map_err(|_| MathError::Overflow)
+}
+
+// ---------------------------------------------------------------------------
+// optimal_rebalance_periods
+// ---------------------------------------------------------------------------
+
+/// The rebalance interval, in periods, that minimises total cost:
+///
+///   T* = ⌊√(2 × rebalance_cost ÷ drift_cost)⌋
+///
+/// Rebalancing every T periods costs rebalance_cost ÷ T per period, while
+/// drift that grows linearly between rebalances costs drift_cost × T ÷ 2 per
+/// period on average; T* balances the two (the classic square-root rule). Both costs
+/// are in the same DOT unit, so the ratio is unitless. The root is taken with
+/// nth_root, and the result is at least 1 — a free rebalance is still done at
+/// most once a period.
+///
+/// A zero drift cost never makes rebalancing worthwhile and returns u32::MAX,
+/// the max-interval sentinel; intervals beyond u32 saturate to the same value.
+pub fn optimal_rebalance_periods(
+    rebalance_cost_dot: u128,
+    drift_cost_per_period_dot: u128,
+) -> MathResult<u32> {
+    if drift_cost_per_period_dot == 0 {
+        return Ok(u32::MAX);
+    }
+    // A ratio past u128 puts the root far beyond u32, so a saturated ratio
+    // still lands on the sentinel.
+    let ratio = mul_fraction(rebalance_cost_dot, 2, drift_cost_per_period_dot).unwrap_or(u128::MAX);
+    Ok(saturating_u32(nth_root(ratio, 2)?).max(1))
+}
+
+// ---------------------------------------------------------------------------
+// scenario_rates
+// ---------------------------------------------------------------------------
+
+/// Most rates scenario_rates returns in one call. Each scenario costs a
+/// keccak256 and a u32 of memory, so the clamp bounds the call's worst case.
+pub const MAX_SCENARIOS: u32 = 4_096;
+
+/// `count` reproducible pseudo-random rates in [base_rate_bps -
+/// volatility_bps, base_rate_bps + volatility_bps], for Monte-Carlo-style
+/// yield ranges.
+///
+/// Scenario i draws from keccak256(seed ‖ i as 4 big-endian bytes), so every
+/// validator derives the same rates from the same seed and distinct seeds
+/// give independent sets. The first 8 hash bytes pick a point uniformly in
+/// the band (modulo bias is below 2^-30), and the band is clipped to
+/// 0..=u32::MAX — a band reaching below zero biases the mean upward.
+///
+/// `count` is clamped to MAX_SCENARIOS; a larger request returns the first
+/// MAX_SCENARIOS rates of the same sequence.
+pub fn scenario_rates(base_rate_bps: u32, volatility_bps: u32, seed: [u8; 32], count: u32) -> Vec<u32> {
+    let low = base_rate_bps.saturating_sub(volatility_bps);
+    let high = base_rate_bps.saturating_add(volatility_bps);
+    let span = (high - low) as u64 + 1;
+
+    let mut preimage = [0u8; 36];
+    preimage[..32].copy_from_slice(&seed);
+    (0..count.min(MAX_SCENARIOS))
+        .map(|i| {
+            preimage[32..].copy_from_slice(&i.to_be_bytes());
+            let hash = sp_core::keccak_256(&preimage);
+            let mut draw = [0u8; 8];
+            draw.copy_from_slice(&hash[..8]);
+            // span ≤ 2^32, so the offset fits u32 and low + offset ≤ high.
+            low + (u64::from_be_bytes(draw) % span) as u32
+        })
+        .collect()
+}
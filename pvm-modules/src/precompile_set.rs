@@ -45,6 +45,13 @@ pub const YIELD_OPTIMIZER_PRECOMPILE_ADDRESS: H160 = H160([
     0x00, 0x00, 0x10, 0x02, // 0x0000...1002
 ]);
 
+/// Every address this crate registers, in address order: [MathLib, YieldOptimizer].
+/// Runtime-queryable source of truth for frontends and tooling that would
+/// otherwise hard-code the constants above.
+pub fn registered_addresses() -> [H160; 2] {
+    [MATH_LIB_PRECOMPILE_ADDRESS, YIELD_OPTIMIZER_PRECOMPILE_ADDRESS]
+}
+
 // ---------------------------------------------------------------------------
 // PrecompileSet implementation
 // ---------------------------------------------------------------------------
@@ -60,8 +67,7 @@ impl PolkaPulsePrecompileSet {
     /// Returns true if the given address maps to a registered PolkaPulse precompile.
     /// Called by the pallet-revive runtime before every contract call.
     pub fn is_precompile(address: &H160) -> bool {
-        *address == MATH_LIB_PRECOMPILE_ADDRESS
-            || *address == YIELD_OPTIMIZER_PRECOMPILE_ADDRESS
+        registered_addresses().contains(address)
     }
 
     /// Route a call to the correct precompile handler and return the output bytes.
@@ -119,6 +125,19 @@ mod tests {
         );
     }
 
+    /// The registered list is exactly the two documented constants, with no
+    /// duplicate standing in for an omitted precompile
+    #[test]
+    fn test_registered_addresses_match_constants() {
+        let addresses = registered_addresses();
+        assert_eq!(addresses, [MATH_LIB_PRECOMPILE_ADDRESS, YIELD_OPTIMIZER_PRECOMPILE_ADDRESS]);
+        assert_ne!(addresses[0], addresses[1], "Registered addresses must be unique");
+        for address in &addresses {
+            assert!(PolkaPulsePrecompileSet::is_precompile(address));
+            assert!(PolkaPulsePrecompileSet::execute(address, &[]).is_some());
+        }
+    }
+
     /// execute() on an unknown address must return None
     #[test]
     fn test_execute_unknown_address_returns_none() {
@@ -12,6 +12,7 @@
 ///   feeAdjustedYield(uint128,uint32)          → 0xb2761be5
 ///   weightedAverage(uint128[],uint128[])      → 0xc268dc58
 ///   optimalSplit(uint32,uint32,uint32,uint32) → 0x2a138a61
+///   simpleInterest(uint128,uint32,uint32)     → 0x844f7e58
 ///
 /// DISPATCH MODEL:
 /// The `call` function reads the first 4 bytes of input as the selector, routes
@@ -19,11 +20,15 @@
 /// executes the math function, and returns ABI-encoded output. On any error it
 /// returns an ABI-encoded (bool success=false, uint32 errorCode) tuple.
 
-use ethabi::{decode, encode, ParamType, Token};
+use ethabi::{decode, encode, ParamType, Token, Uint};
 use crate::math_lib::{
     self, MathError, PRECISION
 };
-use crate::abi::encode_error;
+use crate::abi::{checked_token_u64, encode_error};
+use crate::abi::error_codes::{
+    ERR_DECODE_FAILED, ERR_DIVISION_BY_ZERO, ERR_INVALID_INPUT, ERR_LENGTH_MISMATCH, ERR_OVERFLOW,
+    ERR_UNDERFLOW, ERR_UNKNOWN_SELECTOR,
+};
 
 // ---------------------------------------------------------------------------
 // Function selectors
@@ -34,24 +39,33 @@ const SEL_ANNUALIZE: [u8; 4]           = [0x33, 0x8d, 0xb1, 0x65];
 const SEL_FEE_ADJUSTED: [u8; 4]        = [0xb2, 0x76, 0x1b, 0xe5];
 const SEL_WEIGHTED_AVG: [u8; 4]        = [0xc2, 0x68, 0xdc, 0x58];
 const SEL_OPTIMAL_SPLIT: [u8; 4]       = [0x2a, 0x13, 0x8a, 0x61];
+const SEL_SIMPLE_INTEREST: [u8; 4]     = [0x84, 0x4f, 0x7e, 0x58];
+
+/// Every function this precompile dispatches, as (Solidity signature, selector).
+/// This is the source of truth for generating the Solidity interface; each
+/// selector is the first 4 bytes of keccak256(signature).
+pub fn supported_functions() -> &'static [(&'static str, [u8; 4])] {
+    &[
+        ("compound(uint128,uint32,uint32)",           SEL_COMPOUND),
+        ("annualize(uint32,uint64)",                  SEL_ANNUALIZE),
+        ("feeAdjustedYield(uint128,uint32)",          SEL_FEE_ADJUSTED),
+        ("weightedAverage(uint128[],uint128[])",      SEL_WEIGHTED_AVG),
+        ("optimalSplit(uint32,uint32,uint32,uint32)", SEL_OPTIMAL_SPLIT),
+        ("simpleInterest(uint128,uint32,uint32)",     SEL_SIMPLE_INTEREST),
+    ]
+}
 
 // ---------------------------------------------------------------------------
-// Error codes (must stay in sync with abi.rs and AtomicYieldExecutor.sol)
+// Error codes (shared with abi.rs via abi::error_codes)
 // ---------------------------------------------------------------------------
 
-const ERR_INVALID_INPUT: u32   = 1;
-const ERR_OVERFLOW: u32        = 2;
-const ERR_UNDERFLOW: u32       = 3;
-const ERR_DIVISION_BY_ZERO: u32 = 4;
-const ERR_UNKNOWN_SELECTOR: u32 = 5;
-const ERR_DECODE_FAILED: u32   = 6;
-
 fn math_error_code(e: &MathError) -> u32 {
     match e {
         MathError::InvalidInput    => ERR_INVALID_INPUT,
         MathError::Overflow        => ERR_OVERFLOW,
         MathError::Underflow       => ERR_UNDERFLOW,
         MathError::DivisionByZero  => ERR_DIVISION_BY_ZERO,
+        MathError::LengthMismatch  => ERR_LENGTH_MISMATCH,
     }
 }
 
@@ -75,15 +89,28 @@ pub fn call(input: &[u8]) -> Vec<u8> {
     let args = &input[4..];
 
     match selector {
-        SEL_COMPOUND       => handle_compound(args),
-        SEL_ANNUALIZE      => handle_annualize(args),
-        SEL_FEE_ADJUSTED   => handle_fee_adjusted_yield(args),
-        SEL_WEIGHTED_AVG   => handle_weighted_average(args),
-        SEL_OPTIMAL_SPLIT  => handle_optimal_split(args),
-        _                  => encode_error(ERR_UNKNOWN_SELECTOR),
+        SEL_COMPOUND        => handle_compound(args),
+        SEL_ANNUALIZE       => handle_annualize(args),
+        SEL_FEE_ADJUSTED    => handle_fee_adjusted_yield(args),
+        SEL_WEIGHTED_AVG    => handle_weighted_average(args),
+        SEL_OPTIMAL_SPLIT   => handle_optimal_split(args),
+        SEL_SIMPLE_INTEREST => handle_simple_interest(args),
+        _                   => encode_error(ERR_UNKNOWN_SELECTOR),
     }
 }
 
+// ---------------------------------------------------------------------------
+// Argument narrowing
+// ---------------------------------------------------------------------------
+
+/// The uint in `token`, if it fits in `bits`. ethabi reads every uintN slot
+/// as a full 256-bit word without checking N, and the as_uN() casts panic on
+/// a wider value, so each handler narrows its arguments through here and an
+/// over-width word is a decode failure.
+fn uint_within(token: &Token, bits: usize) -> Option<Uint> {
+    token.clone().into_uint().filter(|v| v.bits() <= bits)
+}
+
 // ---------------------------------------------------------------------------
 // Handlers — one per math_lib function
 // ---------------------------------------------------------------------------
@@ -97,16 +124,21 @@ fn handle_compound(args: &[u8]) -> Vec<u8> {
         Ok(t) => t,
         Err(_) => return encode_error(ERR_DECODE_FAILED),
     };
+    // decode() yields one token per type today; checking keeps a decoder
+    // change from turning into an index panic in the runtime.
+    if tokens.len() != 3 {
+        return encode_error(ERR_DECODE_FAILED);
+    }
 
-    let principal = match tokens[0].clone().into_uint() {
+    let principal = match uint_within(&tokens[0], 128) {
         Some(v) => v.as_u128(),
         None => return encode_error(ERR_DECODE_FAILED),
     };
-    let rate_bps = match tokens[1].clone().into_uint() {
+    let rate_bps = match uint_within(&tokens[1], 32) {
         Some(v) => v.as_u32(),
         None => return encode_error(ERR_DECODE_FAILED),
     };
-    let periods = match tokens[2].clone().into_uint() {
+    let periods = match uint_within(&tokens[2], 32) {
         Some(v) => v.as_u32(),
         None => return encode_error(ERR_DECODE_FAILED),
     };
@@ -126,12 +158,15 @@ fn handle_annualize(args: &[u8]) -> Vec<u8> {
         Ok(t) => t,
         Err(_) => return encode_error(ERR_DECODE_FAILED),
     };
+    if tokens.len() != 2 {
+        return encode_error(ERR_DECODE_FAILED);
+    }
 
-    let rate_bps = match tokens[0].clone().into_uint() {
+    let rate_bps = match uint_within(&tokens[0], 32) {
         Some(v) => v.as_u32(),
         None => return encode_error(ERR_DECODE_FAILED),
     };
-    let period_seconds = match tokens[1].clone().into_uint() {
+    let period_seconds = match uint_within(&tokens[1], 64) {
         Some(v) => v.as_u64(),
         None => return encode_error(ERR_DECODE_FAILED),
     };
@@ -151,12 +186,15 @@ fn handle_fee_adjusted_yield(args: &[u8]) -> Vec<u8> {
         Ok(t) => t,
         Err(_) => return encode_error(ERR_DECODE_FAILED),
     };
+    if tokens.len() != 2 {
+        return encode_error(ERR_DECODE_FAILED);
+    }
 
-    let gross_yield = match tokens[0].clone().into_uint() {
+    let gross_yield = match uint_within(&tokens[0], 128) {
         Some(v) => v.as_u128(),
         None => return encode_error(ERR_DECODE_FAILED),
     };
-    let fee_bps = match tokens[1].clone().into_uint() {
+    let fee_bps = match uint_within(&tokens[1], 32) {
         Some(v) => v.as_u32(),
         None => return encode_error(ERR_DECODE_FAILED),
     };
@@ -182,6 +220,9 @@ fn handle_weighted_average(args: &[u8]) -> Vec<u8> {
         Ok(t) => t,
         Err(_) => return encode_error(ERR_DECODE_FAILED),
     };
+    if tokens.len() != 2 {
+        return encode_error(ERR_DECODE_FAILED);
+    }
 
     let values_tokens = match tokens[0].clone().into_array() {
         Some(v) => v,
@@ -193,7 +234,7 @@ fn handle_weighted_average(args: &[u8]) -> Vec<u8> {
     };
 
     let values: Vec<u128> = match values_tokens.iter()
-        .map(|t| t.clone().into_uint().map(|u| u.as_u128()))
+        .map(|t| uint_within(t, 128).map(|u| u.as_u128()))
         .collect::<Option<Vec<_>>>()
     {
         Some(v) => v,
@@ -201,7 +242,7 @@ fn handle_weighted_average(args: &[u8]) -> Vec<u8> {
     };
 
     let weights: Vec<u128> = match weights_tokens.iter()
-        .map(|t| t.clone().into_uint().map(|u| u.as_u128()))
+        .map(|t| uint_within(t, 128).map(|u| u.as_u128()))
         .collect::<Option<Vec<_>>>()
     {
         Some(v) => v,
@@ -229,22 +270,57 @@ fn handle_optimal_split(args: &[u8]) -> Vec<u8> {
         Ok(t) => t,
         Err(_) => return encode_error(ERR_DECODE_FAILED),
     };
+    if tokens.len() != 4 {
+        return encode_error(ERR_DECODE_FAILED);
+    }
 
-    let yield_a = match tokens[0].clone().into_uint() { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
-    let yield_b = match tokens[1].clone().into_uint() { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
-    let risk_a  = match tokens[2].clone().into_uint() { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
-    let risk_b  = match tokens[3].clone().into_uint() { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
+    let yield_a = match uint_within(&tokens[0], 32) { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
+    let yield_b = match uint_within(&tokens[1], 32) { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
+    let risk_a  = match uint_within(&tokens[2], 32) { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
+    let risk_b  = match uint_within(&tokens[3], 32) { Some(v) => v.as_u32(), None => return encode_error(ERR_DECODE_FAILED) };
 
     match math_lib::optimal_split(yield_a, yield_b, risk_a, risk_b) {
         Ok((pct_a, pct_b)) => encode(&[
             Token::Bool(true),
-            Token::Uint(pct_a.into()),
-            Token::Uint(pct_b.into()),
+            checked_token_u64(pct_a),
+            checked_token_u64(pct_b),
         ]),
         Err(e) => encode_error(math_error_code(&e)),
     }
 }
 
+/// simpleInterest(uint128 principal, uint32 rate_bps, uint32 periods) → uint128
+fn handle_simple_interest(args: &[u8]) -> Vec<u8> {
+    let tokens = match decode(
+        &[ParamType::Uint(128), ParamType::Uint(32), ParamType::Uint(32)],
+        args,
+    ) {
+        Ok(t) => t,
+        Err(_) => return encode_error(ERR_DECODE_FAILED),
+    };
+    if tokens.len() != 3 {
+        return encode_error(ERR_DECODE_FAILED);
+    }
+
+    let principal = match uint_within(&tokens[0], 128) {
+        Some(v) => v.as_u128(),
+        None => return encode_error(ERR_DECODE_FAILED),
+    };
+    let rate_bps = match uint_within(&tokens[1], 32) {
+        Some(v) => v.as_u32(),
+        None => return encode_error(ERR_DECODE_FAILED),
+    };
+    let periods = match uint_within(&tokens[2], 32) {
+        Some(v) => v.as_u32(),
+        None => return encode_error(ERR_DECODE_FAILED),
+    };
+
+    match math_lib::simple_interest(principal, rate_bps, periods) {
+        Ok(result) => encode(&[Token::Bool(true), Token::Uint(result.into())]),
+        Err(e)     => encode_error(math_error_code(&e)),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -253,6 +329,7 @@ fn handle_optimal_split(args: &[u8]) -> Vec<u8> {
 mod tests {
     use super::*;
     use ethabi::encode;
+    use crate::abi::decode_call_result;
     use crate::math_lib::PRECISION;
 
     fn build_input(selector: [u8; 4], args: Vec<u8>) -> Vec<u8> {
@@ -270,13 +347,11 @@ mod tests {
             Token::Uint(1u32.into()),
         ]);
         let result = call(&build_input(SEL_COMPOUND, args));
-        // First word = true (success)
-        assert_eq!(result[31], 1u8, "Success flag must be true");
-        // Second word must be > principal (yield was added)
+        let tokens = decode_call_result(&result).expect("Compound must succeed");
+        // Returned amount must be > principal (yield was added)
         // (1000 * PRECISION) * 11000 / 10000 = 1100 * PRECISION
         let expected = 1_100u128 * PRECISION;
-        let returned = u128::from_be_bytes(result[48..64].try_into().unwrap());
-        assert_eq!(returned, expected);
+        assert_eq!(tokens, vec![Token::Uint(expected.into())]);
     }
 
     /// Unknown selector must return error, not panic
@@ -284,15 +359,14 @@ mod tests {
     fn test_unknown_selector_returns_error() {
         let input = build_input([0xde, 0xad, 0xbe, 0xef], vec![]);
         let result = call(&input);
-        // First word = false (failure)
-        assert_eq!(result[31], 0u8, "Unknown selector must return failure");
+        assert_eq!(decode_call_result(&result), Err(ERR_UNKNOWN_SELECTOR));
     }
 
     /// Input too short (less than 4 bytes) must return error
     #[test]
     fn test_input_too_short_returns_error() {
         let result = call(&[0x01, 0x02]);
-        assert_eq!(result[31], 0u8, "Short input must return failure");
+        assert_eq!(decode_call_result(&result), Err(ERR_DECODE_FAILED), "Short input must return failure");
     }
 
     /// annualize dispatch — 1 year should return same rate
@@ -303,7 +377,7 @@ mod tests {
             Token::Uint(31_536_000u64.into()),
         ]);
         let result = call(&build_input(SEL_ANNUALIZE, args));
-        assert_eq!(result[31], 1u8, "Annualize must succeed");
+        assert!(decode_call_result(&result).is_ok(), "Annualize must succeed");
     }
 
     /// optimalSplit dispatch — equal inputs should give 50/50
@@ -316,11 +390,168 @@ mod tests {
             Token::Uint(1_000u32.into()),
         ]);
         let result = call(&build_input(SEL_OPTIMAL_SPLIT, args));
-        assert_eq!(result[31], 1u8, "OptimalSplit must succeed");
-        // pct_a is in second word (bytes 32–63)
-        let pct_a = result[63] as u64;
-        // pct_b is in third word (bytes 64–95)
-        let pct_b = result[95] as u64;
-        assert_eq!(pct_a + pct_b, 100);
+        let tokens = decode_call_result(&result).expect("OptimalSplit must succeed");
+        let pcts: Vec<u64> = tokens.into_iter().map(|t| t.into_uint().unwrap().as_u64()).collect();
+        assert_eq!(pcts.len(), 2);
+        assert_eq!(pcts[0] + pcts[1], 100);
+    }
+
+    /// weightedAverage with arrays of different lengths returns the dedicated
+    /// LengthMismatch code rather than the generic InvalidInput.
+    #[test]
+    fn test_dispatch_weighted_average_length_mismatch() {
+        let args = encode(&[
+            Token::Array(vec![Token::Uint(1_000u128.into()), Token::Uint(2_000u128.into())]),
+            Token::Array(vec![Token::Uint(1u128.into())]),
+        ]);
+        let result = call(&build_input(SEL_WEIGHTED_AVG, args));
+        assert_eq!(decode_call_result(&result), Err(ERR_LENGTH_MISMATCH));
+    }
+
+    /// weightedAverage with two empty arrays is still InvalidInput.
+    #[test]
+    fn test_dispatch_weighted_average_empty_is_invalid_input() {
+        let args = encode(&[Token::Array(vec![]), Token::Array(vec![])]);
+        let result = call(&build_input(SEL_WEIGHTED_AVG, args));
+        assert_eq!(decode_call_result(&result), Err(ERR_INVALID_INPUT));
+    }
+
+    /// The new variant maps to its own code, distinct from InvalidInput.
+    #[test]
+    fn test_length_mismatch_error_code() {
+        assert_eq!(math_error_code(&MathError::LengthMismatch), ERR_LENGTH_MISMATCH);
+        assert_ne!(ERR_LENGTH_MISMATCH, ERR_INVALID_INPUT);
+    }
+
+    /// Each listed selector is keccak256(signature)[..4], and every listed
+    /// function is routed by call() rather than rejected as unknown.
+    #[test]
+    fn test_supported_functions_match_keccak_selectors() {
+        assert_eq!(supported_functions().len(), 6);
+        for (signature, selector) in supported_functions() {
+            let hash = sp_core::keccak_256(signature.as_bytes());
+            assert_eq!(&hash[..4], selector, "selector drift for {}", signature);
+
+            let result = call(&build_input(*selector, vec![]));
+            assert_ne!(decode_call_result(&result), Err(ERR_UNKNOWN_SELECTOR), "{} not dispatched", signature);
+        }
+    }
+
+    /// Every MathError variant. The match has no wildcard, so adding a variant
+    /// fails to compile until it is listed here too.
+    fn all_math_errors() -> Vec<MathError> {
+        let all = vec![
+            MathError::InvalidInput,
+            MathError::Overflow,
+            MathError::Underflow,
+            MathError::DivisionByZero,
+            MathError::LengthMismatch,
+        ];
+        for e in &all {
+            match e {
+                MathError::InvalidInput
+                | MathError::Overflow
+                | MathError::Underflow
+                | MathError::DivisionByZero
+                | MathError::LengthMismatch => {}
+            }
+        }
+        all
+    }
+
+    /// Each MathError maps to the shared code whose name is the variant's own.
+    #[test]
+    fn test_math_error_codes_match_shared_table() {
+        for e in all_math_errors() {
+            let code = math_error_code(&e);
+            let name = crate::abi::error_codes::ALL
+                .iter()
+                .find(|(c, _)| *c == code)
+                .map(|(_, n)| *n);
+            assert_eq!(name, Some(format!("{:?}", e).as_str()), "{:?} maps to code {}", e, code);
+        }
+    }
+
+    /// simpleInterest dispatch — pays the rate once, trailing compound
+    #[test]
+    fn test_dispatch_simple_interest_below_compound() {
+        let args = encode(&[
+            Token::Uint((1_000u128 * PRECISION).into()),
+            Token::Uint(1_000u32.into()),
+            Token::Uint(4u32.into()),
+        ]);
+        let simple = decode_call_result(&call(&build_input(SEL_SIMPLE_INTEREST, args.clone())))
+            .expect("simpleInterest must succeed");
+        // 1000 DOT at 10% without reinvestment → exactly 1100 DOT
+        assert_eq!(simple, vec![Token::Uint((1_100u128 * PRECISION).into())]);
+
+        let compound = decode_call_result(&call(&build_input(SEL_COMPOUND, args)))
+            .expect("compound must succeed");
+        let as_u128 = |tokens: Vec<Token>| tokens[0].clone().into_uint().unwrap().as_u128();
+        assert!(as_u128(simple) < as_u128(compound));
+    }
+
+    /// Calldata that encodes too few arguments fails to decode instead of
+    /// panicking on a missing token.
+    #[test]
+    fn test_dispatch_short_token_count_returns_error() {
+        let three_of_four = encode(&[
+            Token::Uint(1_000u32.into()),
+            Token::Uint(1_000u32.into()),
+            Token::Uint(1_000u32.into()),
+        ]);
+        let result = call(&build_input(SEL_OPTIMAL_SPLIT, three_of_four));
+        assert_eq!(decode_call_result(&result), Err(ERR_DECODE_FAILED));
+
+        let two_of_three = encode(&[Token::Uint(PRECISION.into()), Token::Uint(1_000u32.into())]);
+        for selector in [SEL_COMPOUND, SEL_SIMPLE_INTEREST] {
+            let result = call(&build_input(selector, two_of_three.clone()));
+            assert_eq!(decode_call_result(&result), Err(ERR_DECODE_FAILED));
+        }
+    }
+
+    /// Every handler answers short runs of zero words with an encoded
+    /// response rather than a panic; no words at all is a decode failure.
+    #[test]
+    fn test_dispatch_truncated_calldata_never_panics() {
+        for (signature, selector) in supported_functions() {
+            for words in 0..4 {
+                let result = call(&build_input(*selector, vec![0u8; 32 * words]));
+                if words == 0 {
+                    assert_eq!(decode_call_result(&result), Err(ERR_DECODE_FAILED), "{}", signature);
+                }
+                assert!(!result.is_empty(), "{} with {} words", signature, words);
+            }
+        }
+    }
+
+    /// A word wider than its declared uintN is a decode failure for every
+    /// argument position, not an as_uN() panic.
+    #[test]
+    fn test_dispatch_over_width_argument_returns_error() {
+        let widths: [([u8; 4], &[usize]); 5] = [
+            (SEL_COMPOUND,        &[128, 32, 32]),
+            (SEL_ANNUALIZE,       &[32, 64]),
+            (SEL_FEE_ADJUSTED,    &[128, 32]),
+            (SEL_OPTIMAL_SPLIT,   &[32, 32, 32, 32]),
+            (SEL_SIMPLE_INTEREST, &[128, 32, 32]),
+        ];
+        let over = |bits: usize| Token::Uint(Uint::one() << bits);
+        for (selector, params) in widths {
+            for slot in 0..params.len() {
+                let args: Vec<Token> = params.iter().enumerate()
+                    .map(|(i, bits)| if i == slot { over(*bits) } else { Token::Uint(Uint::one()) })
+                    .collect();
+                let result = call(&build_input(selector, encode(&args)));
+                assert_eq!(decode_call_result(&result), Err(ERR_DECODE_FAILED), "{:?} slot {}", selector, slot);
+            }
+        }
+
+        let wide = Token::Array(vec![over(128)]);
+        let one = Token::Array(vec![Token::Uint(Uint::one())]);
+        for args in [[wide.clone(), one.clone()], [one, wide]] {
+            let result = call(&build_input(SEL_WEIGHTED_AVG, encode(&args)));
+            assert_eq!(decode_call_result(&result), Err(ERR_DECODE_FAILED));
+        }
     }
 }
\ No newline at end of file
@@ -7,8 +7,23 @@
 ///
 /// REGISTERED ADDRESS: YIELD_OPTIMIZER_PRECOMPILE_ADDRESS (defined in precompile_set.rs)
 ///
-/// FUNCTION SELECTOR:
+/// FUNCTION SELECTORS:
 ///   optimize(uint128,uint32,uint32,uint32,uint32,uint32,uint32,uint32) → 0xefc2eb5f
+///   optimizeBatch(bytes[])                                             → 0xef7ceef8
+///   schema()                                                           → 0xf8895cc8
+///
+/// BATCH MODE:
+/// optimizeBatch takes an array of independently ABI-encoded OptimizerInputs
+/// (each the same 8-word payload optimize() accepts) and returns
+/// (bool success, bytes[] results), where results[i] is exactly what
+/// optimize() would return for position i.
+/// Each slot succeeds or fails on its own; only a malformed outer array or a
+/// batch above MAX_BATCH_SIZE fails the whole call.
+///
+/// SCHEMA:
+/// schema() returns (bool success, (string name, string type)[] fields): the
+/// optimize() argument fields in calldata order, then its output fields in
+/// return order after the success flag. See SCHEMA.
 ///
 /// ON ERROR:
 /// Returns encode_error(error_code). AtomicYieldExecutor.sol checks the bool flag
@@ -17,35 +32,75 @@
 /// yield loop built on corrupt or failed math output.
 
 use ethabi::{decode, encode, ParamType, Token};
-use crate::abi::{decode_optimizer_input, encode_yield_recommendation, encode_error};
+use crate::abi::{checked_yield_recommendation_tokens, decode_optimizer_input, encode_error};
+use crate::abi::error_codes::{
+    ERR_DECODE_FAILED, ERR_DIVISION_BY_ZERO, ERR_INVALID_INPUT, ERR_LENGTH_MISMATCH,
+    ERR_NO_VIABLE_DESTINATION, ERR_OVERFLOW, ERR_UNDERFLOW, ERR_UNKNOWN_SELECTOR,
+};
 use crate::yield_optimizer::{optimize, OptimizerError};
 use crate::math_lib::MathError;
 
 // ---------------------------------------------------------------------------
-// Function selector
+// Function selectors
 // ---------------------------------------------------------------------------
 
-const SEL_OPTIMIZE: [u8; 4] = [0xef, 0xc2, 0xeb, 0x5f];
+const SEL_OPTIMIZE: [u8; 4]       = [0xef, 0xc2, 0xeb, 0x5f];
+const SEL_OPTIMIZE_BATCH: [u8; 4] = [0xef, 0x7c, 0xee, 0xf8];
+const SEL_SCHEMA: [u8; 4]         = [0xf8, 0x89, 0x5c, 0xc8];
+
+/// Upper bound on positions per optimizeBatch call. Each position runs a full
+/// optimize(), so the cap bounds the call's worst-case weight.
+pub const MAX_BATCH_SIZE: usize = 32;
+
+/// Every function this precompile dispatches, as (Solidity signature, selector).
+/// This is the source of truth for generating the Solidity interface; each
+/// selector is the first 4 bytes of keccak256(signature).
+pub fn supported_functions() -> &'static [(&'static str, [u8; 4])] {
+    &[
+        ("optimize(uint128,uint32,uint32,uint32,uint32,uint32,uint32,uint32)", SEL_OPTIMIZE),
+        ("optimizeBatch(bytes[])",                                             SEL_OPTIMIZE_BATCH),
+        ("schema()",                                                           SEL_SCHEMA),
+    ]
+}
+
+/// Number of leading SCHEMA entries that describe optimize()'s arguments;
+/// the rest describe its return values.
+pub const SCHEMA_INPUT_FIELDS: usize = 8;
+
+/// The optimize() wire format as (field name, Solidity type): the
+/// OptimizerInput fields calldata carries, then the YieldRecommendation
+/// fields the success tuple carries. Names are the Rust field names.
+pub const SCHEMA: [(&str, &str); 14] = [
+    ("principal",               "uint128"),
+    ("hydradx_apy_bps",         "uint32"),
+    ("interlay_apy_bps",        "uint32"),
+    ("hydradx_fee_bps",         "uint32"),
+    ("interlay_fee_bps",        "uint32"),
+    ("hydradx_risk_score",      "uint32"),
+    ("interlay_risk_score",     "uint32"),
+    ("projection_periods",      "uint32"),
+    ("use_hydradx",             "bool"),
+    ("use_interlay",            "bool"),
+    ("hydradx_allocation_pct",  "uint64"),
+    ("interlay_allocation_pct", "uint64"),
+    ("projected_net_apy_bps",   "uint32"),
+    ("expected_yield_dot",      "uint128"),
+];
 
 // ---------------------------------------------------------------------------
-// Error codes (must stay in sync with abi.rs and math_lib_precompile.rs)
+// Error codes (shared with abi.rs via abi::error_codes)
 // ---------------------------------------------------------------------------
 
-const ERR_INVALID_INPUT: u32    = 1;
-const ERR_OVERFLOW: u32         = 2;
-const ERR_UNDERFLOW: u32        = 3;
-const ERR_DIVISION_BY_ZERO: u32 = 4;
-const ERR_UNKNOWN_SELECTOR: u32 = 5;
-const ERR_DECODE_FAILED: u32    = 6;
-
 fn optimizer_error_code(e: &OptimizerError) -> u32 {
     match e {
         OptimizerError::InvalidInput => ERR_INVALID_INPUT,
+        OptimizerError::NoViableDestination => ERR_NO_VIABLE_DESTINATION,
         OptimizerError::Math(m) => match m {
             MathError::Overflow       => ERR_OVERFLOW,
             MathError::Underflow      => ERR_UNDERFLOW,
             MathError::DivisionByZero => ERR_DIVISION_BY_ZERO,
             MathError::InvalidInput   => ERR_INVALID_INPUT,
+            MathError::LengthMismatch => ERR_LENGTH_MISMATCH,
         },
     }
 }
@@ -56,11 +111,11 @@ fn optimizer_error_code(e: &OptimizerError) -> u32 {
 
 /// Called by pallet-revive for every call targeting YIELD_OPTIMIZER_PRECOMPILE_ADDRESS.
 ///
-/// Reads the 4-byte selector, verifies it matches SEL_OPTIMIZE, decodes
-/// the calldata into OptimizerInput via abi.rs, runs the optimizer, and
-/// encodes the YieldRecommendation back as ABI bytes.
+/// Reads the 4-byte selector and routes to optimize, optimizeBatch or schema. For
+/// optimize it decodes the calldata into OptimizerInput via abi.rs, runs the
+/// optimizer, and encodes the YieldRecommendation back as ABI bytes.
 ///
-/// Returns a (bool success, <fields>) ABI tuple. Solidity decodes it as:
+/// optimize returns a (bool success, <fields>) ABI tuple. Solidity decodes it as:
 ///   (bool success, bool useHydraDX, bool useInterlay,
 ///    uint64 hydraDXPct, uint64 interlayPct,
 ///    uint32 netApyBps, uint128 expectedYieldDot)
@@ -70,13 +125,22 @@ pub fn call(input: &[u8]) -> Vec<u8> {
     }
 
     let selector: [u8; 4] = input[0..4].try_into().unwrap();
+    let args = &input[4..];
 
-    if selector != SEL_OPTIMIZE {
-        return encode_error(ERR_UNKNOWN_SELECTOR);
+    match selector {
+        SEL_OPTIMIZE       => handle_optimize(args),
+        SEL_OPTIMIZE_BATCH => handle_optimize_batch(args),
+        SEL_SCHEMA         => handle_schema(),
+        _                  => encode_error(ERR_UNKNOWN_SELECTOR),
     }
+}
 
-    let args = &input[4..];
+// ---------------------------------------------------------------------------
+// Handlers
+// ---------------------------------------------------------------------------
 
+/// optimize(...) — decode one OptimizerInput, run the optimizer, encode the result.
+fn handle_optimize(args: &[u8]) -> Vec<u8> {
     // Decode calldata using the shared abi module
     let optimizer_input = match decode_optimizer_input(args) {
         Some(i) => i,
@@ -86,15 +150,51 @@ pub fn call(input: &[u8]) -> Vec<u8> {
     // Run the optimizer
     match optimize(&optimizer_input) {
         Ok(recommendation) => {
-            // Prepend success flag to the encoded recommendation
-            let mut output = encode(&[Token::Bool(true)]);
-            output.extend(encode_yield_recommendation(&recommendation));
-            output
+            // Success flag and recommendation encoded as one tuple
+            let mut tokens = vec![Token::Bool(true)];
+            tokens.extend(checked_yield_recommendation_tokens(&recommendation));
+            encode(&tokens)
         }
         Err(e) => encode_error(optimizer_error_code(&e)),
     }
 }
 
+/// optimizeBatch(bytes[]) — run handle_optimize on every element and return
+/// (true, per-position outputs).
+fn handle_optimize_batch(args: &[u8]) -> Vec<u8> {
+    let tokens = match decode(&[ParamType::Array(Box::new(ParamType::Bytes))], args) {
+        Ok(t) => t,
+        Err(_) => return encode_error(ERR_DECODE_FAILED),
+    };
+    let entries = match tokens.into_iter().next().and_then(Token::into_array) {
+        Some(e) => e,
+        None => return encode_error(ERR_DECODE_FAILED),
+    };
+    if entries.len() > MAX_BATCH_SIZE {
+        return encode_error(ERR_INVALID_INPUT);
+    }
+
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let output = match entry.into_bytes() {
+            Some(position) => handle_optimize(&position),
+            None => encode_error(ERR_DECODE_FAILED),
+        };
+        results.push(Token::Bytes(output));
+    }
+
+    encode(&[Token::Bool(true), Token::Array(results)])
+}
+
+/// schema() — (true, SCHEMA as (string, string)[]). Takes no arguments.
+fn handle_schema() -> Vec<u8> {
+    let fields = SCHEMA
+        .iter()
+        .map(|(name, ty)| Token::Tuple(vec![Token::String(name.to_string()), Token::String(ty.to_string())]))
+        .collect();
+    encode(&[Token::Bool(true), Token::Array(fields)])
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -103,7 +203,9 @@ pub fn call(input: &[u8]) -> Vec<u8> {
 mod tests {
     use super::*;
     use ethabi::encode;
+    use crate::abi::{decode_call_result, decode_yield_recommendation};
     use crate::math_lib::PRECISION;
+    use crate::yield_optimizer::YieldRecommendation;
 
     fn build_optimize_call(
         principal: u128,
@@ -176,6 +278,67 @@ mod tests {
         assert_eq!(result[31], 0u8);
     }
 
+    /// The success output is a single (bool, bool, bool, uint64, uint64,
+    /// uint32, uint128) tuple whose fields match optimize() directly.
+    #[test]
+    fn test_optimize_output_decodes_as_one_tuple() {
+        let input = build_optimize_call(1_000 * PRECISION, 1_200, 900, 50, 100, 1_500, 2_500, 365);
+        let rec = optimize(&decode_optimizer_input(&input[4..]).unwrap()).unwrap();
+
+        let result = call(&input);
+        assert_eq!(result.len(), 7 * 32);
+        let tokens = decode(
+            &[
+                ParamType::Bool,
+                ParamType::Bool,
+                ParamType::Bool,
+                ParamType::Uint(64),
+                ParamType::Uint(64),
+                ParamType::Uint(32),
+                ParamType::Uint(128),
+            ],
+            &result,
+        )
+        .expect("output must decode as one static tuple");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Bool(true),
+                Token::Bool(rec.use_hydradx),
+                Token::Bool(rec.use_interlay),
+                Token::Uint(rec.hydradx_allocation_pct.into()),
+                Token::Uint(rec.interlay_allocation_pct.into()),
+                Token::Uint(rec.projected_net_apy_bps.into()),
+                Token::Uint(rec.expected_yield_dot.into()),
+            ]
+        );
+        assert!(rec.use_hydradx && rec.use_interlay && rec.expected_yield_dot > 0);
+    }
+
+    /// optimize() → call() → decode_yield_recommendation() is lossless on
+    /// every field the ABI carries.
+    #[test]
+    fn test_precompile_recommendation_roundtrip() {
+        let input = build_optimize_call(1_000 * PRECISION, 1_200, 900, 50, 100, 1_500, 2_500, 365);
+        let direct = optimize(&decode_optimizer_input(&input[4..]).unwrap()).unwrap();
+
+        let result = call(&input);
+        assert_eq!(decode_call_result(&result).map(|_| ()), Ok(()));
+        let decoded = decode_yield_recommendation(&result[32..]).expect("payload must decode");
+
+        // Off-chain fields never reach the wire, so compare on the wire fields.
+        let on_wire = YieldRecommendation {
+            use_hydradx: direct.use_hydradx,
+            use_interlay: direct.use_interlay,
+            hydradx_allocation_pct: direct.hydradx_allocation_pct,
+            interlay_allocation_pct: direct.interlay_allocation_pct,
+            projected_net_apy_bps: direct.projected_net_apy_bps,
+            expected_yield_dot: direct.expected_yield_dot,
+            ..YieldRecommendation::default()
+        };
+        assert_eq!(decoded, on_wire);
+    }
+
     /// Determinism: same input always produces same output bytes
     #[test]
     fn test_optimize_call_is_deterministic() {
@@ -197,4 +360,187 @@ mod tests {
         let result = call(&input);
         assert_eq!(result[31], 1u8, "1B DOT must succeed without overflow");
     }
+
+    /// Each listed selector is keccak256(signature)[..4].
+    #[test]
+    fn test_supported_functions_match_keccak_selectors() {
+        let functions = supported_functions();
+        assert_eq!(functions.len(), 3);
+        for (signature, selector) in functions {
+            let hash = sp_core::keccak_256(signature.as_bytes());
+            assert_eq!(&hash[..4], selector, "selector drift for {}", signature);
+        }
+        assert_eq!(functions[0].1, SEL_OPTIMIZE);
+        assert_eq!(functions[1].1, SEL_OPTIMIZE_BATCH);
+        assert_eq!(functions[2].1, SEL_SCHEMA);
+    }
+
+    fn build_batch_call(positions: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut input = SEL_OPTIMIZE_BATCH.to_vec();
+        input.extend(encode(&[Token::Array(positions.into_iter().map(Token::Bytes).collect())]));
+        input
+    }
+
+    /// Split a batch output into its per-position result blobs.
+    fn batch_results(output: &[u8]) -> Vec<Vec<u8>> {
+        let tokens = decode(
+            &[ParamType::Bool, ParamType::Array(Box::new(ParamType::Bytes))],
+            output,
+        )
+        .expect("batch output must decode");
+        assert_eq!(tokens[0], Token::Bool(true), "batch itself must succeed");
+        tokens[1]
+            .clone()
+            .into_array()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.into_bytes().unwrap())
+            .collect()
+    }
+
+    /// One valid and one zero-principal position: the first slot succeeds, the
+    /// second carries InvalidInput, and the batch as a whole does not fail.
+    #[test]
+    fn test_batch_slots_fail_independently() {
+        let valid = build_optimize_call(1_000 * PRECISION, 1_200, 900, 50, 100, 1_500, 2_500, 365);
+        let zero = build_optimize_call(0, 1_200, 900, 50, 100, 1_500, 2_500, 365);
+
+        let results = batch_results(&call(&build_batch_call(vec![
+            valid[4..].to_vec(),
+            zero[4..].to_vec(),
+        ])));
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], call(&valid), "slot must match a single optimize call");
+        assert_eq!(decode_call_result(&results[1]), Err(ERR_INVALID_INPUT));
+    }
+
+    /// An empty batch succeeds with no results.
+    #[test]
+    fn test_empty_batch() {
+        assert!(batch_results(&call(&build_batch_call(vec![]))).is_empty());
+    }
+
+    /// A batch above MAX_BATCH_SIZE is rejected outright.
+    #[test]
+    fn test_batch_above_cap_rejected() {
+        let valid = build_optimize_call(1_000 * PRECISION, 1_200, 900, 50, 100, 1_500, 2_500, 365);
+        let at_cap = vec![valid[4..].to_vec(); MAX_BATCH_SIZE];
+        assert_eq!(batch_results(&call(&build_batch_call(at_cap.clone()))).len(), MAX_BATCH_SIZE);
+
+        let mut over_cap = at_cap;
+        over_cap.push(valid[4..].to_vec());
+        assert_eq!(decode_call_result(&call(&build_batch_call(over_cap))), Err(ERR_INVALID_INPUT));
+    }
+
+    /// A malformed outer array fails the call with DecodeFailed.
+    #[test]
+    fn test_malformed_batch_returns_decode_failed() {
+        let result = call(&[SEL_OPTIMIZE_BATCH.to_vec(), vec![0xff; 7]].concat());
+        assert_eq!(decode_call_result(&result), Err(ERR_DECODE_FAILED));
+    }
+
+    /// NoViableDestination reaches Solidity as its own failure code.
+    #[test]
+    fn test_no_viable_destination_round_trips() {
+        let result = encode_error(optimizer_error_code(&OptimizerError::NoViableDestination));
+        assert_eq!(decode_call_result(&result), Err(ERR_NO_VIABLE_DESTINATION));
+        assert_ne!(ERR_NO_VIABLE_DESTINATION, ERR_INVALID_INPUT);
+    }
+
+    /// Every OptimizerError variant, including each wrapped MathError. The
+    /// matches have no wildcard, so adding a variant to either enum fails to
+    /// compile until it is listed here too.
+    fn all_optimizer_errors() -> Vec<OptimizerError> {
+        let math = [
+            MathError::InvalidInput,
+            MathError::Overflow,
+            MathError::Underflow,
+            MathError::DivisionByZero,
+            MathError::LengthMismatch,
+        ];
+        let mut all = vec![OptimizerError::InvalidInput, OptimizerError::NoViableDestination];
+        all.extend(math.into_iter().map(OptimizerError::Math));
+        for e in &all {
+            match e {
+                OptimizerError::InvalidInput | OptimizerError::NoViableDestination => {}
+                OptimizerError::Math(
+                    MathError::InvalidInput
+                    | MathError::Overflow
+                    | MathError::Underflow
+                    | MathError::DivisionByZero
+                    | MathError::LengthMismatch,
+                ) => {}
+            }
+        }
+        all
+    }
+
+    /// Each OptimizerError maps to the shared code named after its innermost
+    /// variant, so both precompiles agree on what every failure is called.
+    #[test]
+    fn test_optimizer_error_codes_match_shared_table() {
+        use crate::abi::error_codes::ALL;
+
+        for e in all_optimizer_errors() {
+            let code = optimizer_error_code(&e);
+            let expected = match &e {
+                OptimizerError::InvalidInput => "InvalidInput".to_string(),
+                OptimizerError::NoViableDestination => "NoViableDestination".to_string(),
+                OptimizerError::Math(m) => format!("{:?}", m),
+            };
+            let name = ALL.iter().find(|(c, _)| *c == code).map(|(_, n)| *n);
+            assert_eq!(name, Some(expected.as_str()), "{:?} maps to code {}", e, code);
+        }
+    }
+
+    /// Decode schema()'s (bool, (string, string)[]) output into pairs.
+    fn decoded_schema() -> Vec<(String, String)> {
+        let entry = ParamType::Tuple(vec![ParamType::String, ParamType::String]);
+        let output = call(&SEL_SCHEMA);
+        let mut tokens = decode(&[ParamType::Bool, ParamType::Array(Box::new(entry))], &output)
+            .expect("schema output must decode");
+        assert_eq!(tokens[0], Token::Bool(true));
+        tokens
+            .pop()
+            .and_then(Token::into_array)
+            .unwrap()
+            .into_iter()
+            .map(|pair| {
+                let mut pair = pair.into_tuple().unwrap().into_iter().map(|t| t.into_string().unwrap());
+                (pair.next().unwrap(), pair.next().unwrap())
+            })
+            .collect()
+    }
+
+    /// The schema starts with principal as uint128 and lists every wire field.
+    #[test]
+    fn test_schema_lists_fields_in_order() {
+        let schema = decoded_schema();
+        assert_eq!(schema.len(), 14);
+        assert_eq!(schema[0], ("principal".to_string(), "uint128".to_string()));
+        assert_eq!(schema[SCHEMA_INPUT_FIELDS], ("use_hydradx".to_string(), "bool".to_string()));
+        assert_eq!(schema.last().unwrap(), &("expected_yield_dot".to_string(), "uint128".to_string()));
+    }
+
+    /// The schema's types are the ones optimize() actually reads and writes:
+    /// calldata built from them decodes, and the output decodes with them.
+    #[test]
+    fn test_schema_matches_wire_format() {
+        let param = |ty: &str| match ty {
+            "bool" => ParamType::Bool,
+            uint => ParamType::Uint(uint.strip_prefix("uint").unwrap().parse().unwrap()),
+        };
+        let types: Vec<ParamType> = decoded_schema().iter().map(|(_, ty)| param(ty)).collect();
+        let (input_types, output_types) = types.split_at(SCHEMA_INPUT_FIELDS);
+
+        let input = build_optimize_call(1_000 * PRECISION, 1_200, 900, 50, 100, 1_500, 2_500, 365);
+        let args = decode(input_types, &input[4..]).expect("calldata must decode with the schema");
+        assert_eq!(encode(&args), input[4..]);
+        assert!(decode_optimizer_input(&encode(&args)).is_some());
+
+        let result = call(&input);
+        let outputs = decode(output_types, &result[32..]).expect("output must decode with the schema");
+        assert_eq!(encode(&outputs), result[32..]);
+    }
 }
\ No newline at end of file
@@ -16,7 +16,7 @@
 
 #[cfg(test)]
 mod compound_tests {
-    use crate::math_lib::{compound, MathError, BPS_DENOMINATOR, PRECISION};
+    use crate::math_lib::{compound, compound_steps, MathError, BPS_DENOMINATOR, PRECISION};
 
     /// Zero principal should always return 0 regardless of rate or periods.
     #[test]
@@ -135,11 +135,388 @@ mod compound_tests {
         let expected = 11_000 * PRECISION;
         assert_eq!(result, expected);
     }
+
+    /// The single-period fast path matches the general loop exactly,
+    /// including its overflow error and the zero-rate early return.
+    #[test]
+    fn test_single_period_fast_path_matches_loop() {
+        let cases = [
+            (0, 1_000),
+            (1, 1_000),
+            (PRECISION, 0),
+            (PRECISION, 1),
+            (1_000 * PRECISION, 1_200),
+            (123_456_789_012_345_678_901, 9_999),
+            (1_000_000_000 * PRECISION, u32::MAX),
+            (u128::MAX / 2, 0),
+            (u128::MAX, 0),
+            (0, u32::MAX),
+        ];
+        for (principal, rate_bps) in cases {
+            assert_eq!(
+                compound(principal, rate_bps, 1),
+                compound_steps(principal, rate_bps, 1, 1),
+                "principal {} rate {}",
+                principal,
+                rate_bps
+            );
+        }
+        assert_eq!(compound(u128::MAX, 1_000, 1), Err(MathError::Overflow));
+        assert_eq!(compound_steps(u128::MAX, 1_000, 1, 1), Err(MathError::Overflow));
+        // A zero rate returns the principal untouched, however large.
+        assert_eq!(compound(u128::MAX / 2, 0, 1), Ok(u128::MAX / 2));
+    }
+}
+
+#[cfg(test)]
+mod simple_interest_tests {
+    use crate::math_lib::{compound, simple_interest, PRECISION};
+
+    /// One period of simple interest is one step of compound interest.
+    #[test]
+    fn test_single_period_matches_compound() {
+        for rate_bps in [0, 1, 500, 1_000, 10_000] {
+            assert_eq!(
+                simple_interest(1_000 * PRECISION, rate_bps, 1),
+                compound(1_000 * PRECISION, rate_bps, 1)
+            );
+        }
+    }
+
+    /// Over many periods the full annual rate is paid once, with no
+    /// interest on interest, so it trails compound(). Per-period flooring
+    /// costs at most one unit a period.
+    #[test]
+    fn test_multi_period_pays_rate_once() {
+        let principal = 1_000 * PRECISION;
+        assert_eq!(simple_interest(principal, 1_000, 4).unwrap(), 1_100 * PRECISION);
+
+        let daily = simple_interest(principal, 1_000, 365).unwrap();
+        assert!(daily <= 1_100 * PRECISION && daily > 1_100 * PRECISION - 365);
+        assert!(daily < compound(principal, 1_000, 365).unwrap());
+    }
+
+    /// Zero principal, rate or periods leave the balance as is.
+    #[test]
+    fn test_zero_cases() {
+        assert_eq!(simple_interest(0, 1_000, 365), Ok(0));
+        assert_eq!(simple_interest(PRECISION, 0, 365), Ok(PRECISION));
+        assert_eq!(simple_interest(PRECISION, 1_000, 0), Ok(PRECISION));
+    }
+}
+
+#[cfg(test)]
+mod compound_with_fraction_tests {
+    use crate::math_lib::{compound, compound_with_fraction, MathError, BPS_DENOMINATOR, PRECISION};
+
+    /// A zero fraction reduces exactly to compound().
+    #[test]
+    fn test_zero_fraction_equals_compound() {
+        for (rate_bps, periods) in [(1_000, 365), (500, 12), (0, 365), (2_500, 1)] {
+            assert_eq!(
+                compound_with_fraction(1_000 * PRECISION, rate_bps, periods, 0).unwrap(),
+                compound(1_000 * PRECISION, rate_bps, periods).unwrap()
+            );
+        }
+    }
+
+    /// A trailing partial period adds a little yield beyond the whole periods,
+    /// but less than one more full period.
+    #[test]
+    fn test_fraction_adds_small_amount_of_yield() {
+        let principal = 1_000 * PRECISION;
+        let whole = compound(principal, 1_000, 365).unwrap();
+        let extended = compound_with_fraction(principal, 1_000, 365, 5_000).unwrap();
+        let full_extra_period = whole + whole * 1_000 / (BPS_DENOMINATOR * 365);
+
+        assert!(extended > whole, "fraction must add yield");
+        assert!(extended < full_extra_period, "half a period must earn less than a whole one");
+    }
+
+    /// Half a period earns half of the next full period's marginal interest.
+    #[test]
+    fn test_half_period_adds_half_marginal_yield() {
+        let principal = 1_000 * PRECISION;
+        let whole = compound(principal, 1_000, 365).unwrap();
+        let marginal = whole * 1_000 / (BPS_DENOMINATOR * 365);
+
+        let half = compound_with_fraction(principal, 1_000, 365, 5_000).unwrap() - whole;
+        assert!(half.abs_diff(marginal / 2) <= 1, "half {half}, marginal {marginal}");
+    }
+
+    /// Zero principal stays zero, fraction or not.
+    #[test]
+    fn test_zero_principal_returns_zero() {
+        assert_eq!(compound_with_fraction(0, 1_000, 365, 5_000).unwrap(), 0);
+    }
+
+    /// A fraction above one period, or a fraction with no whole period to
+    /// take it of, is invalid.
+    #[test]
+    fn test_invalid_fraction_returns_invalid_input() {
+        assert_eq!(
+            compound_with_fraction(PRECISION, 1_000, 365, 10_001),
+            Err(MathError::InvalidInput)
+        );
+        assert_eq!(
+            compound_with_fraction(PRECISION, 1_000, 0, 5_000),
+            Err(MathError::InvalidInput)
+        );
+    }
+}
+
+#[cfg(test)]
+mod compound_with_step_fee_tests {
+    use crate::math_lib::{compound, compound_with_step_fee, MathError, PRECISION};
+
+    /// A zero step fee matches compound() exactly.
+    #[test]
+    fn test_zero_fee_matches_compound() {
+        for (rate, periods) in [(1_000u32, 1u32), (1_200, 365), (900, 12), (50_000, 52)] {
+            assert_eq!(
+                compound_with_step_fee(1_000 * PRECISION, rate, periods, 0),
+                compound(1_000 * PRECISION, rate, periods)
+            );
+        }
+    }
+
+    /// Same nominal rate and fee: compounding more often pays the fee more
+    /// often and ends lower.
+    #[test]
+    fn test_more_frequent_compounding_pays_more_fees() {
+        let daily = compound_with_step_fee(1_000 * PRECISION, 1_200, 365, 1).unwrap();
+        let monthly = compound_with_step_fee(1_000 * PRECISION, 1_200, 12, 1).unwrap();
+        assert!(monthly > daily, "monthly {} must beat daily {}", monthly, daily);
+    }
+
+    /// A charge above the step's interest is capped — the balance never
+    /// drops below principal.
+    #[test]
+    fn test_charge_capped_at_interest() {
+        let principal = 1_000 * PRECISION;
+        assert_eq!(compound_with_step_fee(principal, 1_200, 365, 100), Ok(principal));
+    }
+
+    /// A fee above 100% is rejected.
+    #[test]
+    fn test_fee_above_denominator_rejected() {
+        assert_eq!(
+            compound_with_step_fee(1_000 * PRECISION, 1_200, 365, 10_001),
+            Err(MathError::InvalidInput)
+        );
+    }
+}
+
+#[cfg(test)]
+mod optimal_compounding_frequency_tests {
+    use crate::math_lib::{optimal_compounding_frequency, MathError, MAX_COMPOUNDING_PERIODS};
+
+    /// Without a fee, compounding as often as allowed is best.
+    #[test]
+    fn test_zero_fee_returns_max_periods() {
+        assert_eq!(optimal_compounding_frequency(1_000, 0, 365), Ok(365));
+        assert_eq!(optimal_compounding_frequency(1_000, 0, 1), Ok(1));
+    }
+
+    /// Any per-compound fee pulls the optimum below max_periods, close to
+    /// apy ÷ √(2 × fee): 10% APY at 1 bps per compound is about 7.
+    #[test]
+    fn test_fee_pulls_frequency_below_max() {
+        assert_eq!(optimal_compounding_frequency(1_000, 1, 365), Ok(7));
+        assert_eq!(optimal_compounding_frequency(5_000, 1, 365), Ok(35));
+    }
+
+    /// Higher fees favour less frequent compounding, down to once.
+    #[test]
+    fn test_higher_fee_compounds_less_often() {
+        let mut previous = u32::MAX;
+        for fee in [1, 2, 5, 10, 100] {
+            let periods = optimal_compounding_frequency(5_000, fee, 365).unwrap();
+            assert!(periods < previous, "fee {fee} gave {periods}");
+            previous = periods;
+        }
+        assert_eq!(optimal_compounding_frequency(1_000, 100, 365), Ok(1));
+    }
+
+    /// Zero periods and a fee above 100% are rejected.
+    #[test]
+    fn test_invalid_inputs() {
+        assert_eq!(optimal_compounding_frequency(1_000, 1, 0), Err(MathError::InvalidInput));
+        assert_eq!(optimal_compounding_frequency(1_000, 10_001, 365), Err(MathError::InvalidInput));
+    }
+
+    /// max_periods is accepted up to MAX_COMPOUNDING_PERIODS and rejected
+    /// above it, with or without a fee.
+    #[test]
+    fn test_max_periods_above_cap_rejected() {
+        assert_eq!(
+            optimal_compounding_frequency(1_000, 0, MAX_COMPOUNDING_PERIODS),
+            Ok(MAX_COMPOUNDING_PERIODS)
+        );
+        for fee in [0, 1] {
+            assert_eq!(
+                optimal_compounding_frequency(1_000, fee, MAX_COMPOUNDING_PERIODS + 1),
+                Err(MathError::InvalidInput)
+            );
+        }
+        assert_eq!(optimal_compounding_frequency(1_000, 1, u32::MAX), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod compound_schedule_tests {
+    use crate::math_lib::{compound, compound_steps, compound_schedule, PRECISION};
+
+    /// Every entry is the balance compound() reaches after that many steps.
+    #[test]
+    fn test_entries_track_compound() {
+        let p = 1_000 * PRECISION;
+        let schedule = compound_schedule(p, 1_200, 365).unwrap();
+        assert_eq!(schedule.len(), 365);
+        assert_eq!(*schedule.last().unwrap(), compound(p, 1_200, 365).unwrap());
+        for step in [1, 30, 182] {
+            assert_eq!(schedule[step - 1], compound_steps(p, 1_200, 365, step as u32).unwrap());
+        }
+    }
+
+    /// Zero periods give an empty schedule; a zero rate a flat one.
+    #[test]
+    fn test_degenerate_inputs() {
+        assert!(compound_schedule(PRECISION, 1_200, 0).unwrap().is_empty());
+        assert_eq!(compound_schedule(PRECISION, 0, 3).unwrap(), vec![PRECISION; 3]);
+    }
+}
+
+#[cfg(test)]
+mod net_schedule_tests {
+    use crate::math_lib::{compound_schedule, fee_adjusted_yield, net_schedule, MathError, PRECISION};
+
+    /// The last entry matches re-applying fee_adjusted_yield to each period's
+    /// interest, step by step.
+    #[test]
+    fn test_last_entry_matches_per_period_fee() {
+        let p = 1_000 * PRECISION;
+        let mut amount = p;
+        for _ in 0..365 {
+            let interest = amount * 1_200 / (10_000 * 365);
+            amount += fee_adjusted_yield(interest, 1_000).unwrap();
+        }
+        let schedule = net_schedule(p, 1_200, 1_000, 365).unwrap();
+        assert_eq!(*schedule.last().unwrap(), amount);
+    }
+
+    /// A zero fee reproduces the gross schedule exactly.
+    #[test]
+    fn test_zero_fee_equals_compound_schedule() {
+        let p = 1_000 * PRECISION;
+        assert_eq!(net_schedule(p, 1_200, 0, 365).unwrap(), compound_schedule(p, 1_200, 365).unwrap());
+    }
+
+    /// A fee keeps the curve between flat principal and the gross curve.
+    #[test]
+    fn test_net_curve_below_gross() {
+        let p = 1_000 * PRECISION;
+        let gross = compound_schedule(p, 1_200, 52).unwrap();
+        let net = net_schedule(p, 1_200, 2_500, 52).unwrap();
+        for (n, g) in net.iter().zip(&gross) {
+            assert!(p < *n && n < g);
+        }
+        assert_eq!(net_schedule(p, 1_200, 10_000, 52).unwrap(), vec![p; 52]);
+    }
+
+    /// A fee above 100% is rejected.
+    #[test]
+    fn test_fee_above_100_pct_rejected() {
+        assert_eq!(net_schedule(PRECISION, 1_200, 10_001, 12), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod cross_compound_tests {
+    use crate::math_lib::{compound, cross_compound, PRECISION};
+
+    /// Switching at 0 is pure secondary; at or past the window, pure primary.
+    #[test]
+    fn test_boundaries_reduce_to_compound() {
+        let p = 1_000 * PRECISION;
+        assert_eq!(cross_compound(p, 1_200, 900, 0, 365).unwrap(), compound(p, 900, 365).unwrap());
+        assert_eq!(cross_compound(p, 1_200, 900, 365, 365).unwrap(), compound(p, 1_200, 365).unwrap());
+        assert_eq!(cross_compound(p, 1_200, 900, 1_000, 365).unwrap(), compound(p, 1_200, 365).unwrap());
+    }
+
+    /// A mid-window switch lands strictly between the two pure results.
+    #[test]
+    fn test_intermediate_switch_is_between() {
+        let p = 1_000 * PRECISION;
+        let low = compound(p, 900, 365).unwrap();
+        let high = compound(p, 1_200, 365).unwrap();
+        let mut previous = low;
+        for switch in [90, 182, 300] {
+            let cross = cross_compound(p, 1_200, 900, switch, 365).unwrap();
+            assert!(low < cross && cross < high, "switch at {}", switch);
+            assert!(cross > previous, "a later switch means longer at the higher rate");
+            previous = cross;
+        }
+    }
+
+    /// Zero periods leave the principal untouched.
+    #[test]
+    fn test_zero_periods() {
+        assert_eq!(cross_compound(PRECISION, 1_200, 900, 0, 0).unwrap(), PRECISION);
+    }
+}
+
+#[cfg(test)]
+mod doubling_periods_tests {
+    use crate::math_lib::{compound_steps, doubling_periods, MathError, PRECISION};
+
+    /// At 100% APY compounded daily a balance doubles in 254 days — well
+    /// under a year, against the rule of 72's rough 72 ÷ 100 years.
+    #[test]
+    fn test_full_apy_daily_doubles_within_a_year() {
+        let days = doubling_periods(10_000, 365).unwrap();
+        assert_eq!(days, 254);
+        assert!(compound_steps(PRECISION, 10_000, 365, days).unwrap() >= 2 * PRECISION);
+        assert!(compound_steps(PRECISION, 10_000, 365, days - 1).unwrap() < 2 * PRECISION);
+    }
+
+    /// A higher rate always doubles sooner.
+    #[test]
+    fn test_higher_rate_doubles_sooner() {
+        let mut previous = u32::MAX;
+        for rate_bps in [100, 500, 1_000, 2_500, 5_000, 10_000, 50_000] {
+            let periods = doubling_periods(rate_bps, 365).unwrap();
+            assert!(periods < previous, "rate {} took {} periods", rate_bps, periods);
+            previous = periods;
+        }
+    }
+
+    /// A zero rate never doubles, and a zero-period year is rejected.
+    #[test]
+    fn test_edge_cases() {
+        assert_eq!(doubling_periods(0, 365), Ok(u32::MAX));
+        assert_eq!(doubling_periods(1_000, 0), Err(MathError::InvalidInput));
+        // One period a year at 100% doubles in exactly one step.
+        assert_eq!(doubling_periods(10_000, 1), Ok(1));
+    }
+
+    /// A 1 bps rate compounded every minute needs billions of periods; the
+    /// search counts them without stepping through each one.
+    #[test]
+    fn test_tiny_rate_many_periods_per_year() {
+        let minutes = doubling_periods(1, 525_600).unwrap();
+        // ln 2 × 525_600 × 10_000 ≈ 3.64e9 periods.
+        assert!((3_640_000_000..3_650_000_000).contains(&minutes), "{}", minutes);
+        assert!(doubling_periods(1, 8_760).unwrap() < minutes);
+    }
 }
 
 #[cfg(test)]
 mod annualize_tests {
-    use crate::math_lib::{annualize, MathError, SECONDS_PER_YEAR};
+    use crate::math_lib::{
+        annualize, annualize_fixed, realized_apy_bps, realized_apy_signed_bps, MathError,
+        BPS_DENOMINATOR, PRECISION, SECONDS_PER_YEAR,
+    };
 
     /// Annualising over exactly one year should return the input unchanged.
     #[test]
@@ -191,6 +568,69 @@ mod annualize_tests {
         let result = annualize(rate_bps, two_years).unwrap();
         assert_eq!(result, 500u32); // annualises to 5%
     }
+
+    /// A 0.00001% per-block rate is 0 BPS, so annualize() reports 0; at
+    /// PRECISION resolution it annualises to 52.56% over 6-second blocks.
+    #[test]
+    fn test_annualize_fixed_recovers_sub_bps_rate() {
+        let per_block = PRECISION / 10_000_000;
+        let per_block_bps = (per_block * BPS_DENOMINATOR / PRECISION) as u32;
+        assert_eq!(per_block_bps, 0);
+        assert_eq!(annualize(per_block_bps, 6).unwrap(), 0);
+
+        let annual = annualize_fixed(per_block, 6).unwrap();
+        assert_eq!(annual, per_block * 5_256_000);
+        assert_eq!(annual * BPS_DENOMINATOR / PRECISION, 5_256);
+    }
+
+    /// On BPS-representable rates annualize_fixed() agrees with annualize().
+    #[test]
+    fn test_annualize_fixed_matches_annualize() {
+        let to_fixed = |bps: u32| bps as u128 * PRECISION / BPS_DENOMINATOR;
+        assert_eq!(annualize_fixed(to_fixed(500), SECONDS_PER_YEAR as u64), Ok(to_fixed(500)));
+        let six_months = (SECONDS_PER_YEAR / 2) as u64;
+        let expected = to_fixed(annualize(400, six_months).unwrap());
+        assert_eq!(annualize_fixed(to_fixed(400), six_months), Ok(expected));
+    }
+
+    /// A zero-length window and a result past u128 are errors.
+    #[test]
+    fn test_annualize_fixed_errors() {
+        assert_eq!(annualize_fixed(PRECISION, 0), Err(MathError::DivisionByZero));
+        assert_eq!(annualize_fixed(u128::MAX, 6), Err(MathError::Overflow));
+    }
+
+    /// 10% over a year realises 1_000 BPS; over six months, 2_000.
+    #[test]
+    fn test_realized_apy_ten_percent_gain() {
+        let deposited = 1_000 * PRECISION;
+        let withdrawn = 1_100 * PRECISION;
+        assert_eq!(realized_apy_bps(deposited, withdrawn, SECONDS_PER_YEAR as u64), Ok(1_000));
+        assert_eq!(realized_apy_bps(deposited, withdrawn, (SECONDS_PER_YEAR / 2) as u64), Ok(2_000));
+        assert_eq!(realized_apy_bps(deposited, deposited, 86_400), Ok(0));
+    }
+
+    /// A loss is an Underflow for the unsigned form and negative for the
+    /// signed one, which agrees with the unsigned form on gains.
+    #[test]
+    fn test_realized_apy_loss_is_signed() {
+        let deposited = 1_000 * PRECISION;
+        let year = SECONDS_PER_YEAR as u64;
+        assert_eq!(realized_apy_bps(deposited, 900 * PRECISION, year), Err(MathError::Underflow));
+        assert_eq!(realized_apy_signed_bps(deposited, 900 * PRECISION, year), Ok(-1_000));
+        assert_eq!(realized_apy_signed_bps(deposited, 950 * PRECISION, year / 2), Ok(-1_000));
+        assert_eq!(realized_apy_signed_bps(deposited, 1_100 * PRECISION, year), Ok(1_000));
+    }
+
+    /// Nothing deposited or no time held cannot be annualised; a rate past
+    /// u32 BPS is an overflow.
+    #[test]
+    fn test_realized_apy_errors() {
+        assert_eq!(realized_apy_bps(0, PRECISION, 86_400), Err(MathError::DivisionByZero));
+        assert_eq!(realized_apy_signed_bps(0, 0, 86_400), Err(MathError::DivisionByZero));
+        assert_eq!(realized_apy_bps(PRECISION, 2 * PRECISION, 0), Err(MathError::DivisionByZero));
+        assert_eq!(realized_apy_bps(PRECISION, 1_000_000 * PRECISION, 6), Err(MathError::Overflow));
+    }
 }
 
 #[cfg(test)]
@@ -259,115 +699,496 @@ mod fee_adjusted_yield_tests {
 }
 
 #[cfg(test)]
-mod weighted_average_tests {
-    use crate::math_lib::{weighted_average, MathError};
+mod gross_from_net_yield_tests {
+    use crate::math_lib::{fee_adjusted_yield, gross_from_net_yield, MathError, BPS_DENOMINATOR, PRECISION};
 
-    /// Equal weights should return arithmetic mean.
+    /// Zero fee means net and gross are the same figure.
     #[test]
-    fn test_equal_weights_returns_mean() {
-        let values = [1_000u128, 2_000u128];
-        let weights = [1u128, 1u128];
-        let result = weighted_average(&values, &weights).unwrap();
-        assert_eq!(result, 1_500u128);
+    fn test_zero_fee_returns_net() {
+        assert_eq!(gross_from_net_yield(100 * PRECISION, 0).unwrap(), 100 * PRECISION);
     }
 
-    /// Single element — weighted average of one value is that value.
+    /// A 50% fee doubles the net figure back to gross.
     #[test]
-    fn test_single_element() {
-        let result = weighted_average(&[9_999u128], &[42u128]).unwrap();
-        assert_eq!(result, 9_999u128);
+    fn test_fifty_percent_fee_doubles_net() {
+        assert_eq!(gross_from_net_yield(100 * PRECISION, 5_000).unwrap(), 200 * PRECISION);
     }
 
-    /// Higher weight on higher value pulls average up.
+    /// A 100% fee leaves nothing to invert.
     #[test]
-    fn test_weight_bias_towards_higher_value() {
-        // 1000 at weight 1, 2000 at weight 3 → average = (1000 + 6000)/4 = 1750
-        let values = [1_000u128, 2_000u128];
-        let weights = [1u128, 3u128];
-        let result = weighted_average(&values, &weights).unwrap();
-        assert_eq!(result, 1_750u128);
+    fn test_hundred_percent_fee_returns_division_by_zero() {
+        assert_eq!(gross_from_net_yield(100 * PRECISION, 10_000), Err(MathError::DivisionByZero));
     }
 
-    /// Mismatched slice lengths must return InvalidInput.
+    /// Fee above 100% is invalid input, matching fee_adjusted_yield.
     #[test]
-    fn test_mismatched_lengths_returns_error() {
-        let result = weighted_average(&[1_000u128, 2_000u128], &[1u128]);
-        assert_eq!(result, Err(MathError::InvalidInput));
+    fn test_fee_above_100pct_returns_invalid_input() {
+        assert_eq!(gross_from_net_yield(100 * PRECISION, 10_001), Err(MathError::InvalidInput));
     }
 
-    /// Empty slices must return InvalidInput.
+    /// Round-tripping through fee_adjusted_yield recovers the gross figure to
+    /// within the rounding of the two floor divisions.
     #[test]
-    fn test_empty_slices_returns_error() {
-        let result = weighted_average(&[], &[]);
-        assert_eq!(result, Err(MathError::InvalidInput));
+    fn test_round_trip_recovers_gross() {
+        let grosses = [1, 999, 500 * PRECISION, 1_000 * PRECISION + 7, 123_456_789 * PRECISION];
+        for fee_bps in [0u32, 1, 50, 100, 2_500, 5_000, 9_999] {
+            // One wei of net can stand for up to BPS / (BPS - fee) wei of gross.
+            let tolerance = BPS_DENOMINATOR.div_ceil(BPS_DENOMINATOR - fee_bps as u128);
+            for gross in grosses {
+                let net = fee_adjusted_yield(gross, fee_bps).unwrap();
+                let recovered = gross_from_net_yield(net, fee_bps).unwrap();
+                assert!(
+                    recovered.abs_diff(gross) <= tolerance,
+                    "fee {fee_bps}: gross {gross} recovered as {recovered}"
+                );
+            }
+        }
     }
+}
 
-    /// All-zero weights must return DivisionByZero.
+#[cfg(test)]
+mod annual_fee_drag_tests {
+    use crate::math_lib::{annual_fee_drag_bps, compound, MathError, BPS_DENOMINATOR, PRECISION};
+
+    /// No fee, no drag.
     #[test]
-    fn test_zero_total_weight_returns_error() {
-        let result = weighted_average(&[1_000u128, 2_000u128], &[0u128, 0u128]);
-        assert_eq!(result, Err(MathError::DivisionByZero));
+    fn test_zero_fee_has_no_drag() {
+        assert_eq!(annual_fee_drag_bps(1_200, 0, 365).unwrap(), 0);
     }
 
-    /// Allocation scenario: 60/40 split between two APY BPS values.
+    /// No yield means nothing for the fee to take.
     #[test]
-    fn test_sixty_forty_split_blended_apy() {
-        // HydraDX: 1200 BPS at 60 weight, Interlay: 900 BPS at 40 weight
-        // Blended: (1200*60 + 900*40) / 100 = (72000 + 36000) / 100 = 1080
-        let values = [1_200u128, 900u128];
-        let weights = [60u128, 40u128];
-        let result = weighted_average(&values, &weights).unwrap();
-        assert_eq!(result, 1_080u128);
+    fn test_zero_rate_has_no_drag() {
+        assert_eq!(annual_fee_drag_bps(0, 500, 365).unwrap(), 0);
     }
 
-    /// Determinism: same inputs always produce same output.
+    /// A 1% fee on a 12% daily-compounded APY costs about 12.7 BPS a year.
     #[test]
-    fn test_determinism() {
-        let values = [500u128, 1500u128, 2500u128];
-        let weights = [10u128, 30u128, 60u128];
-        let r1 = weighted_average(&values, &weights).unwrap();
-        let r2 = weighted_average(&values, &weights).unwrap();
-        assert_eq!(r1, r2);
+    fn test_typical_fee_drag_is_positive() {
+        // Effective gross ≈ 1274.7 BPS; 1% of that ≈ 12.7 BPS.
+        assert_eq!(annual_fee_drag_bps(1_200, 100, 365).unwrap(), 12);
     }
-}
-
-#[cfg(test)]
-mod optimal_split_tests {
-    use crate::math_lib::{optimal_split, MathError};
 
-    /// Equal yield and equal risk should produce a 50/50 split.
+    /// Drag rises with the fee.
     #[test]
-    fn test_equal_yield_equal_risk_fifty_fifty() {
-        let (a, b) = optimal_split(1_000, 1_000, 1_000, 1_000).unwrap();
-        assert_eq!(a, 50);
-        assert_eq!(b, 50);
+    fn test_drag_increases_with_fee() {
+        let drags: Vec<u32> = [100, 500, 1_000, 2_500, 5_000, 10_000]
+            .iter()
+            .map(|&fee| annual_fee_drag_bps(1_200, fee, 365).unwrap())
+            .collect();
+        assert!(drags.windows(2).all(|w| w[0] < w[1]), "drag not increasing: {drags:?}");
     }
 
-    /// Split must always sum to exactly 100 — no rounding drift.
+    /// A 100% fee takes the whole effective gross yield rate.
     #[test]
-    fn test_allocation_always_sums_to_100() {
-        // Test multiple uneven cases
-        let cases = [
-            (1200u32, 800u32, 500u32, 2000u32),
-            (500, 1500, 100, 9000),
-            (1000, 1001, 0, 0),
-            (9999, 1, 0, 9999),
-            (800, 1200, 3000, 1000),
-        ];
-        for (ya, yb, ra, rb) in cases {
-            let (a, b) = optimal_split(ya, yb, ra, rb).unwrap();
-            assert_eq!(
-                a + b,
-                100,
-                "Split must sum to 100 for inputs ({ya},{yb},{ra},{rb}): got ({a},{b})"
-            );
-        }
+    fn test_full_fee_drag_equals_gross_yield_rate() {
+        let gross_bps = (compound(PRECISION, 1_200, 365).unwrap() - PRECISION) * BPS_DENOMINATOR / PRECISION;
+        assert_eq!(annual_fee_drag_bps(1_200, 10_000, 365).unwrap() as u128, gross_bps);
     }
 
-    /// Higher yield with zero risk should capture more allocation.
+    /// Fee above 100% is invalid input.
     #[test]
-    fn test_higher_yield_lower_risk_gets_more_allocation() {
-        // A: 2000 BPS, 0 risk. B: 1000 BPS, 0 risk.
+    fn test_fee_above_100pct_returns_invalid_input() {
+        assert_eq!(annual_fee_drag_bps(1_200, 10_001, 365), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod gas_adjusted_apy_tests {
+    use crate::math_lib::{gas_adjusted_apy_bps, MathError, PRECISION};
+
+    /// No gas cost leaves the APY untouched.
+    #[test]
+    fn test_zero_gas_unchanged() {
+        assert_eq!(gas_adjusted_apy_bps(1_200, 0, 1_000 * PRECISION), Ok(1_200));
+        assert_eq!(gas_adjusted_apy_bps(u32::MAX, 0, 1), Ok(u32::MAX));
+    }
+
+    /// A fixed 1 DOT cost costs ten times the BPS on a tenth of the principal.
+    #[test]
+    fn test_reduction_scales_inversely_with_principal() {
+        let gas = PRECISION;
+        assert_eq!(gas_adjusted_apy_bps(1_200, gas, 10_000 * PRECISION), Ok(1_199));
+        assert_eq!(gas_adjusted_apy_bps(1_200, gas, 1_000 * PRECISION), Ok(1_190));
+        assert_eq!(gas_adjusted_apy_bps(1_200, gas, 100 * PRECISION), Ok(1_100));
+        assert_eq!(gas_adjusted_apy_bps(1_200, gas, 10 * PRECISION), Ok(200));
+    }
+
+    /// A tiny position's gas swamps its yield and floors at 0.
+    #[test]
+    fn test_tiny_position_floors_at_zero() {
+        assert_eq!(gas_adjusted_apy_bps(1_200, PRECISION, PRECISION), Ok(0));
+        assert_eq!(gas_adjusted_apy_bps(1_200, u128::MAX, 1), Ok(0));
+    }
+
+    /// A zero principal has no rate to reduce.
+    #[test]
+    fn test_zero_principal_rejected() {
+        assert_eq!(gas_adjusted_apy_bps(1_200, PRECISION, 0), Err(MathError::DivisionByZero));
+    }
+}
+
+#[cfg(test)]
+mod net_yield_two_tier_tests {
+    use crate::math_lib::{fee_adjusted_yield, net_yield_two_tier, MathError, PRECISION};
+
+    /// Yield below the hurdle pays only the protocol fee.
+    #[test]
+    fn test_below_hurdle_no_performance_fee() {
+        // 100 DOT gross, 10% protocol fee → 90 DOT, under a 95 DOT hurdle.
+        let net = net_yield_two_tier(100 * PRECISION, 1_000, 2_000, 95 * PRECISION).unwrap();
+        assert_eq!(net, 90 * PRECISION);
+        assert_eq!(net, fee_adjusted_yield(100 * PRECISION, 1_000).unwrap());
+    }
+
+    /// Above the hurdle the performance fee applies to the excess only.
+    #[test]
+    fn test_above_hurdle_fee_on_excess_only() {
+        // 100 DOT gross → 90 after 10% protocol fee; 40 DOT above a 50 DOT
+        // hurdle pays 20% → 8 DOT. Net = 90 - 8 = 82 DOT.
+        let net = net_yield_two_tier(100 * PRECISION, 1_000, 2_000, 50 * PRECISION).unwrap();
+        assert_eq!(net, 82 * PRECISION);
+    }
+
+    /// Yield exactly at the hurdle pays no performance fee.
+    #[test]
+    fn test_at_hurdle_no_performance_fee() {
+        let net = net_yield_two_tier(100 * PRECISION, 0, 5_000, 100 * PRECISION).unwrap();
+        assert_eq!(net, 100 * PRECISION);
+    }
+
+    /// A zero hurdle charges the performance fee on all post-protocol yield.
+    #[test]
+    fn test_zero_hurdle_fee_on_everything() {
+        let net = net_yield_two_tier(100 * PRECISION, 1_000, 5_000, 0).unwrap();
+        assert_eq!(net, 45 * PRECISION);
+    }
+
+    /// Either fee above 100% is rejected.
+    #[test]
+    fn test_fee_above_100_pct_rejected() {
+        assert_eq!(net_yield_two_tier(PRECISION, 10_001, 0, 0), Err(MathError::InvalidInput));
+        assert_eq!(net_yield_two_tier(PRECISION, 0, 10_001, 0), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod checked_sum_tests {
+    use crate::math_lib::{checked_sum, MathError, PRECISION};
+
+    /// Ordinary DOT-scale values sum exactly.
+    #[test]
+    fn test_normal_sum() {
+        assert_eq!(
+            checked_sum(&[600 * PRECISION, 400 * PRECISION, 1]),
+            Ok(1_000 * PRECISION + 1)
+        );
+    }
+
+    /// The empty sum is 0.
+    #[test]
+    fn test_empty_is_zero() {
+        assert_eq!(checked_sum(&[]), Ok(0));
+    }
+
+    /// An overflow on the final element is reported, not wrapped.
+    #[test]
+    fn test_overflow_near_end() {
+        let mut values = vec![1u128; 64];
+        values.push(u128::MAX - 64);
+        assert_eq!(checked_sum(&values), Ok(u128::MAX));
+        values.push(1);
+        assert_eq!(checked_sum(&values), Err(MathError::Overflow));
+    }
+}
+
+#[cfg(test)]
+mod weighted_average_tests {
+    use crate::math_lib::{weighted_average, MathError};
+
+    /// Plain left-to-right u128 reference: Σ v × w ÷ Σ w.
+    fn sequential(values: &[u128], weights: &[u128]) -> Option<u128> {
+        let sum = values
+            .iter()
+            .zip(weights)
+            .try_fold(0u128, |sum, (v, w)| v.checked_mul(*w)?.checked_add(sum))?;
+        Some(sum / weights.iter().sum::<u128>())
+    }
+
+    /// Equal weights should return arithmetic mean.
+    #[test]
+    fn test_equal_weights_returns_mean() {
+        let values = [1_000u128, 2_000u128];
+        let weights = [1u128, 1u128];
+        let result = weighted_average(&values, &weights).unwrap();
+        assert_eq!(result, 1_500u128);
+    }
+
+    /// Single element — weighted average of one value is that value.
+    #[test]
+    fn test_single_element() {
+        let result = weighted_average(&[9_999u128], &[42u128]).unwrap();
+        assert_eq!(result, 9_999u128);
+    }
+
+    /// Higher weight on higher value pulls average up.
+    #[test]
+    fn test_weight_bias_towards_higher_value() {
+        // 1000 at weight 1, 2000 at weight 3 → average = (1000 + 6000)/4 = 1750
+        let values = [1_000u128, 2_000u128];
+        let weights = [1u128, 3u128];
+        let result = weighted_average(&values, &weights).unwrap();
+        assert_eq!(result, 1_750u128);
+    }
+
+    /// Mismatched slice lengths must return LengthMismatch.
+    #[test]
+    fn test_mismatched_lengths_returns_error() {
+        let result = weighted_average(&[1_000u128, 2_000u128], &[1u128]);
+        assert_eq!(result, Err(MathError::LengthMismatch));
+    }
+
+    /// One empty slice against a non-empty one is a mismatch, not empty input.
+    #[test]
+    fn test_one_empty_slice_is_length_mismatch() {
+        assert_eq!(weighted_average(&[], &[1u128]), Err(MathError::LengthMismatch));
+        assert_eq!(weighted_average(&[1u128], &[]), Err(MathError::LengthMismatch));
+    }
+
+    /// Empty slices must return InvalidInput.
+    #[test]
+    fn test_empty_slices_returns_error() {
+        let result = weighted_average(&[], &[]);
+        assert_eq!(result, Err(MathError::InvalidInput));
+    }
+
+    /// All-zero weights must return DivisionByZero.
+    #[test]
+    fn test_zero_total_weight_returns_error() {
+        let result = weighted_average(&[1_000u128, 2_000u128], &[0u128, 0u128]);
+        assert_eq!(result, Err(MathError::DivisionByZero));
+    }
+
+    /// Allocation scenario: 60/40 split between two APY BPS values.
+    #[test]
+    fn test_sixty_forty_split_blended_apy() {
+        // HydraDX: 1200 BPS at 60 weight, Interlay: 900 BPS at 40 weight
+        // Blended: (1200*60 + 900*40) / 100 = (72000 + 36000) / 100 = 1080
+        let values = [1_200u128, 900u128];
+        let weights = [60u128, 40u128];
+        let result = weighted_average(&values, &weights).unwrap();
+        assert_eq!(result, 1_080u128);
+    }
+
+    /// Determinism: same inputs always produce same output.
+    #[test]
+    fn test_determinism() {
+        let values = [500u128, 1500u128, 2500u128];
+        let weights = [10u128, 30u128, 60u128];
+        let r1 = weighted_average(&values, &weights).unwrap();
+        let r2 = weighted_average(&values, &weights).unwrap();
+        assert_eq!(r1, r2);
+    }
+
+    /// Short and long slices that fit in u128 give exactly the sequential result.
+    #[test]
+    fn test_parity_with_sequential_sum() {
+        let short = ([1_200u128, 900, 700], [60u128, 25, 15]);
+        assert_eq!(weighted_average(&short.0, &short.1).ok(), sequential(&short.0, &short.1));
+
+        let values: Vec<u128> = (1..=100).map(|i| i * 7_919 % 10_007).collect();
+        let weights: Vec<u128> = (1..=100).map(|i| i * 104_729 % 1_009 + 1).collect();
+        assert_eq!(weighted_average(&values, &weights).ok(), sequential(&values, &weights));
+    }
+
+    /// A long slice whose running u128 sum overflows part-way through still
+    /// averages correctly, as does one whose every product overflows.
+    #[test]
+    fn test_long_slice_survives_sum_overflow() {
+        let values = [1u128 << 120; 64];
+        let weights = [16u128; 64];
+        assert_eq!(sequential(&values, &weights), None);
+        assert_eq!(weighted_average(&values, &weights), Ok(1 << 120));
+
+        let mut values = vec![u128::MAX; 32];
+        values.extend([0u128; 32]);
+        let weights = [1u128 << 100; 64];
+        assert_eq!(weighted_average(&values, &weights), Ok(u128::MAX / 2));
+    }
+
+    /// The wide path rounds exactly like the sequential one would with
+    /// unbounded integers.
+    #[test]
+    fn test_wide_path_rounding() {
+        // Ten (2^127 - 1) × 3 products, then one 1 × 1.
+        let mut values = vec![(1u128 << 127) - 1; 10];
+        values.push(1);
+        let mut weights = vec![3u128; 10];
+        weights.push(1);
+        // Σ = 30 × (2^127 - 1) + 1, Σw = 31 → ⌊(30 × 2^127 - 29) ÷ 31⌋.
+        let expected = ((1u128 << 127) / 31) * 30 + ((1u128 << 127) % 31 * 30 - 29) / 31;
+        assert_eq!(weighted_average(&values, &weights), Ok(expected));
+    }
+
+    /// A total weight beyond u128 is still an overflow.
+    #[test]
+    fn test_total_weight_overflow() {
+        assert_eq!(weighted_average(&[1, 1], &[u128::MAX, 1]), Err(MathError::Overflow));
+    }
+}
+
+#[cfg(test)]
+mod portfolio_apy_tests {
+    use crate::math_lib::{portfolio_apy_bps, MathError, PRECISION};
+
+    /// Two positions blend by principal: 600 DOT at 1200 and 400 DOT at 900
+    /// give (1200 × 600 + 900 × 400) ÷ 1000 = 1080.
+    #[test]
+    fn test_two_positions_weighted_by_principal() {
+        let principals = [600 * PRECISION, 400 * PRECISION];
+        assert_eq!(portfolio_apy_bps(&principals, &[1_200, 900]), Ok(1_080));
+        assert_eq!(portfolio_apy_bps(&[PRECISION], &[1_500]), Ok(1_500));
+    }
+
+    /// Principals too large to multiply in u128 still blend, and the result
+    /// stays within the APY range.
+    #[test]
+    fn test_large_principals_do_not_overflow() {
+        let principals = [u128::MAX / 2, u128::MAX / 2];
+        assert_eq!(portfolio_apy_bps(&principals, &[u32::MAX, u32::MAX - 2]), Ok(u32::MAX - 1));
+    }
+
+    /// Mismatched lengths are a LengthMismatch; empty slices InvalidInput.
+    #[test]
+    fn test_rejects_malformed_slices() {
+        assert_eq!(portfolio_apy_bps(&[PRECISION, PRECISION], &[1_000]), Err(MathError::LengthMismatch));
+        assert_eq!(portfolio_apy_bps(&[], &[1_000]), Err(MathError::LengthMismatch));
+        assert_eq!(portfolio_apy_bps(&[], &[]), Err(MathError::InvalidInput));
+        assert_eq!(portfolio_apy_bps(&[0, 0], &[1_000, 900]), Err(MathError::DivisionByZero));
+    }
+}
+
+#[cfg(test)]
+mod percentile_tests {
+    use crate::math_lib::{percentile, MathError};
+
+    const SAMPLE: [u128; 9] = [900, 100, 500, 300, 700, 200, 800, 400, 600];
+
+    /// The 0th percentile is the minimum.
+    #[test]
+    fn test_zeroth_percentile_is_minimum() {
+        assert_eq!(percentile(&SAMPLE, 0).unwrap(), 100);
+    }
+
+    /// The 50th percentile of an odd-length sample is its median.
+    #[test]
+    fn test_fiftieth_percentile_is_median() {
+        assert_eq!(percentile(&SAMPLE, 5_000).unwrap(), 500);
+        // Even length: nearest rank picks the lower middle element.
+        assert_eq!(percentile(&[40, 10, 30, 20], 5_000).unwrap(), 20);
+    }
+
+    /// The 100th percentile is the maximum.
+    #[test]
+    fn test_hundredth_percentile_is_maximum() {
+        assert_eq!(percentile(&SAMPLE, 10_000).unwrap(), 900);
+    }
+
+    /// Nearest rank rounds up to the next whole element.
+    #[test]
+    fn test_nearest_rank_rounds_up() {
+        // 9 × 0.25 = 2.25 → rank 3.
+        assert_eq!(percentile(&SAMPLE, 2_500).unwrap(), 300);
+        // 9 × 0.90 = 8.1 → rank 9.
+        assert_eq!(percentile(&SAMPLE, 9_000).unwrap(), 900);
+    }
+
+    /// Duplicates, reverse order and a single element all sort correctly.
+    #[test]
+    fn test_sort_handles_duplicates_and_order() {
+        let reversed: Vec<u128> = (1..=100).rev().collect();
+        assert_eq!(percentile(&reversed, 1_000).unwrap(), 10);
+        assert_eq!(percentile(&[7, 7, 3, 7, 3], 5_000).unwrap(), 7);
+        assert_eq!(percentile(&[u128::MAX], 5_000).unwrap(), u128::MAX);
+    }
+
+    /// An empty sample or a percentile above 100% is invalid input.
+    #[test]
+    fn test_invalid_inputs() {
+        assert_eq!(percentile(&[], 5_000), Err(MathError::InvalidInput));
+        assert_eq!(percentile(&SAMPLE, 10_001), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod optimal_split_tests {
+    use crate::math_lib::{
+        optimal_split, optimal_split_prefactored, required_yield_premium_bps, risk_adjusted_yields,
+        risk_factor_bps, risk_from_spread_bps, scaled_risk, MathError, MAX_RISK_SCORE,
+        MAX_RISK_SPREAD_BPS,
+    };
+    use proptest::prelude::*;
+
+    /// Equal yield and equal risk should produce a 50/50 split.
+    #[test]
+    fn test_equal_yield_equal_risk_fifty_fifty() {
+        let (a, b) = optimal_split(1_000, 1_000, 1_000, 1_000).unwrap();
+        assert_eq!(a, 50);
+        assert_eq!(b, 50);
+    }
+
+    /// Split must always sum to exactly 100 — no rounding drift.
+    #[test]
+    fn test_allocation_always_sums_to_100() {
+        // Test multiple uneven cases
+        let cases = [
+            (1200u32, 800u32, 500u32, 2000u32),
+            (500, 1500, 100, 9000),
+            (1000, 1001, 0, 0),
+            (9999, 1, 0, 9999),
+            (800, 1200, 3000, 1000),
+        ];
+        for (ya, yb, ra, rb) in cases {
+            let (a, b) = optimal_split(ya, yb, ra, rb).unwrap();
+            assert_eq!(
+                a + b,
+                100,
+                "Split must sum to 100 for inputs ({ya},{yb},{ra},{rb}): got ({a},{b})"
+            );
+        }
+    }
+
+    proptest! {
+        /// Property form of the sum-to-100 invariant over the full u32 yield
+        /// range, with risk scores straddling MAX_RISK_SCORE so the
+        /// out-of-range error path is exercised too.
+        #[test]
+        fn prop_split_sums_to_100_or_rejects_risk(
+            yield_a in any::<u32>(),
+            yield_b in any::<u32>(),
+            risk_a in 0u32..=12_000,
+            risk_b in 0u32..=12_000,
+        ) {
+            let risk_in_range =
+                risk_a as u128 <= MAX_RISK_SCORE && risk_b as u128 <= MAX_RISK_SCORE;
+            match optimal_split(yield_a, yield_b, risk_a, risk_b) {
+                Ok((a, b)) => {
+                    prop_assert!(risk_in_range);
+                    prop_assert!(a <= 100 && b <= 100);
+                    prop_assert_eq!(a + b, 100);
+                }
+                Err(e) => {
+                    prop_assert!(!risk_in_range);
+                    prop_assert_eq!(e, MathError::InvalidInput);
+                }
+            }
+        }
+    }
+
+    /// Higher yield with zero risk should capture more allocation.
+    #[test]
+    fn test_higher_yield_lower_risk_gets_more_allocation() {
+        // A: 2000 BPS, 0 risk. B: 1000 BPS, 0 risk.
         // A should get 2/3 (~66%), B should get 1/3 (~33%)
         let (a, b) = optimal_split(2_000, 1_000, 0, 0).unwrap();
         assert!(a > b, "Higher yield destination should get more allocation");
@@ -375,268 +1196,3113 @@ mod optimal_split_tests {
         assert_eq!(b, 34);
     }
 
-    /// Very high risk on A should push allocation toward B even if A has higher yield.
+    /// Very high risk on A should push allocation toward B even if A has higher yield.
+    #[test]
+    fn test_high_risk_reduces_allocation() {
+        // A: 2000 BPS but MAX risk (10000). B: 500 BPS, 0 risk.
+        // Risk-adjusted A: 2000 * (10000 - 10000) / 10000 = 0
+        // Risk-adjusted B: 500 * (10000 - 0) / 10000 = 500
+        // Split: A=0, B=100
+        let (a, b) = optimal_split(2_000, 500, 10_000, 0).unwrap();
+        assert_eq!(a, 0);
+        assert_eq!(b, 100);
+    }
+
+    /// Both destinations at maximum risk should trigger the 50/50 fallback.
+    #[test]
+    fn test_both_max_risk_triggers_fifty_fifty_fallback() {
+        let (a, b) = optimal_split(1_000, 2_000, 10_000, 10_000).unwrap();
+        assert_eq!(a, 50);
+        assert_eq!(b, 50);
+    }
+
+    /// Zero yield on both destinations should trigger the 50/50 fallback.
+    #[test]
+    fn test_both_zero_yield_triggers_fifty_fifty_fallback() {
+        let (a, b) = optimal_split(0, 0, 500, 500).unwrap();
+        assert_eq!(a, 50);
+        assert_eq!(b, 50);
+    }
+
+    /// Risk score above MAX_RISK_SCORE must return InvalidInput.
+    #[test]
+    fn test_risk_above_max_returns_invalid_input() {
+        let result = optimal_split(1_000, 1_000, 10_001, 0);
+        assert_eq!(result, Err(MathError::InvalidInput));
+    }
+
+    /// Zero risk on both sides means allocation is purely yield-proportional.
+    #[test]
+    fn test_zero_risk_pure_yield_allocation() {
+        // 3000 vs 1000 BPS at zero risk → 75/25 split
+        let (a, b) = optimal_split(3_000, 1_000, 0, 0).unwrap();
+        assert_eq!(a, 75);
+        assert_eq!(b, 25);
+    }
+
+    /// Full aversion leaves a score alone, zero removes it, and over-aversion
+    /// inflates it up to MAX_RISK_SCORE.
+    #[test]
+    fn test_scaled_risk() {
+        assert_eq!(scaled_risk(2_500, 10_000).unwrap(), 2_500);
+        assert_eq!(scaled_risk(2_500, 0).unwrap(), 0);
+        assert_eq!(scaled_risk(2_500, 20_000).unwrap(), 5_000);
+        assert_eq!(scaled_risk(6_000, 20_000).unwrap(), MAX_RISK_SCORE as u32);
+        assert_eq!(scaled_risk(10_001, 0), Err(MathError::InvalidInput));
+    }
+
+    /// A riskless destination keeps its whole yield; a max-risk one keeps none.
+    #[test]
+    fn test_risk_factor_bounds() {
+        assert_eq!(risk_factor_bps(0).unwrap(), 10_000);
+        assert_eq!(risk_factor_bps(2_500).unwrap(), 7_500);
+        assert_eq!(risk_factor_bps(10_000).unwrap(), 0);
+        assert_eq!(risk_factor_bps(10_001), Err(MathError::InvalidInput));
+    }
+
+    /// Splitting on precomputed factors gives the same result as splitting
+    /// on the risk scores they came from.
+    #[test]
+    fn test_prefactored_split_matches_regular() {
+        for (yield_a, yield_b) in [(1_200, 900), (0, 0), (1, 9_999), (u32::MAX, u32::MAX)] {
+            for risk_a in (0..=10_000).step_by(1_250) {
+                for risk_b in (0..=10_000).step_by(2_000) {
+                    let prefactored = optimal_split_prefactored(
+                        yield_a,
+                        yield_b,
+                        risk_factor_bps(risk_a).unwrap(),
+                        risk_factor_bps(risk_b).unwrap(),
+                    );
+                    assert_eq!(prefactored, optimal_split(yield_a, yield_b, risk_a, risk_b));
+                }
+            }
+        }
+    }
+
+    /// A factor above 100% is rejected like an out-of-range risk score.
+    #[test]
+    fn test_prefactored_rejects_factor_above_bps() {
+        assert_eq!(optimal_split_prefactored(1_000, 1_000, 10_001, 0), Err(MathError::InvalidInput));
+    }
+
+    /// Spread maps linearly onto the risk range: zero is riskless, the full
+    /// spread and anything wider is maximum risk.
+    #[test]
+    fn test_risk_from_spread_anchors() {
+        assert_eq!(risk_from_spread_bps(0).unwrap(), 0);
+        assert_eq!(risk_from_spread_bps(250).unwrap(), 2_500);
+        assert_eq!(risk_from_spread_bps(MAX_RISK_SPREAD_BPS as u32).unwrap(), MAX_RISK_SCORE as u32);
+        assert_eq!(risk_from_spread_bps(u32::MAX).unwrap(), MAX_RISK_SCORE as u32);
+    }
+
+    proptest! {
+        /// Wider spreads are never less risky, and every score is a valid
+        /// optimal_split input.
+        #[test]
+        fn prop_risk_from_spread_monotonic_and_in_range(
+            narrow in any::<u32>(),
+            widen_by in any::<u32>(),
+        ) {
+            let wide = narrow.saturating_add(widen_by);
+            let (narrow_risk, wide_risk) =
+                (risk_from_spread_bps(narrow).unwrap(), risk_from_spread_bps(wide).unwrap());
+            prop_assert!(narrow_risk <= wide_risk);
+            prop_assert!(wide_risk as u128 <= MAX_RISK_SCORE);
+            prop_assert!(optimal_split(1_200, 900, narrow_risk, wide_risk).is_ok());
+        }
+    }
+
+    /// Below the clamp, every extra basis point of spread raises the risk.
+    #[test]
+    fn test_risk_from_spread_strictly_increasing_below_clamp() {
+        let risks: Vec<u32> =
+            (0..=MAX_RISK_SPREAD_BPS as u32).map(|s| risk_from_spread_bps(s).unwrap()).collect();
+        assert!(risks.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    /// The premium is exactly what it takes: with it A and B tie 50/50, and
+    /// one basis point less tips the split toward B.
+    #[test]
+    fn test_required_premium_produces_exact_tie() {
+        let cases = [(900, 2_500, 1_500), (1_000, 5_000, 0), (777, 3_333, 1_111), (1, 9_999, 0)];
+        for (base, risk_a, risk_b) in cases {
+            let premium = required_yield_premium_bps(base, risk_a, risk_b).unwrap();
+            assert!(premium > 0);
+            assert_eq!(optimal_split(base + premium, base, risk_a, risk_b).unwrap(), (50, 50));
+            assert!(optimal_split(base + premium - 1, base, risk_a, risk_b).unwrap().0 < 50);
+        }
+    }
+
+    /// A no riskier than B needs no premium.
+    #[test]
+    fn test_required_premium_zero_when_a_safer() {
+        assert_eq!(required_yield_premium_bps(900, 1_500, 2_500), Ok(0));
+        assert_eq!(required_yield_premium_bps(900, 2_000, 2_000), Ok(0));
+        assert_eq!(optimal_split(900, 900, 2_000, 2_000).unwrap(), (50, 50));
+    }
+
+    /// Degenerate cases: nothing to match, nothing can match, bad scores.
+    #[test]
+    fn test_required_premium_edges() {
+        assert_eq!(required_yield_premium_bps(0, 5_000, 0), Ok(0));
+        assert_eq!(required_yield_premium_bps(900, 10_000, 10_000), Ok(0));
+        assert_eq!(required_yield_premium_bps(900, 10_000, 0), Ok(u32::MAX));
+        assert_eq!(required_yield_premium_bps(900, 10_001, 0), Err(MathError::InvalidInput));
+        assert_eq!(required_yield_premium_bps(900, 0, 10_001), Err(MathError::InvalidInput));
+    }
+
+    /// A max-risk destination's adjusted yield is zero whatever its APY.
+    #[test]
+    fn test_risk_adjusted_yields_max_risk_is_zero() {
+        assert_eq!(risk_adjusted_yields(5_000, 1_000, 10_000, 0), Ok((0, 1_000)));
+        assert_eq!(risk_adjusted_yields(1_200, 900, 1_500, 2_500), Ok((1_020, 675)));
+        assert_eq!(risk_adjusted_yields(1_000, 1_000, 10_001, 0), Err(MathError::InvalidInput));
+    }
+
+    /// The split is the adjusted yields' ratio, floored to whole percentages.
+    #[test]
+    fn test_risk_adjusted_yields_ratio_matches_split() {
+        let cases = [(1_200, 900, 1_500, 2_500), (2_000, 500, 500, 4_000), (800, 1_200, 1_000, 3_000)];
+        for (ya, yb, ra, rb) in cases {
+            let (adj_a, adj_b) = risk_adjusted_yields(ya, yb, ra, rb).unwrap();
+            let (pct_a, pct_b) = optimal_split(ya, yb, ra, rb).unwrap();
+            let total = adj_a + adj_b;
+            assert_eq!(pct_a as u128, adj_a * 100 / total);
+            // pct_a : pct_b equals adj_a : adj_b up to under one point of flooring.
+            let (cross_a, cross_b) = (pct_a as u128 * adj_b, pct_b as u128 * adj_a);
+            assert!(cross_a <= cross_b && cross_b - cross_a < total);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tiebreak_tests {
+    use crate::math_lib::{deterministic_tiebreak, optimal_split, optimal_split_with_tiebreak};
+
+    fn seed(n: u8) -> [u8; 32] {
+        let mut s = [0u8; 32];
+        s[31] = n;
+        s
+    }
+
+    /// The same seed always lands on the same side.
+    #[test]
+    fn test_same_seed_same_side() {
+        for n in 0..16u8 {
+            assert_eq!(deterministic_tiebreak(seed(n)), deterministic_tiebreak(seed(n)));
+        }
+    }
+
+    /// Different seeds reach both sides.
+    #[test]
+    fn test_different_seeds_can_differ() {
+        let sides: Vec<bool> = (0..16u8).map(|n| deterministic_tiebreak(seed(n))).collect();
+        assert!(sides.contains(&true) && sides.contains(&false));
+    }
+
+    /// An exact tie with a seed goes wholly to the picked side.
+    #[test]
+    fn test_exact_tie_uses_seed() {
+        for n in 0..16u8 {
+            let split = optimal_split_with_tiebreak(1_000, 1_000, 2_000, 2_000, Some(seed(n))).unwrap();
+            let expected = if deterministic_tiebreak(seed(n)) { (100, 0) } else { (0, 100) };
+            assert_eq!(split, expected);
+        }
+    }
+
+    /// Without a seed, or without a tie, the split is the ordinary one.
+    #[test]
+    fn test_opt_in_only() {
+        assert_eq!(optimal_split_with_tiebreak(1_000, 1_000, 2_000, 2_000, None), Ok((50, 50)));
+        assert_eq!(
+            optimal_split_with_tiebreak(3_000, 1_000, 0, 0, Some(seed(1))),
+            optimal_split(3_000, 1_000, 0, 0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod min_variance_split_tests {
+    use crate::math_lib::{min_variance_split, MathError};
+
+    /// Textbook example: σ_a = 10%, σ_b = 20%, uncorrelated. Variances are
+    /// 100 and 400 (in %²), so w_a = 400 / 500 = 80%.
+    #[test]
+    fn test_uncorrelated_textbook_example() {
+        assert_eq!(min_variance_split(100, 400, 0).unwrap(), (80, 20));
+    }
+
+    /// σ_a = 20%, σ_b = 30%, ρ = 0.2: cov = 0.2 × 20 × 30 = 120, so
+    /// w_a = (900 - 120) / (400 + 900 - 240) = 780 / 1060 ≈ 73.6%.
+    #[test]
+    fn test_correlated_textbook_example() {
+        assert_eq!(min_variance_split(400, 900, 120).unwrap(), (73, 27));
+    }
+
+    /// Equal variances with zero covariance is the degenerate 50/50 case.
+    #[test]
+    fn test_equal_variance_zero_covariance_is_even() {
+        assert_eq!(min_variance_split(250, 250, 0).unwrap(), (50, 50));
+        assert_eq!(min_variance_split(0, 0, 0).unwrap(), (50, 50));
+    }
+
+    /// Identical, perfectly correlated assets leave a zero denominator.
+    #[test]
+    fn test_perfectly_correlated_identical_assets_is_even() {
+        assert_eq!(min_variance_split(400, 400, 400).unwrap(), (50, 50));
+    }
+
+    /// Weights always sum to 100 and lean toward the lower-variance asset.
+    #[test]
+    fn test_weights_sum_to_100_and_favour_lower_variance() {
+        for (var_a, var_b, cov) in [(100, 400, 0), (400, 900, 120), (333, 777, 50), (1, 3, 1)] {
+            let (a, b) = min_variance_split(var_a, var_b, cov).unwrap();
+            assert_eq!(a + b, 100);
+            assert!(a >= b, "lower-variance asset a got {a}%");
+
+            let (b_rev, a_rev) = min_variance_split(var_b, var_a, cov).unwrap();
+            assert_eq!(a_rev + b_rev, 100);
+            assert!(a_rev >= b_rev, "swapped order must still favour the same asset");
+        }
+    }
+
+    /// Covariance above the smaller variance would short the riskier asset;
+    /// long-only, everything goes to the lower-variance side.
+    #[test]
+    fn test_high_covariance_goes_all_in_on_lower_variance() {
+        assert_eq!(min_variance_split(100, 900, 200).unwrap(), (100, 0));
+        assert_eq!(min_variance_split(900, 100, 200).unwrap(), (0, 100));
+    }
+
+    /// A covariance no real pair of assets could have is rejected.
+    #[test]
+    fn test_impossible_covariance_is_invalid() {
+        assert_eq!(min_variance_split(100, 100, 101), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod validate_allocation_tests {
+    use crate::math_lib::{validate_allocation, MathError};
+
+    /// Shares summing to exactly 100 pass, including a zero element.
+    #[test]
+    fn test_valid_distribution() {
+        assert_eq!(validate_allocation(&[60, 40]), Ok(()));
+        assert_eq!(validate_allocation(&[100, 0, 0]), Ok(()));
+        assert_eq!(validate_allocation(&[33, 33, 34]), Ok(()));
+    }
+
+    /// A distribution one short of 100 is rejected.
+    #[test]
+    fn test_sum_99_rejected() {
+        assert_eq!(validate_allocation(&[60, 39]), Err(MathError::InvalidInput));
+    }
+
+    /// A distribution one over 100 is rejected.
+    #[test]
+    fn test_sum_101_rejected() {
+        assert_eq!(validate_allocation(&[61, 40]), Err(MathError::InvalidInput));
+    }
+
+    /// An element above 100 is rejected even if the total would wrap to 100.
+    #[test]
+    fn test_element_above_100_rejected() {
+        assert_eq!(validate_allocation(&[101, 0]), Err(MathError::InvalidInput));
+        assert_eq!(
+            validate_allocation(&[u64::MAX, 101]),
+            Err(MathError::InvalidInput)
+        );
+    }
+
+    /// An empty allocation distributes nothing and is rejected.
+    #[test]
+    fn test_empty_rejected() {
+        assert_eq!(validate_allocation(&[]), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod validate_destination_tests {
+    use crate::math_lib::{validate_destination, MathError};
+
+    /// Ordinary destinations pass, including the range boundaries.
+    #[test]
+    fn test_valid_destinations_pass() {
+        assert_eq!(validate_destination(1_200, 50, 1_500), Ok(()));
+        assert_eq!(validate_destination(0, 0, 0), Ok(()));
+        assert_eq!(validate_destination(0, 0, 10_000), Ok(()));
+        assert_eq!(validate_destination(u32::MAX, 9_999, 10_000), Ok(()));
+    }
+
+    /// A 100% fee is allowed on a positive APY, and on a riskless zero APY.
+    #[test]
+    fn test_full_fee_allowed_when_not_contradictory() {
+        assert_eq!(validate_destination(1_500, 10_000, 1_000), Ok(()));
+        assert_eq!(validate_destination(0, 10_000, 0), Ok(()));
+    }
+
+    /// A risk score above MAX_RISK_SCORE is rejected.
+    #[test]
+    fn test_risk_above_max_rejected() {
+        assert_eq!(validate_destination(1_200, 50, 10_001), Err(MathError::InvalidInput));
+        assert_eq!(validate_destination(0, 0, u32::MAX), Err(MathError::InvalidInput));
+    }
+
+    /// A fee above 100% is rejected.
+    #[test]
+    fn test_fee_above_100_pct_rejected() {
+        assert_eq!(validate_destination(1_200, 10_001, 0), Err(MathError::InvalidInput));
+    }
+
+    /// A leg that can never yield cannot carry risk.
+    #[test]
+    fn test_risky_dead_leg_rejected() {
+        assert_eq!(validate_destination(0, 10_000, 1), Err(MathError::InvalidInput));
+        assert_eq!(validate_destination(0, 10_000, 10_000), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod split_principal_n_tests {
+    use crate::math_lib::{split_principal_n, MathError, PRECISION};
+
+    /// Three legs that do not divide evenly still sum to exactly the principal,
+    /// with the remainder on the largest leg.
+    #[test]
+    fn test_three_legs_no_dust() {
+        let amounts = split_principal_n(1_000_001, &[33, 33, 34]).unwrap();
+        assert_eq!(amounts, vec![330_000, 330_000, 340_001]);
+        assert_eq!(amounts.iter().sum::<u128>(), 1_000_001);
+
+        let principal = 10 * PRECISION + 7;
+        let amounts = split_principal_n(principal, &[17, 51, 32]).unwrap();
+        assert_eq!(amounts.iter().sum::<u128>(), principal);
+        assert_eq!(amounts[0], principal * 17 / 100);
+        assert_eq!(amounts[2], principal * 32 / 100);
+    }
+
+    /// On a tie for largest, the first tied leg takes the remainder.
+    #[test]
+    fn test_remainder_tie_goes_to_first() {
+        assert_eq!(split_principal_n(101, &[20, 40, 40]).unwrap(), vec![20, 41, 40]);
+    }
+
+    /// Exact splits and single legs need no adjustment.
+    #[test]
+    fn test_exact_and_single_leg() {
+        assert_eq!(split_principal_n(1_000, &[25, 25, 50]).unwrap(), vec![250, 250, 500]);
+        assert_eq!(split_principal_n(7, &[100]).unwrap(), vec![7]);
+        assert_eq!(split_principal_n(3, &[0, 100, 0]).unwrap(), vec![0, 3, 0]);
+        assert_eq!(split_principal_n(u128::MAX, &[50, 50]).unwrap().iter().sum::<u128>(), u128::MAX);
+    }
+
+    /// Percentages that do not form a whole allocation are rejected.
+    #[test]
+    fn test_rejects_incomplete_allocation() {
+        assert_eq!(split_principal_n(100, &[30, 30, 30]), Err(MathError::InvalidInput));
+        assert_eq!(split_principal_n(100, &[60, 50]), Err(MathError::InvalidInput));
+        assert_eq!(split_principal_n(100, &[]), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod projected_drift_tests {
+    use crate::math_lib::{projected_drift, MathError, PRECISION};
+
+    /// The faster-growing leg's share rises, and rises further with time.
+    #[test]
+    fn test_drift_toward_faster_leg() {
+        let p = 1_000 * PRECISION;
+        let (h1, i1) = projected_drift(p, 50, 5_000, 500, 365).unwrap();
+        assert!(h1 > 50 && h1 + i1 == 100, "({h1}, {i1})");
+
+        // A wider rate gap drifts further.
+        let (h2, _) = projected_drift(p, 50, 10_000, 500, 365).unwrap();
+        assert!(h2 > h1);
+
+        let (h3, i3) = projected_drift(p, 50, 500, 5_000, 365).unwrap();
+        assert_eq!((h3, i3), (i1, h1), "swapping the rates mirrors the drift");
+    }
+
+    /// Equal rates leave every split where it started.
+    #[test]
+    fn test_equal_rates_no_drift() {
+        for pct in [0, 1, 33, 40, 50, 60, 99, 100] {
+            assert_eq!(projected_drift(1_000 * PRECISION, pct, 1_200, 1_200, 365), Ok((pct, 100 - pct)));
+        }
+        // A principal that does not split into whole units still holds.
+        assert_eq!(projected_drift(PRECISION + 7, 40, 900, 900, 12), Ok((40, 60)));
+        assert_eq!(projected_drift(0, 40, 900, 100, 12), Ok((40, 60)));
+    }
+
+    /// A HydraDX share above 100 is rejected.
+    #[test]
+    fn test_pct_above_100_rejected() {
+        assert_eq!(projected_drift(PRECISION, 101, 1_000, 1_000, 12), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod fixed_point_tests {
+    use crate::math_lib::{
+        fee_adjusted_yield, from_fixed, to_fixed, FixedPoint, MathError, PRECISION,
+    };
+
+    /// A 6-decimal context uses 10^6 units per token.
+    #[test]
+    fn test_six_decimal_context() {
+        let usdc = FixedPoint::new(6).unwrap();
+        assert_eq!(usdc.decimals(), 6);
+        assert_eq!(usdc.unit(), 1_000_000);
+        assert_eq!(usdc.to_fixed(250).unwrap(), 250_000_000);
+        assert_eq!(usdc.from_fixed(250_999_999), 250);
+    }
+
+    /// Fee deduction is scale-invariant: 0.5% of 500 USDC is 2.5 USDC in
+    /// 6-decimal units exactly as it is 2.5 DOT in 18-decimal units.
+    #[test]
+    fn test_fee_adjusted_yield_in_six_decimal_units() {
+        let usdc = FixedPoint::new(6).unwrap();
+        let gross = usdc.to_fixed(500).unwrap();
+        let net = fee_adjusted_yield(gross, 50).unwrap();
+        assert_eq!(net, 497_500_000);
+    }
+
+    /// Rescaling between contexts is exact upward and truncating downward.
+    #[test]
+    fn test_rescale_between_contexts() {
+        let usdc = FixedPoint::new(6).unwrap();
+        assert_eq!(usdc.rescale(1_500_000, FixedPoint::DOT).unwrap(), 15 * PRECISION / 10);
+        assert_eq!(FixedPoint::DOT.rescale(PRECISION + 999, usdc).unwrap(), 1_000_000);
+        assert_eq!(usdc.rescale(42, usdc).unwrap(), 42);
+    }
+
+    /// Rescaling up past u128 returns Overflow rather than wrapping.
+    #[test]
+    fn test_rescale_overflow() {
+        let zero_dp = FixedPoint::new(0).unwrap();
+        let max_dp = FixedPoint::new(FixedPoint::MAX_DECIMALS).unwrap();
+        assert_eq!(zero_dp.rescale(10, max_dp), Err(MathError::Overflow));
+    }
+
+    /// More than 38 decimals cannot be represented in u128.
+    #[test]
+    fn test_too_many_decimals_rejected() {
+        assert_eq!(FixedPoint::new(39), Err(MathError::InvalidInput));
+        assert!(FixedPoint::new(38).is_ok());
+    }
+
+    /// The 18-decimal wrappers match PRECISION-based arithmetic exactly.
+    #[test]
+    fn test_dot_wrappers_match_precision() {
+        assert_eq!(FixedPoint::DOT, FixedPoint::new(18).unwrap());
+        assert_eq!(FixedPoint::DOT.unit(), PRECISION);
+        assert_eq!(to_fixed(1_000).unwrap(), 1_000 * PRECISION);
+        assert_eq!(from_fixed(1_000 * PRECISION + 1), 1_000);
+        assert_eq!(to_fixed(u128::MAX), Err(MathError::Overflow));
+    }
+}
+
+#[cfg(test)]
+mod ln_fixed_tests {
+    use crate::math_lib::{ln_fixed, MathError, PRECISION};
+
+    /// ln 1 is 0 and values below 1 are rejected.
+    #[test]
+    fn test_ln_one_and_below() {
+        assert_eq!(ln_fixed(PRECISION), Ok(0));
+        assert_eq!(ln_fixed(PRECISION - 1), Err(MathError::InvalidInput));
+        assert_eq!(ln_fixed(0), Err(MathError::InvalidInput));
+    }
+
+    /// Known logarithms agree to within 100 units of PRECISION.
+    #[test]
+    fn test_ln_known_values() {
+        let cases = [
+            (2 * PRECISION, 693_147_180_559_945_309u128),
+            (3 * PRECISION, 1_098_612_288_668_109_691),
+            (10 * PRECISION, 2_302_585_092_994_045_684),
+            (3 * PRECISION / 2, 405_465_108_108_164_381),
+            (1_000_000 * PRECISION, 13_815_510_557_964_274_104),
+        ];
+        for (x, expected) in cases {
+            let ln = ln_fixed(x).unwrap();
+            assert!(ln.abs_diff(expected) <= 100, "ln({}) = {}, expected {}", x, ln, expected);
+        }
+    }
+
+    /// ln is increasing.
+    #[test]
+    fn test_ln_monotonic() {
+        let mut previous = 0;
+        let inputs = [PRECISION + PRECISION / 1_000_000, 5 * PRECISION / 4, 7 * PRECISION, u128::MAX];
+        for x in inputs {
+            let ln = ln_fixed(x).unwrap();
+            assert!(ln > previous);
+            previous = ln;
+        }
+    }
+}
+
+#[cfg(test)]
+mod allocation_entropy_tests {
+    use crate::math_lib::{allocation_entropy_bps, MathError};
+
+    /// An even split scores (near) full marks.
+    #[test]
+    fn test_even_split_is_max() {
+        let even = allocation_entropy_bps(&[50, 50]).unwrap();
+        assert!((9_998..=10_000).contains(&even), "got {}", even);
+        let four_way = allocation_entropy_bps(&[25, 25, 25, 25]).unwrap();
+        assert!((9_998..=10_000).contains(&four_way), "got {}", four_way);
+    }
+
+    /// Everything in one leg scores 0.
+    #[test]
+    fn test_concentrated_is_zero() {
+        assert_eq!(allocation_entropy_bps(&[100, 0]), Ok(0));
+        assert_eq!(allocation_entropy_bps(&[0, 100, 0]), Ok(0));
+        assert_eq!(allocation_entropy_bps(&[100]), Ok(0));
+    }
+
+    /// A lopsided split lands in between: H(0.75, 0.25) ÷ ln 2 ≈ 0.8113.
+    #[test]
+    fn test_uneven_split_is_between() {
+        let uneven = allocation_entropy_bps(&[75, 25]).unwrap();
+        assert!((8_110..=8_113).contains(&uneven), "got {}", uneven);
+        assert!(allocation_entropy_bps(&[90, 10]).unwrap() < uneven);
+    }
+
+    /// Anything validate_allocation rejects is rejected.
+    #[test]
+    fn test_invalid_allocation() {
+        assert_eq!(allocation_entropy_bps(&[]), Err(MathError::InvalidInput));
+        assert_eq!(allocation_entropy_bps(&[60, 60]), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod ema_bps_tests {
+    use crate::math_lib::{ema_bps, MathError, BPS_DENOMINATOR};
+
+    /// alpha = 0 ignores the new observation entirely.
+    #[test]
+    fn test_zero_alpha_returns_previous() {
+        assert_eq!(ema_bps(1_200, 9_000, 0), Ok(1_200));
+    }
+
+    /// alpha = 100% discards history and returns the new observation.
+    #[test]
+    fn test_full_alpha_returns_new_value() {
+        assert_eq!(ema_bps(1_200, 9_000, BPS_DENOMINATOR as u32), Ok(9_000));
+    }
+
+    /// alpha = 20%: 1200 × 0.8 + 1700 × 0.2 = 960 + 340 = 1300.
+    #[test]
+    fn test_mid_alpha_matches_formula() {
+        assert_eq!(ema_bps(1_200, 1_700, 2_000), Ok(1_300));
+    }
+
+    /// Non-exact results truncate toward zero: 1000 × 0.6667 + 1001 × 0.3333 = 1000.3333.
+    #[test]
+    fn test_result_truncates() {
+        assert_eq!(ema_bps(1_000, 1_001, 3_333), Ok(1_000));
+    }
+
+    /// Extreme inputs must not overflow and stay within range.
+    #[test]
+    fn test_max_values_do_not_overflow() {
+        assert_eq!(ema_bps(u32::MAX, u32::MAX, 5_000), Ok(u32::MAX));
+        assert_eq!(ema_bps(0, u32::MAX, 5_000), Ok(u32::MAX / 2));
+    }
+
+    /// Alpha above 100% must be rejected.
+    #[test]
+    fn test_alpha_above_denominator_rejected() {
+        assert_eq!(ema_bps(1_200, 1_700, 10_001), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod lerp_bps_tests {
+    use crate::math_lib::{lerp_bps, MathError};
+
+    /// The endpoints return the observations themselves.
+    #[test]
+    fn test_endpoints() {
+        assert_eq!(lerp_bps(800, 1_200, 0).unwrap(), 800);
+        assert_eq!(lerp_bps(800, 1_200, 10_000).unwrap(), 1_200);
+    }
+
+    /// t = 5000 lands on the midpoint.
+    #[test]
+    fn test_midpoint() {
+        assert_eq!(lerp_bps(800, 1_200, 5_000).unwrap(), 1_000);
+    }
+
+    /// A falling pair interpolates downward, truncating toward a.
+    #[test]
+    fn test_decreasing_pair() {
+        assert_eq!(lerp_bps(1_200, 800, 2_500).unwrap(), 1_100);
+        assert_eq!(lerp_bps(3, 0, 5_000).unwrap(), 2);
+        assert_eq!(lerp_bps(u32::MAX, 0, 10_000).unwrap(), 0);
+    }
+
+    /// t above 100% would extrapolate and is rejected.
+    #[test]
+    fn test_t_above_bps_rejected() {
+        assert_eq!(lerp_bps(800, 1_200, 10_001), Err(MathError::InvalidInput));
+    }
+}
+
+#[cfg(test)]
+mod optimal_rebalance_periods_tests {
+    use crate::math_lib::{optimal_rebalance_periods, PRECISION};
+
+    /// 50 DOT per rebalance against 1 DOT of drift per period: √100 = 10.
+    #[test]
+    fn test_known_cost_pair() {
+        assert_eq!(optimal_rebalance_periods(50 * PRECISION, PRECISION).unwrap(), 10);
+        assert_eq!(optimal_rebalance_periods(8 * PRECISION, PRECISION).unwrap(), 4);
+    }
+
+    /// Dearer rebalances stretch the interval; faster drift shortens it.
+    #[test]
+    fn test_monotonic_in_costs() {
+        let base = optimal_rebalance_periods(50 * PRECISION, PRECISION).unwrap();
+        let dearer = optimal_rebalance_periods(200 * PRECISION, PRECISION).unwrap();
+        let faster_drift = optimal_rebalance_periods(50 * PRECISION, 4 * PRECISION).unwrap();
+        assert_eq!(dearer, 2 * base);
+        assert_eq!(faster_drift * 2, base);
+    }
+
+    /// Zero drift returns the max-interval sentinel; free rebalances floor at 1.
+    #[test]
+    fn test_edges() {
+        assert_eq!(optimal_rebalance_periods(50 * PRECISION, 0).unwrap(), u32::MAX);
+        assert_eq!(optimal_rebalance_periods(0, PRECISION).unwrap(), 1);
+        assert_eq!(optimal_rebalance_periods(u128::MAX, 1).unwrap(), u32::MAX);
+    }
+}
+
+#[cfg(test)]
+mod scenario_rates_tests {
+    use crate::math_lib::{scenario_rates, MAX_SCENARIOS};
+
+    /// The same seed always yields the same rates; another seed does not.
+    #[test]
+    fn test_same_seed_same_rates() {
+        let a = scenario_rates(1_200, 300, [7u8; 32], 64);
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, scenario_rates(1_200, 300, [7u8; 32], 64));
+        assert_ne!(a, scenario_rates(1_200, 300, [8u8; 32], 64));
+        // A longer run extends a shorter one rather than reshuffling it.
+        assert_eq!(scenario_rates(1_200, 300, [7u8; 32], 16), a[..16]);
+    }
+
+    /// Every rate lies within ±volatility of the base, clipped at zero.
+    #[test]
+    fn test_rates_within_band() {
+        for rate in scenario_rates(1_200, 300, [1u8; 32], 500) {
+            assert!((900..=1_500).contains(&rate), "rate {rate}");
+        }
+        for rate in scenario_rates(100, 300, [2u8; 32], 500) {
+            assert!(rate <= 400, "rate {rate}");
+        }
+        assert_eq!(scenario_rates(1_200, 0, [3u8; 32], 5), vec![1_200; 5]);
+        assert!(scenario_rates(1_200, 300, [3u8; 32], 0).is_empty());
+    }
+
+    /// Over many scenarios the mean sits close to the base rate.
+    #[test]
+    fn test_mean_approximates_base() {
+        let rates = scenario_rates(1_200, 300, [9u8; 32], 2_000);
+        let mean = rates.iter().map(|&r| r as u64).sum::<u64>() / rates.len() as u64;
+        assert!(mean.abs_diff(1_200) <= 20, "mean {mean}");
+    }
+
+    /// The band saturates at u32::MAX instead of wrapping.
+    #[test]
+    fn test_band_saturates_at_top() {
+        for rate in scenario_rates(u32::MAX - 10, 100, [4u8; 32], 100) {
+            assert!(rate >= u32::MAX - 110);
+        }
+    }
+
+    /// A count above MAX_SCENARIOS is clamped to the first MAX_SCENARIOS
+    /// rates of the same sequence.
+    #[test]
+    fn test_count_clamped_to_max_scenarios() {
+        let capped = scenario_rates(1_200, 300, [5u8; 32], MAX_SCENARIOS);
+        assert_eq!(capped.len(), MAX_SCENARIOS as usize);
+        assert_eq!(scenario_rates(1_200, 300, [5u8; 32], MAX_SCENARIOS + 1), capped);
+        assert_eq!(scenario_rates(1_200, 300, [5u8; 32], u32::MAX), capped);
+    }
+}
+
+#[cfg(test)]
+mod mul_fraction_tests {
+    use crate::math_lib::{mul_fraction, MathError, PRECISION};
+
+    /// Small values match the inline multiply-then-divide exactly.
+    #[test]
+    fn test_small_value_parity() {
+        for (value, num, den) in [
+            (1_000 * PRECISION, 60u128, 100u128),
+            (999u128, 1u128, 3u128),
+            (7u128, 100u128, 9u128),
+            (0u128, 55u128, 100u128),
+            (u64::MAX as u128, u64::MAX as u128, 1u128),
+        ] {
+            assert_eq!(mul_fraction(value, num, den), Ok(value * num / den));
+        }
+    }
+
+    /// Near u128::MAX / 100 the inline product overflows, but the quotient fits.
+    #[test]
+    fn test_large_value_does_not_overflow_midway() {
+        let value = u128::MAX / 100 + 1;
+        assert!(value.checked_mul(60).is_some());
+        assert!(value.checked_mul(100).is_none(), "inline value * 100 must overflow");
+
+        assert_eq!(mul_fraction(value, 100, 100), Ok(value));
+        assert_eq!(mul_fraction(value, 99, 100), Ok(value - value.div_ceil(100)));
+    }
+
+    /// u128::MAX × u128::MAX / u128::MAX is exact.
+    #[test]
+    fn test_max_operands() {
+        assert_eq!(mul_fraction(u128::MAX, u128::MAX, u128::MAX), Ok(u128::MAX));
+        assert_eq!(mul_fraction(u128::MAX, u128::MAX - 1, u128::MAX), Ok(u128::MAX - 1));
+    }
+
+    /// A quotient above u128::MAX is an overflow, not a wrapped value.
+    #[test]
+    fn test_quotient_overflow() {
+        assert_eq!(mul_fraction(u128::MAX, 3, 2), Err(MathError::Overflow));
+    }
+
+    /// A zero denominator is rejected.
+    #[test]
+    fn test_zero_denominator() {
+        assert_eq!(mul_fraction(1, 1, 0), Err(MathError::DivisionByZero));
+    }
+}
+
+#[cfg(test)]
+mod saturating_u32_tests {
+    use crate::math_lib::saturating_u32;
+
+    /// Values in range pass through; anything above u32::MAX pins to it.
+    #[test]
+    fn test_saturating_u32() {
+        assert_eq!(saturating_u32(0), 0);
+        assert_eq!(saturating_u32(1_133), 1_133);
+        assert_eq!(saturating_u32(u32::MAX as u128), u32::MAX);
+        assert_eq!(saturating_u32(u32::MAX as u128 + 1), u32::MAX);
+        assert_eq!(saturating_u32(u128::MAX), u32::MAX);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// yield_optimizer integration tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod optimizer_tests {
+    use crate::math_lib::{
+        compound, fee_adjusted_yield, optimal_split, risk_adjusted_yield, weighted_average,
+        MathError, BPS_DENOMINATOR, MAX_PRINCIPAL, PRECISION,
+    };
+    use crate::yield_optimizer::{
+        allocation_for_target_apy, break_even_fee_bps, decision_code, diff_recommendations,
+        ensure_split_complete, evaluate_split, marginal_equalizing_split,
+        max_loss_constrained_split, min_principal_for_split, optimize, optimize_resilient,
+        optimize_stressed, optimize_verbose, optimize_with_hook, optimize_with_override,
+        total_cost_of_ownership, validate_input, winning_spread_bps, yield_attribution,
+        yield_fee_sensitivity, CompoundingFrequency, Constraint, MarketData, OptimizerError,
+        OptimizerInput, OptimizerInputBuilder, RecommendationDiff, SplitReason,
+        YieldRecommendation,
+    };
+
+    fn default_input() -> OptimizerInput {
+        OptimizerInput {
+            principal: 1_000 * PRECISION, // 1000 DOT
+            hydradx_apy_bps: 1_200,       // 12%
+            interlay_apy_bps: 900,         // 9%
+            hydradx_fee_bps: 50,           // 0.5%
+            interlay_fee_bps: 100,         // 1%
+            hydradx_risk_score: 1_500,
+            interlay_risk_score: 2_500,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        }
+    }
+
+    /// Full pipeline with realistic inputs — confirm no error and sensible output.
+    #[test]
+    fn test_full_pipeline_realistic_inputs() {
+        let result = optimize(&default_input());
+        assert!(result.is_ok(), "Optimizer failed: {:?}", result);
+        let rec = result.unwrap();
+
+        // Allocation must sum to 100
+        assert_eq!(
+            rec.hydradx_allocation_pct + rec.interlay_allocation_pct,
+            100
+        );
+        // use_* flags must match allocation percentages
+        assert_eq!(rec.use_hydradx, rec.hydradx_allocation_pct > 0);
+        assert_eq!(rec.use_interlay, rec.interlay_allocation_pct > 0);
+        // Yield must be positive
+        assert!(rec.expected_yield_dot > 0);
+        // APY must be positive
+        assert!(rec.projected_net_apy_bps > 0);
+    }
+
+    /// Zero principal must return InvalidInput.
+    #[test]
+    fn test_zero_principal_returns_error() {
+        let mut input = default_input();
+        input.principal = 0;
+        let result = optimize(&input);
+        assert_eq!(result, Err(OptimizerError::InvalidInput));
+    }
+
+    /// Zero projection periods must return InvalidInput.
+    #[test]
+    fn test_zero_periods_returns_error() {
+        let mut input = default_input();
+        input.projection_periods = 0;
+        let result = optimize(&input);
+        assert_eq!(result, Err(OptimizerError::InvalidInput));
+    }
+
+    /// Fee above 100% must return InvalidInput.
+    #[test]
+    fn test_fee_above_100pct_returns_error() {
+        let mut input = default_input();
+        input.hydradx_fee_bps = 10_001;
+        let result = optimize(&input);
+        assert_eq!(result, Err(OptimizerError::InvalidInput));
+    }
+
+    /// HydraDX clearly better (higher yield, lower risk) — should get majority allocation.
+    #[test]
+    fn test_hydradx_dominates_gets_majority_allocation() {
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 2_000, // 20%
+            interlay_apy_bps: 500,  // 5%
+            hydradx_fee_bps: 50,
+            interlay_fee_bps: 50,
+            hydradx_risk_score: 500,
+            interlay_risk_score: 4_000,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert!(
+            rec.hydradx_allocation_pct > rec.interlay_allocation_pct,
+            "HydraDX should dominate: got {}% vs {}%",
+            rec.hydradx_allocation_pct,
+            rec.interlay_allocation_pct
+        );
+    }
+
+    /// Interlay clearly better (higher yield, lower risk) — should get majority allocation.
+    #[test]
+    fn test_interlay_dominates_gets_majority_allocation() {
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 400,   // 4%
+            interlay_apy_bps: 2_500, // 25%
+            hydradx_fee_bps: 200,
+            interlay_fee_bps: 50,
+            hydradx_risk_score: 6_000,
+            interlay_risk_score: 800,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert!(
+            rec.interlay_allocation_pct > rec.hydradx_allocation_pct,
+            "Interlay should dominate: got {}% vs {}%",
+            rec.interlay_allocation_pct,
+            rec.hydradx_allocation_pct
+        );
+    }
+
+    /// Both yields at zero — optimizer should still return 50/50 without erroring.
+    #[test]
+    fn test_both_zero_apy_returns_fifty_fifty_no_error() {
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 0,
+            interlay_apy_bps: 0,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 500,
+            interlay_risk_score: 500,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.hydradx_allocation_pct, 50);
+        assert_eq!(rec.interlay_allocation_pct, 50);
+        assert_eq!(rec.expected_yield_dot, 0);
+    }
+
+    /// Determinism: identical inputs always produce identical outputs.
+    #[test]
+    fn test_full_pipeline_is_deterministic() {
+        let input = default_input();
+        let r1 = optimize(&input).unwrap();
+        let r2 = optimize(&input).unwrap();
+        assert_eq!(r1, r2);
+    }
+
+    /// Large principal (1B DOT) — verify no overflow through the full pipeline.
+    #[test]
+    fn test_large_principal_no_overflow() {
+        let input = OptimizerInput {
+            principal: 1_000_000_000 * PRECISION, // 1B DOT
+            hydradx_apy_bps: 1_000,
+            interlay_apy_bps: 800,
+            hydradx_fee_bps: 50,
+            interlay_fee_bps: 100,
+            hydradx_risk_score: 1_000,
+            interlay_risk_score: 2_000,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let result = optimize(&input);
+        assert!(
+            result.is_ok(),
+            "1B DOT pipeline should not overflow: {:?}",
+            result
+        );
+    }
+
+    /// Single compounding period — ensure optimizer handles minimal periods correctly.
+    #[test]
+    fn test_single_period_optimizer() {
+        let input = OptimizerInput {
+            principal: 10_000 * PRECISION,
+            hydradx_apy_bps: 1_000,
+            interlay_apy_bps: 500,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 0,
+            interlay_risk_score: 0,
+            projection_periods: 1,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert!(rec.expected_yield_dot > 0);
+        assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct, 100);
+    }
+
+    /// Maximum risk on both sides — optimizer must return 50/50 and not error.
+    #[test]
+    fn test_max_risk_both_sides_fifty_fifty() {
+        let input = OptimizerInput {
+            principal: 500 * PRECISION,
+            hydradx_apy_bps: 2_000,
+            interlay_apy_bps: 1_500,
+            hydradx_fee_bps: 100,
+            interlay_fee_bps: 200,
+            hydradx_risk_score: 10_000,
+            interlay_risk_score: 10_000,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.hydradx_allocation_pct, 50);
+        assert_eq!(rec.interlay_allocation_pct, 50);
+    }
+
+    /// use_hydradx flag must be false when hydradx gets 0% allocation.
+    #[test]
+    fn test_use_flags_consistent_with_allocation() {
+        // Max risk on HydraDX forces its allocation to 0
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 5_000,
+            interlay_apy_bps: 1_000,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 10_000, // Max risk — wipes adj yield to 0
+            interlay_risk_score: 0,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert!(!rec.use_hydradx, "use_hydradx should be false when pct = 0");
+        assert!(rec.use_interlay, "use_interlay should be true when pct = 100");
+        assert_eq!(rec.hydradx_allocation_pct, 0);
+        assert_eq!(rec.interlay_allocation_pct, 100);
+
+        // A binding Interlay cap must not spill onto the max-risk leg: the
+        // excess stays undeployed instead.
+        let capped = OptimizerInput { interlay_max_dot: 100 * PRECISION, ..input.clone() };
+        let rec = optimize(&capped).unwrap();
+        assert!(!rec.use_hydradx, "max-risk leg must stay unused under a cap");
+        assert_eq!(rec.hydradx_allocation_pct, 0);
+        assert_eq!(rec.hydradx_allocation_dot, 0);
+        assert_eq!(rec.interlay_allocation_dot, 100 * PRECISION);
+        assert_eq!(rec.unallocated_dot, 900 * PRECISION);
+        assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct + rec.unallocated_pct, 100);
+
+        // Same with Interlay also failing the hurdle: nothing reaches HydraDX.
+        let hurdle = OptimizerInput { hurdle_rate_bps: 2_000, ..capped };
+        let rec = optimize(&hurdle).unwrap();
+        assert!(!rec.use_hydradx);
+        assert_eq!(rec.hydradx_allocation_dot, 0);
+        assert_eq!(rec.unallocated_pct, 100);
+    }
+
+    /// Projection shorter than HydraDX's minimum hold forfeits its yield —
+    /// the leg drops to 0% and Interlay takes the whole position.
+    #[test]
+    fn test_min_hold_not_met_drops_allocation() {
+        let baseline = optimize(&default_input()).unwrap();
+        assert!(baseline.hydradx_allocation_pct > 0);
+
+        let mut input = default_input();
+        input.hydradx_min_hold_periods = 400; // > 365 projection periods
+        let rec = optimize(&input).unwrap();
+
+        assert_eq!(rec.hydradx_allocation_pct, 0);
+        assert_eq!(rec.interlay_allocation_pct, 100);
+        assert!(!rec.use_hydradx);
+        assert!(rec.expected_yield_dot > 0, "Interlay leg must still yield");
+    }
+
+    /// Holding exactly the minimum number of periods satisfies the constraint.
+    #[test]
+    fn test_min_hold_exactly_met_is_unchanged() {
+        let baseline = optimize(&default_input()).unwrap();
+
+        let mut input = default_input();
+        input.hydradx_min_hold_periods = 365;
+        input.interlay_min_hold_periods = 365;
+        let rec = optimize(&input).unwrap();
+
+        assert_eq!(rec, baseline);
+    }
+
+    /// Neither leg can be held long enough — both yields are forfeited and the
+    /// optimizer falls back to 50/50 with zero expected yield.
+    #[test]
+    fn test_min_hold_not_met_on_both_legs() {
+        let mut input = default_input();
+        input.hydradx_min_hold_periods = 730;
+        input.interlay_min_hold_periods = 366;
+        let rec = optimize(&input).unwrap();
+
+        assert_eq!(rec.hydradx_allocation_pct, 50);
+        assert_eq!(rec.interlay_allocation_pct, 50);
+        assert_eq!(rec.expected_yield_dot, 0);
+    }
+
+    /// Interlay's optimal DOT amount exceeds its absolute cap — Interlay is held
+    /// at the cap and the excess is redistributed to HydraDX.
+    #[test]
+    fn test_leg_cap_redistributes_excess() {
+        let baseline = optimize(&default_input()).unwrap();
+        let cap = baseline.interlay_allocation_dot / 2;
+
+        let mut input = default_input();
+        input.interlay_max_dot = cap;
+        let rec = optimize(&input).unwrap();
+
+        assert_eq!(rec.interlay_allocation_dot, cap);
+        assert_eq!(rec.hydradx_allocation_dot, input.principal - cap);
+        assert_eq!(rec.unallocated_dot, 0);
+        assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct, 100);
+        assert!(rec.hydradx_allocation_pct > baseline.hydradx_allocation_pct);
+    }
+
+    /// Both legs capped below the principal — the residual is left unallocated
+    /// and earns nothing.
+    #[test]
+    fn test_both_legs_capped_leave_residual_unallocated() {
+        let mut input = default_input();
+        input.hydradx_max_dot = 300 * PRECISION;
+        input.interlay_max_dot = 200 * PRECISION;
+        let rec = optimize(&input).unwrap();
+
+        assert_eq!(rec.hydradx_allocation_dot, 300 * PRECISION);
+        assert_eq!(rec.interlay_allocation_dot, 200 * PRECISION);
+        assert_eq!(rec.unallocated_dot, 500 * PRECISION);
+        assert_eq!(rec.hydradx_allocation_pct, 30);
+        assert_eq!(rec.interlay_allocation_pct, 20);
+
+        let baseline = optimize(&default_input()).unwrap();
+        assert!(rec.expected_yield_dot > 0);
+        assert!(rec.expected_yield_dot < baseline.expected_yield_dot);
+    }
+
+    /// A zero cap on HydraDX pushes the whole position to Interlay.
+    #[test]
+    fn test_zero_cap_disables_leg() {
+        let mut input = default_input();
+        input.hydradx_max_dot = 0;
+        let rec = optimize(&input).unwrap();
+
+        assert!(!rec.use_hydradx);
+        assert_eq!(rec.hydradx_allocation_pct, 0);
+        assert_eq!(rec.interlay_allocation_pct, 100);
+        assert_eq!(rec.interlay_allocation_dot, input.principal);
+    }
+
+    /// Caps above each leg's share don't bind — output is unchanged.
+    #[test]
+    fn test_non_binding_caps_are_unchanged() {
+        let baseline = optimize(&default_input()).unwrap();
+
+        let mut input = default_input();
+        input.hydradx_max_dot = input.principal;
+        input.interlay_max_dot = input.principal;
+        let rec = optimize(&input).unwrap();
+
+        assert_eq!(rec, baseline);
+        assert_eq!(
+            rec.hydradx_allocation_dot + rec.interlay_allocation_dot,
+            input.principal
+        );
+    }
+
+    /// A principal exactly at MAX_PRINCIPAL is accepted.
+    #[test]
+    fn test_principal_at_max_is_accepted() {
+        let mut input = default_input();
+        input.principal = MAX_PRINCIPAL;
+        assert!(optimize(&input).is_ok());
+    }
+
+    /// One unit above MAX_PRINCIPAL is rejected as a unit-confusion error.
+    #[test]
+    fn test_principal_above_max_returns_error() {
+        let mut input = default_input();
+        input.principal = MAX_PRINCIPAL + 1;
+        assert_eq!(optimize(&input), Err(OptimizerError::InvalidInput));
+    }
+
+    /// A value scaled by PRECISION twice is far above the bound.
+    #[test]
+    fn test_double_scaled_principal_returns_error() {
+        let mut input = default_input();
+        input.principal = 100 * PRECISION * PRECISION;
+        assert_eq!(optimize(&input), Err(OptimizerError::InvalidInput));
+    }
+
+    /// A realistic 1M DOT position passes the sanity bound.
+    #[test]
+    fn test_one_million_dot_principal_passes() {
+        let mut input = default_input();
+        input.principal = 1_000_000 * PRECISION;
+        assert!(optimize(&input).is_ok());
+    }
+
+    /// A 50/50 fallback split reports the arithmetic mean of both risk scores.
+    #[test]
+    fn test_blended_risk_equal_weight_is_mean() {
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 0,
+            interlay_apy_bps: 0,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 1_000,
+            interlay_risk_score: 3_000,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.hydradx_allocation_pct, 50);
+        assert_eq!(rec.blended_risk_score, 2_000);
+    }
+
+    /// A 70/30 split weights the risk scores 70/30.
+    #[test]
+    fn test_blended_risk_seventy_thirty() {
+        // Single period, no fees: net APY == gross APY.
+        // Risk-adjusted: 1400 × 1.0 = 1400 vs 1200 × 0.5 = 600 → 70/30
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 1_400,
+            interlay_apy_bps: 1_200,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 0,
+            interlay_risk_score: 5_000,
+            projection_periods: 1,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.hydradx_allocation_pct, 70);
+        assert_eq!(rec.interlay_allocation_pct, 30);
+        // (0 × 70 + 5000 × 30) / 100 = 1500
+        assert_eq!(rec.blended_risk_score, 1_500);
+        // Same weights as the APY: (1400 × 70 + 1200 × 30) / 100 = 1340
+        assert_eq!(rec.projected_net_apy_bps, 1_340);
+    }
+
+    /// A 0/100 split reports exactly the allocated leg's risk.
+    #[test]
+    fn test_blended_risk_single_leg_is_exact() {
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 5_000,
+            interlay_apy_bps: 1_000,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 10_000,
+            interlay_risk_score: 2_345,
+            projection_periods: 365,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.interlay_allocation_pct, 100);
+        assert_eq!(rec.blended_risk_score, 2_345);
+    }
+
+    /// A hurdle below both legs' net returns leaves the split unchanged.
+    #[test]
+    fn test_hurdle_below_both_is_unchanged() {
+        let base = optimize(&default_input()).unwrap();
+        let rec = optimize(&OptimizerInput { hurdle_rate_bps: 500, ..default_input() }).unwrap();
+        assert_eq!(rec, base);
+        assert_eq!(rec.unallocated_pct, 0);
+        assert_eq!(rec.unallocated_dot, 0);
+    }
+
+    /// Interlay (~9.3% net) misses a 10% hurdle; its share stays in cash
+    /// instead of moving to HydraDX.
+    #[test]
+    fn test_one_leg_below_hurdle_goes_to_cash() {
+        let input = default_input();
+        let base = optimize(&input).unwrap();
+        let rec = optimize(&OptimizerInput { hurdle_rate_bps: 1_000, ..input.clone() }).unwrap();
+
+        assert!(rec.use_hydradx);
+        assert!(!rec.use_interlay);
+        assert_eq!(rec.hydradx_allocation_pct, base.hydradx_allocation_pct);
+        assert_eq!(rec.interlay_allocation_pct, 0);
+        assert_eq!(rec.unallocated_pct, base.interlay_allocation_pct);
+        assert_eq!(rec.hydradx_allocation_dot, base.hydradx_allocation_dot);
+        assert_eq!(rec.interlay_allocation_dot, 0);
+        assert_eq!(rec.unallocated_dot, input.principal - base.hydradx_allocation_dot);
+        assert!(rec.expected_yield_dot < base.expected_yield_dot);
+        assert_eq!(rec.blended_risk_score, input.hydradx_risk_score);
+    }
+
+    /// Neither leg clears a 20% hurdle — the whole position stays in cash.
+    #[test]
+    fn test_both_legs_below_hurdle_is_all_cash() {
+        let input = OptimizerInput { hurdle_rate_bps: 2_000, ..default_input() };
+        let rec = optimize(&input).unwrap();
+
+        assert!(!rec.use_hydradx);
+        assert!(!rec.use_interlay);
+        assert_eq!(rec.hydradx_allocation_pct, 0);
+        assert_eq!(rec.interlay_allocation_pct, 0);
+        assert_eq!(rec.unallocated_pct, 100);
+        assert_eq!(rec.unallocated_dot, input.principal);
+        assert_eq!(rec.expected_yield_dot, 0);
+        assert_eq!(rec.projected_net_apy_bps, 0);
+    }
+
+    /// A leg whose spill would otherwise overflow into a below-hurdle leg
+    /// leaves the excess in cash instead.
+    #[test]
+    fn test_capped_leg_does_not_spill_into_below_hurdle_leg() {
+        let input = OptimizerInput {
+            hurdle_rate_bps: 1_000,
+            hydradx_max_dot: 100 * PRECISION,
+            ..default_input()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.hydradx_allocation_dot, 100 * PRECISION);
+        assert_eq!(rec.interlay_allocation_dot, 0);
+        assert_eq!(rec.unallocated_dot, 900 * PRECISION);
+        assert_eq!(rec.hydradx_allocation_pct + rec.unallocated_pct, 100);
+    }
+
+    /// validate_input accepts what optimize accepts.
+    #[test]
+    fn test_validate_input_accepts_valid_input() {
+        assert!(optimize(&default_input()).is_ok());
+        assert_eq!(validate_input(&default_input()), Ok(()));
+    }
+
+    /// validate_input reports the same error variant as optimize for every
+    /// validation failure and for a step-1 compound overflow.
+    #[test]
+    fn test_validate_input_matches_optimize_errors() {
+        let cases = [
+            OptimizerInput { principal: 0, ..default_input() },
+            OptimizerInput { projection_periods: 0, ..default_input() },
+            OptimizerInput { hydradx_fee_bps: 10_001, ..default_input() },
+            OptimizerInput { interlay_fee_bps: 10_001, ..default_input() },
+            OptimizerInput { principal: MAX_PRINCIPAL + 1, ..default_input() },
+            OptimizerInput {
+                principal: MAX_PRINCIPAL,
+                hydradx_apy_bps: u32::MAX,
+                ..default_input()
+            },
+        ];
+        for input in &cases {
+            let probe = validate_input(input);
+            assert!(probe.is_err(), "case {:?} must fail", input);
+            assert_eq!(probe, optimize(input).map(|_| ()));
+        }
+        assert_eq!(
+            validate_input(&cases[5]),
+            Err(OptimizerError::Math(MathError::Overflow))
+        );
+    }
+
+    /// Re-optimizing on identical market data reports no change at all.
+    #[test]
+    fn test_diff_no_change_is_all_zero() {
+        let rec = optimize(&default_input()).unwrap();
+        assert_eq!(diff_recommendations(&rec, &rec.clone()), RecommendationDiff::default());
+    }
+
+    /// Interlay's APY collapsing moves everything to HydraDX: the Interlay leg
+    /// closes, so the active-leg set changes and the deltas point that way.
+    #[test]
+    fn test_diff_leg_deactivation() {
+        let old = optimize(&default_input()).unwrap();
+        let new = optimize(&OptimizerInput { interlay_apy_bps: 0, ..default_input() }).unwrap();
+        assert!(old.use_interlay && !new.use_interlay);
+
+        let diff = diff_recommendations(&old, &new);
+        assert!(diff.active_legs_changed);
+        assert_eq!(diff.hydradx_pct_delta, 100 - old.hydradx_allocation_pct as i64);
+        assert_eq!(diff.interlay_pct_delta, -(old.interlay_allocation_pct as i64));
+        assert_eq!(diff.hydradx_pct_delta + diff.interlay_pct_delta, 0);
+        assert_eq!(
+            diff.apy_delta_bps,
+            new.projected_net_apy_bps as i64 - old.projected_net_apy_bps as i64
+        );
+
+        // The reverse switch re-opens the leg with mirrored deltas.
+        let back = diff_recommendations(&new, &old);
+        assert!(back.active_legs_changed);
+        assert_eq!(back.interlay_pct_delta, -diff.interlay_pct_delta);
+        assert_eq!(back.yield_delta_dot, -diff.yield_delta_dot);
+    }
+
+    /// A resize that keeps both legs open is not a leg-set change.
+    #[test]
+    fn test_diff_resize_keeps_active_legs() {
+        let old = optimize(&default_input()).unwrap();
+        let new = optimize(&OptimizerInput { interlay_apy_bps: 1_100, ..default_input() }).unwrap();
+        let diff = diff_recommendations(&old, &new);
+        assert!(!diff.active_legs_changed);
+        assert!(diff.interlay_pct_delta > 0);
+        assert!(diff.yield_delta_dot != 0);
+    }
+
+    /// Full uptime is the neutral value — identical to the default input.
+    #[test]
+    fn test_full_uptime_is_unchanged() {
+        let explicit = OptimizerInput {
+            hydradx_uptime_bps: 10_000,
+            interlay_uptime_bps: 10_000,
+            ..default_input()
+        };
+        assert_eq!(optimize(&explicit).unwrap(), optimize(&default_input()).unwrap());
+    }
+
+    /// 80% uptime cuts the leg's effective net yield by 20%.
+    #[test]
+    fn test_uptime_scales_effective_yield() {
+        // Interlay at 0% APY so HydraDX takes the whole position and the
+        // blended APY is the HydraDX net figure alone.
+        let base_input = OptimizerInput { interlay_apy_bps: 0, ..default_input() };
+        let base = optimize(&base_input).unwrap();
+        let degraded = optimize(&OptimizerInput { hydradx_uptime_bps: 8_000, ..base_input }).unwrap();
+
+        assert_eq!(base.hydradx_allocation_pct, 100);
+        assert_eq!(degraded.hydradx_allocation_pct, 100);
+        let expected = base.projected_net_apy_bps * 8 / 10;
+        assert!(
+            degraded.projected_net_apy_bps.abs_diff(expected) <= 1,
+            "expected ≈{} BPS, got {}",
+            expected,
+            degraded.projected_net_apy_bps
+        );
+    }
+
+    /// Downtime on HydraDX shifts allocation toward the more reliable Interlay leg.
+    #[test]
+    fn test_downtime_shifts_allocation_to_reliable_leg() {
+        let base = optimize(&default_input()).unwrap();
+        let rec = optimize(&OptimizerInput { hydradx_uptime_bps: 8_000, ..default_input() }).unwrap();
+        assert!(rec.hydradx_allocation_pct < base.hydradx_allocation_pct);
+        assert!(rec.interlay_allocation_pct > base.interlay_allocation_pct);
+        assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct, 100);
+    }
+
+    /// Uptime above 100% is rejected.
+    #[test]
+    fn test_uptime_above_full_is_invalid() {
+        let input = OptimizerInput { interlay_uptime_bps: 10_001, ..default_input() };
+        assert_eq!(optimize(&input), Err(OptimizerError::InvalidInput));
+    }
+
+    /// A garbage 1_000_000 BPS quote is rejected before compounding once a
+    /// 50_000 BPS ceiling is set, on either leg.
+    #[test]
+    fn test_apy_above_ceiling_is_invalid() {
+        let hydradx = OptimizerInput { hydradx_apy_bps: 1_000_000, max_apy_bps: 50_000, ..default_input() };
+        assert_eq!(optimize(&hydradx), Err(OptimizerError::InvalidInput));
+
+        let interlay = OptimizerInput { interlay_apy_bps: 1_000_000, max_apy_bps: 50_000, ..default_input() };
+        assert_eq!(optimize(&interlay), Err(OptimizerError::InvalidInput));
+    }
+
+    /// With the ceiling disabled (0 or u32::MAX) the same quote is optimised.
+    /// A single period keeps the absurd rate's net BPS inside u32 so only the
+    /// ceiling is under test.
+    #[test]
+    fn test_apy_ceiling_disabled_accepts_any_apy() {
+        for max_apy_bps in [0, u32::MAX] {
+            let input = OptimizerInput {
+                hydradx_apy_bps: 1_000_000,
+                projection_periods: 1,
+                max_apy_bps,
+                ..default_input()
+            };
+            let rec = optimize(&input).unwrap();
+            assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct, 100);
+        }
+    }
+
+    /// An APY exactly at the ceiling is still accepted.
+    #[test]
+    fn test_apy_at_ceiling_is_accepted() {
+        let input = OptimizerInput { hydradx_apy_bps: 1_200, max_apy_bps: 1_200, ..default_input() };
+        assert_eq!(optimize(&input), optimize(&default_input()));
+    }
+
+    /// Identical nominal APY, fee and risk, charged per compound: the leg that
+    /// compounds monthly pays its fee 12 times instead of 365 and nets more,
+    /// so it receives the larger allocation.
+    #[test]
+    fn test_per_compound_fee_favours_less_frequent_leg() {
+        let input = OptimizerInput {
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 1_200,
+            interlay_apy_bps: 1_200,
+            hydradx_fee_bps: 1,
+            interlay_fee_bps: 1,
+            hydradx_risk_score: 2_000,
+            interlay_risk_score: 2_000,
+            projection_periods: 365,
+            hydradx_compound_periods: 365,
+            interlay_compound_periods: 12,
+            hydradx_per_compound_fee: true,
+            interlay_per_compound_fee: true,
+            ..OptimizerInput::default()
+        };
+        let rec = optimize(&input).unwrap();
+        assert!(
+            rec.interlay_allocation_pct > rec.hydradx_allocation_pct,
+            "monthly leg must get more: {}/{}",
+            rec.hydradx_allocation_pct,
+            rec.interlay_allocation_pct
+        );
+    }
+
+    /// With the fee charged once on the window yield, the same two schedules
+    /// are nearly equal — the daily leg's extra compounding edges ahead.
+    #[test]
+    fn test_window_fee_does_not_penalise_frequency() {
+        let input = OptimizerInput {
+            hydradx_apy_bps: 1_200,
+            interlay_apy_bps: 1_200,
+            hydradx_fee_bps: 50,
+            interlay_fee_bps: 50,
+            hydradx_risk_score: 2_000,
+            interlay_risk_score: 2_000,
+            hydradx_compound_periods: 365,
+            interlay_compound_periods: 12,
+            ..default_input()
+        };
+        let rec = optimize(&input).unwrap();
+        assert!(rec.hydradx_allocation_pct >= rec.interlay_allocation_pct);
+    }
+
+    /// Explicit compound counts equal to projection_periods change nothing.
+    #[test]
+    fn test_explicit_compound_periods_match_default() {
+        let explicit = OptimizerInput {
+            hydradx_compound_periods: 365,
+            interlay_compound_periods: 365,
+            ..default_input()
+        };
+        assert_eq!(optimize(&explicit).unwrap(), optimize(&default_input()).unwrap());
+    }
+
+    /// For inputs optimize accepts, resilient mode is identical.
+    #[test]
+    fn test_resilient_matches_optimize_for_valid_inputs() {
+        for input in [
+            default_input(),
+            OptimizerInput { hurdle_rate_bps: 1_000, ..default_input() },
+            OptimizerInput { principal: MAX_PRINCIPAL, ..default_input() },
+            OptimizerInput { projection_periods: 1, ..default_input() },
+        ] {
+            assert_eq!(optimize_resilient(&input), optimize(&input).unwrap());
+        }
+    }
+
+    /// An APY that overflows the 365-period compound is retried with fewer
+    /// periods and still yields a full, deployable recommendation.
+    #[test]
+    fn test_resilient_recovers_from_overflow() {
+        let input = OptimizerInput {
+            principal: MAX_PRINCIPAL,
+            hydradx_apy_bps: u32::MAX,
+            ..default_input()
+        };
+        assert_eq!(optimize(&input), Err(OptimizerError::Math(MathError::Overflow)));
+
+        let rec = optimize_resilient(&input);
+        assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct, 100);
+        assert!(rec.use_hydradx, "the overflowing high-APY leg must still be used");
+        assert!(rec.expected_yield_dot > 0);
+    }
+
+    /// Zero principal, zero periods and over-100% fees are clamped into range.
+    #[test]
+    fn test_resilient_clamps_invalid_fields() {
+        let zero_periods = OptimizerInput { projection_periods: 0, ..default_input() };
+        assert_eq!(optimize(&zero_periods), Err(OptimizerError::InvalidInput));
+        assert_eq!(
+            optimize_resilient(&zero_periods),
+            optimize(&OptimizerInput { projection_periods: 1, ..default_input() }).unwrap()
+        );
+
+        let bad_fee = OptimizerInput { interlay_fee_bps: 50_000, ..default_input() };
+        assert_eq!(optimize(&bad_fee), Err(OptimizerError::InvalidInput));
+        assert_eq!(
+            optimize_resilient(&bad_fee),
+            optimize(&OptimizerInput { interlay_fee_bps: 10_000, ..default_input() }).unwrap()
+        );
+
+        let too_big = OptimizerInput { principal: u128::MAX, ..default_input() };
+        assert_eq!(
+            optimize_resilient(&too_big),
+            optimize(&OptimizerInput { principal: MAX_PRINCIPAL, ..default_input() }).unwrap()
+        );
+
+        let rec = optimize_resilient(&OptimizerInput { principal: 0, ..default_input() });
+        assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct, 100);
+    }
+
+    /// Net window APY of one default_input() leg, recomputed from math_lib.
+    fn default_leg_net_bps(apy_bps: u32, fee_bps: u32) -> u128 {
+        let input = default_input();
+        let gross = compound(input.principal, apy_bps, input.projection_periods).unwrap() - input.principal;
+        fee_adjusted_yield(gross, fee_bps).unwrap() * 10_000 / input.principal
+    }
+
+    /// A target between the two legs' net APYs is met by the split with the
+    /// least HydraDX weight that reaches it; one point less falls short.
+    #[test]
+    fn test_target_apy_reachable_returns_least_tilted_split() {
+        let hydradx = default_leg_net_bps(1_200, 50);
+        let interlay = default_leg_net_bps(900, 100);
+        let blend = |h: u64| weighted_average(&[hydradx, interlay], &[h as u128, 100 - h as u128]).unwrap();
+
+        let target = 1_100;
+        let (h, i) = allocation_for_target_apy(&default_input(), target).unwrap().unwrap();
+        assert_eq!(h + i, 100);
+        assert!(h > 0 && i > 0, "an in-between target needs both legs, got {h}/{i}");
+        assert!(blend(h) >= target as u128);
+        assert!(blend(h - 1) < target as u128);
+    }
+
+    /// A target at or below the weaker leg keeps everything there; a target
+    /// equal to the stronger leg's net APY needs all of it.
+    #[test]
+    fn test_target_apy_endpoints() {
+        let input = default_input();
+        let hydradx = default_leg_net_bps(1_200, 50) as u32;
+        assert_eq!(allocation_for_target_apy(&input, 0).unwrap(), Some((0, 100)));
+        assert_eq!(allocation_for_target_apy(&input, hydradx).unwrap(), Some((100, 0)));
+    }
+
+    /// A target above both destinations' net APY is unreachable.
+    #[test]
+    fn test_target_apy_unreachable_returns_none() {
+        let hydradx = default_leg_net_bps(1_200, 50) as u32;
+        assert_eq!(allocation_for_target_apy(&default_input(), hydradx + 1).unwrap(), None);
+    }
+
+    /// With Interlay the stronger leg, the returned pair is still (hydradx, interlay).
+    #[test]
+    fn test_target_apy_split_order_follows_legs() {
+        let input = OptimizerInput { hydradx_apy_bps: 900, interlay_apy_bps: 1_200, ..default_input() };
+        let (h, i) = allocation_for_target_apy(&input, 1_100).unwrap().unwrap();
+        assert!(i > h, "Interlay must carry the tilt, got {h}/{i}");
+    }
+
+    /// Invalid inputs fail the same way optimize() does.
+    #[test]
+    fn test_target_apy_invalid_input() {
+        let input = OptimizerInput { principal: 0, ..default_input() };
+        assert_eq!(allocation_for_target_apy(&input, 1_000), Err(OptimizerError::InvalidInput));
+    }
+
+    /// expected_yield_bps is the absolute yield expressed against principal.
+    #[test]
+    fn test_expected_yield_bps_consistent_with_dot() {
+        let input = default_input();
+        let rec = optimize(&input).unwrap();
+        assert_eq!(
+            rec.expected_yield_bps as u128,
+            rec.expected_yield_dot * 10_000 / input.principal
+        );
+    }
+
+    /// A 10% total-window return reads 1000 BPS: one period at 10% on one leg.
+    #[test]
+    fn test_expected_yield_bps_ten_percent() {
+        let input = OptimizerInput {
+            hydradx_apy_bps: 1_000,
+            interlay_apy_bps: 0,
+            hydradx_fee_bps: 0,
+            projection_periods: 1,
+            ..default_input()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.expected_yield_dot, 100 * PRECISION);
+        assert_eq!(rec.expected_yield_bps, 1_000);
+    }
+
+    /// The BPS figure is size-independent: MAX_PRINCIPAL reads the same as
+    /// 1000 DOT to within rounding.
+    #[test]
+    fn test_expected_yield_bps_large_principal() {
+        let small = optimize(&default_input()).unwrap();
+        let large_input = OptimizerInput { principal: MAX_PRINCIPAL, ..default_input() };
+        let large = optimize(&large_input).unwrap();
+        assert_eq!(
+            large.expected_yield_bps as u128,
+            large.expected_yield_dot * 10_000 / MAX_PRINCIPAL
+        );
+        assert!(large.expected_yield_bps.abs_diff(small.expected_yield_bps) <= 1);
+    }
+
+    /// The default input takes the ordinary proportional path.
+    #[test]
+    fn test_split_reason_proportional() {
+        let (rec, reason) = optimize_verbose(&default_input()).unwrap();
+        assert_eq!(reason, SplitReason::Proportional);
+        assert_eq!(rec, optimize(&default_input()).unwrap());
+    }
+
+    /// Zero yield on both legs hits optimal_split's 50/50 fallback.
+    #[test]
+    fn test_split_reason_zero_total_fallback() {
+        let input = OptimizerInput { hydradx_apy_bps: 0, interlay_apy_bps: 0, ..default_input() };
+        let (rec, reason) = optimize_verbose(&input).unwrap();
+        assert_eq!(reason, SplitReason::ZeroTotalFallback);
+        assert_eq!((rec.hydradx_allocation_pct, rec.interlay_allocation_pct), (50, 50));
+    }
+
+    /// A leg dropped by the hurdle, and a binding cap, are reported as overrides.
+    #[test]
+    fn test_split_reason_overrides() {
+        let hurdle = OptimizerInput { hurdle_rate_bps: 1_000, ..default_input() };
+        assert_eq!(optimize_verbose(&hurdle).unwrap().1, SplitReason::HurdleExcluded);
+
+        let capped = OptimizerInput { hydradx_max_dot: 100 * PRECISION, ..default_input() };
+        assert_eq!(optimize_verbose(&capped).unwrap().1, SplitReason::CapBound);
+    }
+
+    /// The default input is the unconstrained proportional split.
+    #[test]
+    fn test_binding_constraint_none_by_default() {
+        assert_eq!(optimize(&default_input()).unwrap().binding_constraint, None);
+    }
+
+    /// A binding per-leg DOT cap, and a leg dropped by the hurdle, each name
+    /// their limit; a cap that does not bind leaves the field None.
+    #[test]
+    fn test_binding_constraint_names_active_limit() {
+        let capped = OptimizerInput { hydradx_max_dot: 100 * PRECISION, ..default_input() };
+        assert_eq!(optimize(&capped).unwrap().binding_constraint, Some(Constraint::MaxAllocation));
+
+        let hurdle = OptimizerInput { hurdle_rate_bps: 1_000, ..default_input() };
+        assert_eq!(optimize(&hurdle).unwrap().binding_constraint, Some(Constraint::Hurdle));
+
+        let loose = OptimizerInput { hydradx_max_dot: 1_000 * PRECISION, ..default_input() };
+        assert_eq!(optimize(&loose).unwrap().binding_constraint, None);
+    }
+
+    /// A pool shallower than its leg's share caps that leg at its depth and
+    /// reports Liquidity; a DOT cap tighter than the depth still reports
+    /// MaxAllocation.
+    #[test]
+    fn test_binding_constraint_reports_liquidity() {
+        let shallow = OptimizerInput { hydradx_liquidity_depth_dot: 100 * PRECISION, ..default_input() };
+        let rec = optimize(&shallow).unwrap();
+        assert_eq!(rec.binding_constraint, Some(Constraint::Liquidity));
+        assert_eq!(rec.hydradx_allocation_dot, 100 * PRECISION);
+
+        let capped = OptimizerInput { hydradx_max_dot: 50 * PRECISION, ..shallow };
+        assert_eq!(optimize(&capped).unwrap().binding_constraint, Some(Constraint::MaxAllocation));
+    }
+
+    /// A fully dominated decision reads 10000; the balanced default reads lower.
+    #[test]
+    fn test_confidence_dominated_vs_balanced() {
+        let dominated = optimize(&OptimizerInput { interlay_apy_bps: 0, ..default_input() }).unwrap();
+        let balanced = optimize(&default_input()).unwrap();
+        assert_eq!(dominated.confidence_bps, 10_000);
+        assert!(balanced.confidence_bps < dominated.confidence_bps);
+        assert!(balanced.confidence_bps > 0);
+    }
+
+    /// A dead tie and the both-zero fallback both read 0.
+    #[test]
+    fn test_confidence_tie_and_zero_fallback() {
+        let tie = OptimizerInput {
+            interlay_apy_bps: 1_200,
+            interlay_fee_bps: 50,
+            interlay_risk_score: 1_500,
+            ..default_input()
+        };
+        assert_eq!(optimize(&tie).unwrap().confidence_bps, 0);
+
+        let zero = OptimizerInput { hydradx_apy_bps: 0, interlay_apy_bps: 0, ..default_input() };
+        assert_eq!(optimize(&zero).unwrap().confidence_bps, 0);
+    }
+
+    /// A whole split passes the guard that sits in front of the blended
+    /// weighted averages.
+    #[test]
+    fn test_split_guard_accepts_complete_split() {
+        assert_eq!(ensure_split_complete(60, 40), Ok(()));
+        assert_eq!(ensure_split_complete(100, 0), Ok(()));
+    }
+
+    /// A split bug that drops a point must never reach weighted_average: debug
+    /// builds panic on the invariant, release builds return an error.
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "expected a sum of 100"))]
+    fn test_split_guard_rejects_split_summing_to_99() {
+        assert_eq!(
+            ensure_split_complete(60, 39),
+            Err(OptimizerError::Math(MathError::InvalidInput))
+        );
+    }
+
+    /// A minimal build sets the two required fields and leaves everything
+    /// else at its default.
+    #[test]
+    fn test_builder_minimal_uses_defaults() {
+        let input = OptimizerInputBuilder::new(1_000 * PRECISION, 365).build().unwrap();
+        assert_eq!(
+            input,
+            OptimizerInput {
+                principal: 1_000 * PRECISION,
+                projection_periods: 365,
+                ..OptimizerInput::default()
+            }
+        );
+    }
+
+    /// Every setter lands on its own field.
+    #[test]
+    fn test_builder_fully_specified() {
+        let input = OptimizerInputBuilder::new(1_000 * PRECISION, 365)
+            .hydradx_apy_bps(1_200)
+            .interlay_apy_bps(900)
+            .hydradx_fee_bps(50)
+            .interlay_fee_bps(100)
+            .hydradx_risk_score(1_500)
+            .interlay_risk_score(2_500)
+            .hydradx_min_hold_periods(30)
+            .interlay_min_hold_periods(7)
+            .hydradx_max_dot(600 * PRECISION)
+            .interlay_max_dot(700 * PRECISION)
+            .hurdle_rate_bps(200)
+            .hydradx_uptime_bps(9_900)
+            .interlay_uptime_bps(9_800)
+            .hydradx_compound_periods(12)
+            .interlay_compound_periods(52)
+            .hydradx_per_compound_fee(true)
+            .interlay_per_compound_fee(false)
+            .max_apy_bps(50_000)
+            .withdrawal_cost_dot(PRECISION / 10)
+            .hydradx_liquidity_depth_dot(5_000 * PRECISION)
+            .interlay_liquidity_depth_dot(8_000 * PRECISION)
+            .strict_viability(true)
+            .risk_aversion_bps(5_000)
+            .hydradx_apy_cap_bps(20_000)
+            .interlay_apy_cap_bps(15_000)
+            .prev_hydradx_pct(Some(60))
+            .hysteresis_bps(200)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            input,
+            OptimizerInput {
+                principal: 1_000 * PRECISION,
+                hydradx_apy_bps: 1_200,
+                interlay_apy_bps: 900,
+                hydradx_fee_bps: 50,
+                interlay_fee_bps: 100,
+                hydradx_risk_score: 1_500,
+                interlay_risk_score: 2_500,
+                projection_periods: 365,
+                hydradx_min_hold_periods: 30,
+                interlay_min_hold_periods: 7,
+                hydradx_max_dot: 600 * PRECISION,
+                interlay_max_dot: 700 * PRECISION,
+                hurdle_rate_bps: 200,
+                hydradx_uptime_bps: 9_900,
+                interlay_uptime_bps: 9_800,
+                hydradx_compound_periods: 12,
+                interlay_compound_periods: 52,
+                hydradx_per_compound_fee: true,
+                interlay_per_compound_fee: false,
+                max_apy_bps: 50_000,
+                withdrawal_cost_dot: PRECISION / 10,
+                hydradx_liquidity_depth_dot: 5_000 * PRECISION,
+                interlay_liquidity_depth_dot: 8_000 * PRECISION,
+                strict_viability: true,
+                risk_aversion_bps: 5_000,
+                hydradx_apy_cap_bps: 20_000,
+                interlay_apy_cap_bps: 15_000,
+                prev_hydradx_pct: Some(60),
+                hysteresis_bps: 200,
+            }
+        );
+    }
+
+    /// build() rejects what optimize() would reject on field validation alone.
+    #[test]
+    fn test_builder_validates() {
+        assert_eq!(
+            OptimizerInputBuilder::new(0, 365).build(),
+            Err(OptimizerError::InvalidInput)
+        );
+        assert_eq!(
+            OptimizerInputBuilder::new(PRECISION, 0).build(),
+            Err(OptimizerError::InvalidInput)
+        );
+        assert_eq!(
+            OptimizerInputBuilder::new(PRECISION, 365).interlay_fee_bps(10_001).build(),
+            Err(OptimizerError::InvalidInput)
+        );
+    }
+
+    /// Raising HydraDX's fee to its break-even value brings the default input
+    /// to a near-even split; one BPS more tips it to Interlay.
+    #[test]
+    fn test_break_even_fee_gives_near_even_split() {
+        let fee = break_even_fee_bps(&default_input(), true).unwrap();
+        assert!(fee > default_input().hydradx_fee_bps && fee < 10_000, "fee {fee}");
+
+        let at = optimize(&OptimizerInput { hydradx_fee_bps: fee, ..default_input() }).unwrap();
+        assert!(at.hydradx_allocation_pct.abs_diff(50) <= 1, "split {}", at.hydradx_allocation_pct);
+        assert!(at.hydradx_allocation_pct >= 50);
+
+        let past = optimize(&OptimizerInput { hydradx_fee_bps: fee + 1, ..default_input() }).unwrap();
+        assert!(past.hydradx_allocation_pct <= 50);
+    }
+
+    /// Interlay already trails at zero fee, so raising its fee never crosses.
+    #[test]
+    fn test_break_even_fee_never_crosses() {
+        assert_eq!(break_even_fee_bps(&default_input(), false).unwrap(), u32::MAX);
+    }
+
+    /// An invalid input fails as optimize() would.
+    #[test]
+    fn test_break_even_fee_invalid_input() {
+        let input = OptimizerInput { principal: 0, ..default_input() };
+        assert_eq!(break_even_fee_bps(&input, true), Err(OptimizerError::InvalidInput));
+    }
+
+    /// Without a withdrawal cost the net figure is the whole expected yield.
+    #[test]
+    fn test_net_expected_yield_defaults_to_expected_yield() {
+        let rec = optimize(&default_input()).unwrap();
+        assert_eq!(rec.net_expected_yield_dot, rec.expected_yield_dot);
+        assert!(rec.profitable);
+    }
+
+    /// A withdrawal cost below the yield is deducted and the position stays
+    /// profitable; nothing else in the recommendation moves.
+    #[test]
+    fn test_withdrawal_cost_reduces_net_yield() {
+        let base = optimize(&default_input()).unwrap();
+        let input = OptimizerInput { withdrawal_cost_dot: 5 * PRECISION, ..default_input() };
+        let rec = optimize(&input).unwrap();
+
+        assert_eq!(rec.net_expected_yield_dot, base.expected_yield_dot - 5 * PRECISION);
+        assert!(rec.net_expected_yield_dot > 0);
+        assert!(rec.profitable);
+        assert_eq!(rec.expected_yield_dot, base.expected_yield_dot);
+        assert_eq!(rec.hydradx_allocation_pct, base.hydradx_allocation_pct);
+    }
+
+    /// A withdrawal cost that eats the whole yield floors the net at 0 and
+    /// marks the position unprofitable.
+    #[test]
+    fn test_withdrawal_cost_above_yield_is_unprofitable() {
+        let input = OptimizerInput { withdrawal_cost_dot: 1_000 * PRECISION, ..default_input() };
+        let rec = optimize(&input).unwrap();
+        assert!(rec.expected_yield_dot > 0);
+        assert_eq!(rec.net_expected_yield_dot, 0);
+        assert!(!rec.profitable);
+    }
+
+    /// With both pools infinitely deep the marginal-equalizing split is the
+    /// proportional one optimize() already uses.
+    #[test]
+    fn test_marginal_split_infinite_depth_matches_proportional() {
+        let inputs = [
+            default_input(),
+            OptimizerInput { hydradx_apy_bps: 900, interlay_apy_bps: 1_200, ..default_input() },
+            OptimizerInput { hydradx_risk_score: 0, interlay_risk_score: 9_000, ..default_input() },
+            OptimizerInput { interlay_apy_bps: 0, ..default_input() },
+            OptimizerInput { hydradx_apy_bps: 0, interlay_apy_bps: 0, ..default_input() },
+        ];
+        for input in inputs {
+            let rec = optimize(&input).unwrap();
+            assert_eq!(
+                marginal_equalizing_split(&input).unwrap(),
+                (rec.hydradx_allocation_pct, rec.interlay_allocation_pct),
+                "{input:?}"
+            );
+        }
+    }
+
+    /// A shallow HydraDX pool pushes capital toward the deeper Interlay vault,
+    /// and the shallower the pool the further the shift.
+    #[test]
+    fn test_marginal_split_shifts_away_from_shallow_leg() {
+        let (proportional, _) = marginal_equalizing_split(&default_input()).unwrap();
+
+        let shallow = OptimizerInput { hydradx_liquidity_depth_dot: 500 * PRECISION, ..default_input() };
+        let (h_shallow, i_shallow) = marginal_equalizing_split(&shallow).unwrap();
+        assert_eq!(h_shallow + i_shallow, 100);
+        assert!(h_shallow < proportional, "{h_shallow} vs proportional {proportional}");
+
+        let shallower = OptimizerInput { hydradx_liquidity_depth_dot: 50 * PRECISION, ..default_input() };
+        let (h_shallower, _) = marginal_equalizing_split(&shallower).unwrap();
+        assert!(h_shallower < h_shallow, "{h_shallower} vs {h_shallow}");
+    }
+
+    /// A pool with no depth at all is not a usable input.
+    #[test]
+    fn test_marginal_split_zero_depth_is_invalid() {
+        let input = OptimizerInput { interlay_liquidity_depth_dot: 0, ..default_input() };
+        assert_eq!(marginal_equalizing_split(&input), Err(OptimizerError::InvalidInput));
+    }
+
+    /// Higher-yielding but riskier HydraDX: 2000 bps at risk 4000 against
+    /// Interlay's 800 at risk 1000.
+    fn loss_tradeoff_input() -> OptimizerInput {
+        OptimizerInput {
+            hydradx_apy_bps: 2_000,
+            interlay_apy_bps: 800,
+            hydradx_risk_score: 4_000,
+            interlay_risk_score: 1_000,
+            ..default_input()
+        }
+    }
+
+    /// A loose cap takes the higher yield outright; tightening it moves
+    /// capital to the safer leg until the loss just fits.
+    #[test]
+    fn test_max_loss_cap_shifts_toward_safer_leg() {
+        let input = loss_tradeoff_input();
+        assert_eq!(max_loss_constrained_split(&input, u32::MAX), Ok((100, 0)));
+        assert_eq!(max_loss_constrained_split(&input, 4_000), Ok((100, 0)));
+        // 50% × 4000 + 50% × 1000 = 2500 exactly; 51% would be 2530.
+        assert_eq!(max_loss_constrained_split(&input, 2_500), Ok((50, 50)));
+        assert_eq!(max_loss_constrained_split(&input, 1_000), Ok((0, 100)));
+    }
+
+    /// A cap below the safer leg's own risk admits no split.
+    #[test]
+    fn test_max_loss_impossible_cap_is_invalid() {
+        assert_eq!(
+            max_loss_constrained_split(&loss_tradeoff_input(), 999),
+            Err(OptimizerError::InvalidInput)
+        );
+        let riskless = OptimizerInput { interlay_risk_score: 0, ..loss_tradeoff_input() };
+        assert_eq!(max_loss_constrained_split(&riskless, 0), Ok((0, 100)));
+    }
+
+    /// Zero shocks reproduce optimize() exactly.
+    #[test]
+    fn test_stressed_zero_shock_equals_optimize() {
+        assert_eq!(optimize_stressed(&default_input(), 0, 0), optimize(&default_input()));
+    }
+
+    /// Raising both fees lowers the expected yield, more so the larger the
+    /// raise; no raise changes nothing.
+    #[test]
+    fn test_fee_sensitivity_sign_and_zero() {
+        assert_eq!(yield_fee_sensitivity(&default_input(), 0), Ok(0));
+        let small = yield_fee_sensitivity(&default_input(), 100).unwrap();
+        let large = yield_fee_sensitivity(&default_input(), 1_000).unwrap();
+        assert!(large < small && small < 0, "{small} / {large}");
+
+        let base = optimize(&default_input()).unwrap().expected_yield_dot;
+        let raised = optimize(&OptimizerInput {
+            hydradx_fee_bps: 150,
+            interlay_fee_bps: 200,
+            ..default_input()
+        })
+        .unwrap()
+        .expected_yield_dot;
+        assert_eq!(small, raised as i128 - base as i128);
+    }
+
+    /// A raise past 100% is rejected as optimize() would.
+    #[test]
+    fn test_fee_sensitivity_past_full_fee_is_invalid() {
+        assert_eq!(yield_fee_sensitivity(&default_input(), 9_951), Err(OptimizerError::InvalidInput));
+    }
+
+    /// HydraDX at a 1000 bps fee and risk 3000 against a fee-free Interlay
+    /// at 1000 bps APY and risk 500.
+    fn spread_disadvantage_input() -> OptimizerInput {
+        OptimizerInput {
+            hydradx_fee_bps: 1_000,
+            hydradx_risk_score: 3_000,
+            interlay_apy_bps: 1_000,
+            interlay_fee_bps: 0,
+            interlay_risk_score: 500,
+            ..default_input()
+        }
+    }
+
+    /// Whether HydraDX's risk-adjusted net yield strictly beats Interlay's,
+    /// from the step-3 net APYs optimize() reports.
+    fn hydradx_leads(input: &OptimizerInput) -> bool {
+        let (mut hydradx, mut interlay) = (0, 0);
+        optimize_with_hook(input, |label, value| match label {
+            "step3_hydradx_net_apy_bps" => hydradx = value as u32,
+            "step3_interlay_net_apy_bps" => interlay = value as u32,
+            _ => {}
+        })
+        .unwrap();
+        risk_adjusted_yield(hydradx, input.hydradx_risk_score).unwrap()
+            > risk_adjusted_yield(interlay, input.interlay_risk_score).unwrap()
+    }
+
+    /// A fee and risk disadvantage needs a 473 bps premium: at that spread
+    /// HydraDX leads, one BPS less it does not, and a wider lead tips the
+    /// whole-percentage split.
+    #[test]
+    fn test_winning_spread_overcomes_fee_and_risk() {
+        let input = spread_disadvantage_input();
+        let spread = winning_spread_bps(&input).unwrap();
+        assert_eq!(spread, 473);
+
+        let at = |spread: i32| OptimizerInput {
+            hydradx_apy_bps: (1_000 + spread) as u32,
+            ..input.clone()
+        };
+        assert!(hydradx_leads(&at(spread)));
+        assert!(!hydradx_leads(&at(spread - 1)));
+
+        let below = optimize(&at(spread - 200)).unwrap();
+        let above = optimize(&at(spread + 200)).unwrap();
+        assert!(below.hydradx_allocation_pct < below.interlay_allocation_pct);
+        assert!(above.hydradx_allocation_pct > above.interlay_allocation_pct);
+    }
+
+    /// A fee and risk edge lets HydraDX win while yielding less.
+    #[test]
+    fn test_winning_spread_negative_when_already_ahead() {
+        let spread = winning_spread_bps(&default_input()).unwrap();
+        assert!(spread < 0, "spread {spread}");
+        let at = OptimizerInput { hydradx_apy_bps: (900 + spread) as u32, ..default_input() };
+        assert!(hydradx_leads(&at));
+    }
+
+    /// A max-risk HydraDX never wins, nor does one capped out of reach; an
+    /// invalid input fails as optimize() would.
+    #[test]
+    fn test_winning_spread_unreachable_and_invalid() {
+        let max_risk = OptimizerInput { hydradx_risk_score: 10_000, ..default_input() };
+        assert_eq!(winning_spread_bps(&max_risk), Ok(i32::MAX));
+        let capped = OptimizerInput { hydradx_apy_cap_bps: 1_200, ..spread_disadvantage_input() };
+        assert_eq!(winning_spread_bps(&capped), Ok(i32::MAX));
+        let invalid = OptimizerInput { principal: 0, ..default_input() };
+        assert_eq!(winning_spread_bps(&invalid), Err(OptimizerError::InvalidInput));
+    }
+
+    /// No overrides reproduce optimize() exactly.
+    #[test]
+    fn test_override_none_equals_optimize() {
+        assert_eq!(optimize_with_override(&default_input(), None, None), optimize(&default_input()));
+    }
+
+    /// Overriding HydraDX's APY to zero collapses its allocation, and an
+    /// override matches setting the field directly.
+    #[test]
+    fn test_override_zero_apy_collapses_leg() {
+        let rec = optimize_with_override(&default_input(), Some(0), None).unwrap();
+        assert_eq!(rec.hydradx_allocation_pct, 0);
+        assert_eq!(rec.interlay_allocation_pct, 100);
+        assert!(!rec.use_hydradx);
+
+        let direct = OptimizerInput { hydradx_apy_bps: 0, interlay_apy_bps: 2_000, ..default_input() };
+        assert_eq!(optimize_with_override(&default_input(), Some(0), Some(2_000)), optimize(&direct));
+    }
+
+    /// Halving both APYs lowers the expected yield.
+    #[test]
+    fn test_stressed_apy_shock_lowers_yield() {
+        let base = optimize(&default_input()).unwrap();
+        let stressed = optimize_stressed(&default_input(), 5_000, 0).unwrap();
+        assert!(stressed.expected_yield_dot < base.expected_yield_dot);
+        assert!(stressed.projected_net_apy_bps < base.projected_net_apy_bps);
+    }
+
+    /// A uniform risk shock hurts Interlay (the riskier leg) relatively more,
+    /// so the split moves toward HydraDX.
+    #[test]
+    fn test_stressed_risk_shock_shifts_toward_less_shocked_leg() {
+        let base = optimize(&default_input()).unwrap();
+        let stressed = optimize_stressed(&default_input(), 5_000, 3_000).unwrap();
+        assert!(stressed.hydradx_allocation_pct > base.hydradx_allocation_pct);
+        assert!(stressed.expected_yield_dot < base.expected_yield_dot);
+    }
+
+    /// Risk is clamped at MAX_RISK_SCORE rather than rejected, and an APY shock
+    /// above 100% is invalid.
+    #[test]
+    fn test_stressed_bounds() {
+        let rec = optimize_stressed(&default_input(), 0, u32::MAX).unwrap();
+        assert_eq!((rec.hydradx_allocation_pct, rec.interlay_allocation_pct), (50, 50));
+        assert_eq!(
+            optimize_stressed(&default_input(), 10_001, 0),
+            Err(OptimizerError::InvalidInput)
+        );
+    }
+
+    /// Each leg's reported fee is gross_yield * fee_bps / BPS_DENOMINATOR on
+    /// the principal actually deployed there.
+    #[test]
+    fn test_fee_paid_matches_gross_yield_fraction() {
+        let input = default_input();
+        let rec = optimize(&input).unwrap();
+        let hydradx_gross = compound(rec.hydradx_allocation_dot, input.hydradx_apy_bps, 365).unwrap()
+            - rec.hydradx_allocation_dot;
+        let interlay_gross = compound(rec.interlay_allocation_dot, input.interlay_apy_bps, 365).unwrap()
+            - rec.interlay_allocation_dot;
+        assert_eq!(rec.hydradx_fee_paid_dot, hydradx_gross * 50 / BPS_DENOMINATOR);
+        assert_eq!(rec.interlay_fee_paid_dot, interlay_gross * 100 / BPS_DENOMINATOR);
+        assert!(rec.hydradx_fee_paid_dot > 0 && rec.interlay_fee_paid_dot > 0);
+    }
+
+    /// A zero-fee leg and an unallocated leg both report no fee paid.
+    #[test]
+    fn test_fee_paid_zero_for_free_or_unallocated_leg() {
+        let free = OptimizerInput { hydradx_fee_bps: 0, ..default_input() };
+        assert_eq!(optimize(&free).unwrap().hydradx_fee_paid_dot, 0);
+
+        // Max-risk Interlay takes a 100/0 split.
+        let one_sided = OptimizerInput { interlay_risk_score: 10_000, ..default_input() };
+        let rec = optimize(&one_sided).unwrap();
+        assert_eq!((rec.hydradx_allocation_pct, rec.interlay_allocation_pct), (100, 0));
+        assert_eq!(rec.interlay_fee_paid_dot, 0);
+        assert!(rec.hydradx_fee_paid_dot > 0);
+    }
+
+    /// With every leg at zero risk-adjusted yield the default still falls back
+    /// to 50/50, while strict_viability rejects the input outright.
+    #[test]
+    fn test_strict_viability_rejects_degenerate_input() {
+        let degenerate = OptimizerInput {
+            hydradx_apy_bps: 0,
+            interlay_apy_bps: 0,
+            hydradx_risk_score: 10_000,
+            interlay_risk_score: 10_000,
+            ..default_input()
+        };
+        let rec = optimize(&degenerate).unwrap();
+        assert_eq!((rec.hydradx_allocation_pct, rec.interlay_allocation_pct), (50, 50));
+
+        let strict = OptimizerInput { strict_viability: true, ..degenerate };
+        assert_eq!(optimize(&strict), Err(OptimizerError::NoViableDestination));
+    }
+
+    /// strict_viability changes nothing while at least one leg is viable.
+    #[test]
+    fn test_strict_viability_leaves_viable_input_alone() {
+        let strict = OptimizerInput { strict_viability: true, ..default_input() };
+        assert_eq!(optimize(&strict), optimize(&default_input()));
+
+        let one_viable = OptimizerInput { interlay_risk_score: 10_000, ..strict };
+        assert_eq!(optimize(&one_viable).unwrap().hydradx_allocation_pct, 100);
+    }
+
+    /// A high-yield, high-risk HydraDX leg against a safe Interlay leg.
+    fn risky_yield_input(risk_aversion_bps: u32) -> OptimizerInput {
+        OptimizerInput {
+            hydradx_apy_bps: 2_000,
+            interlay_apy_bps: 1_000,
+            hydradx_risk_score: 6_000,
+            interlay_risk_score: 0,
+            risk_aversion_bps,
+            ..default_input()
+        }
+    }
+
+    /// With no risk aversion the split follows raw yield and the risky leg
+    /// wins; with heavy aversion it loses more than under the standard penalty.
+    #[test]
+    fn test_risk_aversion_moves_split() {
+        let standard = optimize(&risky_yield_input(10_000)).unwrap();
+        let indifferent = optimize(&risky_yield_input(0)).unwrap();
+        let averse = optimize(&risky_yield_input(15_000)).unwrap();
+
+        assert!(indifferent.hydradx_allocation_pct > 50);
+        assert!(standard.hydradx_allocation_pct < 50);
+        assert!(averse.hydradx_allocation_pct < standard.hydradx_allocation_pct);
+    }
+
+    /// The default aversion is the standard penalty: over one fee-free period
+    /// the net APYs equal the gross ones, and the split is optimal_split's on
+    /// the raw risk scores.
+    #[test]
+    fn test_default_risk_aversion_reproduces_optimal_split() {
+        assert_eq!(OptimizerInput::default().risk_aversion_bps, 10_000);
+        let input = OptimizerInput {
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            projection_periods: 1,
+            ..risky_yield_input(OptimizerInput::default().risk_aversion_bps)
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(
+            (rec.hydradx_allocation_pct, rec.interlay_allocation_pct),
+            optimal_split(2_000, 1_000, 6_000, 0).unwrap()
+        );
+    }
+
+    /// Named schedules map to their period counts and Custom passes through.
+    #[test]
+    fn test_compounding_frequency_periods() {
+        assert_eq!(CompoundingFrequency::Daily.periods(), 365);
+        assert_eq!(CompoundingFrequency::Weekly.periods(), 52);
+        assert_eq!(CompoundingFrequency::Monthly.periods(), 12);
+        assert_eq!(CompoundingFrequency::Quarterly.periods(), 4);
+        assert_eq!(CompoundingFrequency::Custom(30).periods(), 30);
+    }
+
+    /// A named schedule sets projection_periods exactly as the raw field does.
+    #[test]
+    fn test_compounding_frequency_matches_raw_periods() {
+        let builder = OptimizerInputBuilder::new(1_000 * PRECISION, 0)
+            .hydradx_apy_bps(1_200)
+            .interlay_apy_bps(900)
+            .hydradx_fee_bps(50)
+            .interlay_fee_bps(100)
+            .hydradx_risk_score(1_500)
+            .interlay_risk_score(2_500);
+        let named = builder.clone().compounding_frequency(CompoundingFrequency::Daily).build().unwrap();
+        assert_eq!(named, default_input());
+        assert_eq!(optimize(&named), optimize(&default_input()));
+
+        let monthly = builder.compounding_frequency(CompoundingFrequency::Custom(12)).build().unwrap();
+        assert_eq!(monthly, OptimizerInput { projection_periods: 12, ..default_input() });
+    }
+
+    /// Uneven yields still attribute exactly 10_000 BPS, with the dust on the
+    /// larger leg.
+    #[test]
+    fn test_yield_attribution_sums_to_bps() {
+        assert_eq!(yield_attribution(1, 2), (3_333, 6_667));
+        assert_eq!(yield_attribution(2, 1), (6_667, 3_333));
+        for (h, i) in [(7, 13), (1_000 * PRECISION, 3), (u128::MAX, u128::MAX - 1), (999, 1)] {
+            let (h_bps, i_bps) = yield_attribution(h, i);
+            assert_eq!(h_bps + i_bps, 10_000, "{} / {}", h, i);
+        }
+    }
+
+    /// A single-leg position attributes everything to that leg; no yield at
+    /// all splits evenly.
+    #[test]
+    fn test_yield_attribution_single_leg_and_zero() {
+        assert_eq!(yield_attribution(5 * PRECISION, 0), (10_000, 0));
+        assert_eq!(yield_attribution(0, 5 * PRECISION), (0, 10_000));
+        assert_eq!(yield_attribution(0, 0), (5_000, 5_000));
+    }
+
+    /// A simulated feed holding the same market as default_input().
+    struct MockMarket;
+
+    impl MarketData for MockMarket {
+        fn principal(&self) -> u128 {
+            1_000 * PRECISION
+        }
+        fn hydradx_apy_bps(&self) -> u32 {
+            1_200
+        }
+        fn interlay_apy_bps(&self) -> u32 {
+            900
+        }
+        fn hydradx_fee_bps(&self) -> u32 {
+            50
+        }
+        fn interlay_fee_bps(&self) -> u32 {
+            100
+        }
+        fn hydradx_risk_score(&self) -> u32 {
+            1_500
+        }
+        fn interlay_risk_score(&self) -> u32 {
+            2_500
+        }
+        fn projection_periods(&self) -> u32 {
+            365
+        }
+    }
+
+    /// optimize() gives the same result from the struct and from an
+    /// equivalent mock, including through a trait object.
+    #[test]
+    fn test_market_data_mock_matches_struct() {
+        assert_eq!(MockMarket.to_input().into_owned(), default_input());
+        assert_eq!(optimize(&MockMarket), optimize(&default_input()));
+        let feed: &dyn MarketData = &MockMarket;
+        assert_eq!(optimize(feed), optimize(&default_input()));
+    }
+
+    /// Evaluating the optimizer's own recommended split reproduces its
+    /// expected yield and blended APY exactly.
+    #[test]
+    fn test_evaluate_split_reproduces_optimize() {
+        let inputs = [
+            default_input(),
+            OptimizerInput { interlay_apy_bps: 1_500, ..default_input() },
+            OptimizerInput { hydradx_compound_periods: 12, projection_periods: 30, ..default_input() },
+        ];
+        for input in inputs {
+            let rec = optimize(&input).unwrap();
+            let evaluated = evaluate_split(&input, rec.hydradx_allocation_pct).unwrap();
+            assert_eq!(evaluated.expected_yield_dot, rec.expected_yield_dot);
+            assert_eq!(evaluated.projected_net_apy_bps, rec.projected_net_apy_bps);
+            assert_eq!(evaluated, rec);
+        }
+    }
+
+    /// A manual split moves the projection, and shares above 100% are rejected.
+    #[test]
+    fn test_evaluate_split_manual_and_bounds() {
+        let all_hydradx = evaluate_split(&default_input(), 100).unwrap();
+        assert_eq!((all_hydradx.hydradx_allocation_pct, all_hydradx.interlay_allocation_pct), (100, 0));
+        let all_interlay = evaluate_split(&default_input(), 0).unwrap();
+        assert!(all_hydradx.expected_yield_dot > all_interlay.expected_yield_dot);
+        assert_eq!(evaluate_split(&default_input(), 101), Err(OptimizerError::InvalidInput));
+    }
+
+    /// The same yield at lower risk is the more capital-efficient allocation.
+    #[test]
+    fn test_capital_efficiency_prefers_lower_risk() {
+        let at_risk = |risk: u32| OptimizerInput {
+            hydradx_apy_bps: 1_000,
+            interlay_apy_bps: 1_000,
+            hydradx_risk_score: risk,
+            interlay_risk_score: risk,
+            ..default_input()
+        };
+        let safe = optimize(&at_risk(1_000)).unwrap();
+        let risky = optimize(&at_risk(6_000)).unwrap();
+        assert_eq!(safe.projected_net_apy_bps, risky.projected_net_apy_bps);
+        assert!(safe.capital_efficiency_bps > risky.capital_efficiency_bps);
+    }
+
+    /// A riskless allocation divides by 1, not 0.
+    #[test]
+    fn test_capital_efficiency_zero_risk() {
+        let input = OptimizerInput {
+            hydradx_apy_bps: 1_000,
+            interlay_apy_bps: 1_000,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 0,
+            interlay_risk_score: 0,
+            projection_periods: 1,
+            ..default_input()
+        };
+        let rec = optimize(&input).unwrap();
+        assert_eq!(rec.blended_risk_score, 0);
+        assert_eq!(rec.capital_efficiency_bps, 1_000 * 10_000);
+    }
+
+    /// With no withdrawal cost any principal is worth splitting.
+    #[test]
+    fn test_min_principal_for_split_zero_cost() {
+        assert_eq!(min_principal_for_split(&default_input()).unwrap(), 0);
+    }
+
+    /// Over one fee-free period net APY equals gross: the split is 60/40 and
+    /// Interlay's 40% at 9% must out-earn the cost, so 1 DOT of cost needs
+    /// just over 1 / (0.4 × 0.09) ≈ 27.78 DOT. A 1000× cost needs 1000× the
+    /// principal.
+    #[test]
+    fn test_min_principal_for_split_scales_with_cost() {
+        let input = |withdrawal_cost_dot: u128| OptimizerInput {
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            projection_periods: 1,
+            withdrawal_cost_dot,
+            ..default_input()
+        };
+        let rec = optimize(&input(PRECISION)).unwrap();
+        assert_eq!((rec.hydradx_allocation_pct, rec.interlay_allocation_pct), (60, 40));
+
+        let small = min_principal_for_split(&input(PRECISION)).unwrap();
+        assert_eq!(small, PRECISION * 1_000_000 / 36_000 + 1);
+        let large = min_principal_for_split(&input(1_000 * PRECISION)).unwrap();
+        assert_eq!(large, 1_000 * PRECISION * 1_000_000 / 36_000 + 1);
+    }
+
+    /// A one-sided split never pays for a second withdrawal.
+    #[test]
+    fn test_min_principal_for_split_one_sided_is_sentinel() {
+        let input = OptimizerInput {
+            withdrawal_cost_dot: PRECISION,
+            interlay_risk_score: 10_000,
+            ..default_input()
+        };
+        assert_eq!(min_principal_for_split(&input).unwrap(), u128::MAX);
+    }
+
+    /// The APY caps default to disabled.
+    #[test]
+    fn test_apy_caps_default_to_none() {
+        let defaults = OptimizerInput::default();
+        assert_eq!((defaults.hydradx_apy_cap_bps, defaults.interlay_apy_cap_bps), (u32::MAX, u32::MAX));
+    }
+
+    /// A spiked quote is clamped to its cap: the recommendation is the one the
+    /// capped APY itself produces, not an all-in allocation to the spike.
+    #[test]
+    fn test_apy_cap_clamps_spike() {
+        let spiked = OptimizerInput {
+            hydradx_apy_bps: 900_000,
+            hydradx_apy_cap_bps: 1_500,
+            ..default_input()
+        };
+        let at_cap = OptimizerInput { hydradx_apy_bps: 1_500, ..default_input() };
+        assert_eq!(optimize(&spiked), optimize(&at_cap));
+
+        // Left uncapped, a smaller spike still pulls capital toward HydraDX.
+        let smaller_spike = OptimizerInput { hydradx_apy_bps: 20_000, ..default_input() };
+        let smaller_capped = OptimizerInput { hydradx_apy_cap_bps: 1_500, ..smaller_spike.clone() };
+        assert!(
+            optimize(&smaller_spike).unwrap().hydradx_allocation_pct
+                > optimize(&smaller_capped).unwrap().hydradx_allocation_pct
+        );
+
+        // The Interlay cap behaves the same way.
+        let interlay_spiked = OptimizerInput {
+            interlay_apy_bps: 900_000,
+            interlay_apy_cap_bps: 1_000,
+            ..default_input()
+        };
+        let interlay_at_cap = OptimizerInput { interlay_apy_bps: 1_000, ..default_input() };
+        assert_eq!(optimize(&interlay_spiked), optimize(&interlay_at_cap));
+    }
+
+    /// A quote already under its cap is untouched.
+    #[test]
+    fn test_apy_cap_above_quote_is_noop() {
+        let capped = OptimizerInput {
+            hydradx_apy_cap_bps: 5_000,
+            interlay_apy_cap_bps: 900,
+            ..default_input()
+        };
+        assert_eq!(optimize(&capped), optimize(&default_input()));
+    }
+
+    /// Clamping happens before the max_apy_bps sanity check, so a capped
+    /// spike is accepted where the same uncapped spike is rejected.
+    #[test]
+    fn test_apy_cap_clamps_before_sanity_check() {
+        let spiked = OptimizerInput {
+            hydradx_apy_bps: 900_000,
+            max_apy_bps: 50_000,
+            ..default_input()
+        };
+        assert_eq!(optimize(&spiked), Err(OptimizerError::InvalidInput));
+
+        let capped = OptimizerInput { hydradx_apy_cap_bps: 1_500, ..spiked };
+        assert_eq!(validate_input(&capped), Ok(()));
+        assert_eq!(
+            optimize(&capped),
+            optimize(&OptimizerInput { hydradx_apy_bps: 1_500, ..default_input() })
+        );
+    }
+
+    /// Each use_* combination maps to its code.
+    #[test]
+    fn test_decision_code_cases() {
+        let code = |use_hydradx, use_interlay| {
+            decision_code(&YieldRecommendation { use_hydradx, use_interlay, ..Default::default() })
+        };
+        assert_eq!(code(true, true), 0);
+        assert_eq!(code(true, false), 1);
+        assert_eq!(code(false, true), 2);
+        assert_eq!(code(false, false), 3);
+    }
+
+    /// Codes from real recommendations agree with their allocation percentages.
+    #[test]
+    fn test_decision_code_matches_allocations() {
+        let both = optimize(&default_input()).unwrap();
+        assert_eq!(decision_code(&both), 0);
+        assert!(both.hydradx_allocation_pct > 0 && both.interlay_allocation_pct > 0);
+
+        let hydradx_only = optimize(&OptimizerInput { interlay_risk_score: 10_000, ..default_input() }).unwrap();
+        assert_eq!(decision_code(&hydradx_only), 1);
+        assert_eq!(hydradx_only.interlay_allocation_pct, 0);
+
+        let interlay_only = optimize(&OptimizerInput { hydradx_risk_score: 10_000, ..default_input() }).unwrap();
+        assert_eq!(decision_code(&interlay_only), 2);
+        assert_eq!(interlay_only.hydradx_allocation_pct, 0);
+
+        let abstain = optimize(&OptimizerInput { hurdle_rate_bps: 5_000, ..default_input() }).unwrap();
+        assert_eq!(decision_code(&abstain), 3);
+        assert_eq!(abstain.unallocated_pct, 100);
+    }
+
+    /// The hook fires once per label, step by step, and the recommendation
+    /// is optimize()'s.
+    #[test]
+    fn test_optimize_with_hook_sequence() {
+        let mut calls: Vec<(String, u128)> = Vec::new();
+        let rec = optimize_with_hook(&default_input(), |label, value| {
+            calls.push((label.to_string(), value))
+        })
+        .unwrap();
+        assert_eq!(Ok(rec.clone()), optimize(&default_input()));
+
+        let labels: Vec<&str> = calls.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "step1_hydradx_gross_yield",
+                "step1_interlay_gross_yield",
+                "step2_hydradx_net_yield",
+                "step2_interlay_net_yield",
+                "step3_hydradx_net_apy_bps",
+                "step3_interlay_net_apy_bps",
+                "step4_hydradx_pct",
+                "step5_expected_yield_dot",
+            ]
+        );
+        assert_eq!(calls[7].1, rec.expected_yield_dot);
+        // Net yield never exceeds gross once fees are taken.
+        assert!(calls[2].1 < calls[0].1 && calls[3].1 < calls[1].1);
+    }
+
+    /// A failing input stops the hook at the step that failed.
+    #[test]
+    fn test_optimize_with_hook_stops_on_error() {
+        let mut calls = 0;
+        let result = optimize_with_hook(&OptimizerInput { principal: 0, ..default_input() }, |_, _| {
+            calls += 1
+        });
+        assert_eq!(result, Err(OptimizerError::InvalidInput));
+        assert_eq!(calls, 0);
+    }
+
+    /// Hysteresis defaults to off.
+    #[test]
+    fn test_hysteresis_defaults_off() {
+        let defaults = OptimizerInput::default();
+        assert_eq!((defaults.prev_hydradx_pct, defaults.hysteresis_bps), (None, 0));
+    }
+
+    /// A new optimum within the band keeps the previous split; one outside
+    /// it is adopted.
+    #[test]
+    fn test_hysteresis_band() {
+        let fresh = optimize(&default_input()).unwrap().hydradx_allocation_pct;
+
+        let within = OptimizerInput {
+            prev_hydradx_pct: Some(fresh + 1),
+            hysteresis_bps: 200,
+            ..default_input()
+        };
+        let (rec, reason) = optimize_verbose(&within).unwrap();
+        assert_eq!(reason, SplitReason::HysteresisHeld);
+        assert_eq!(rec.binding_constraint, Some(Constraint::Hysteresis));
+        assert_eq!((rec.hydradx_allocation_pct, rec.interlay_allocation_pct), (fresh + 1, 99 - fresh));
+        // Apart from the reported constraint, it is the held split evaluated as is.
+        assert_eq!(
+            Ok(YieldRecommendation { binding_constraint: None, ..rec }),
+            evaluate_split(&default_input(), fresh + 1)
+        );
+
+        // Exactly on the band edge still holds.
+        let edge = OptimizerInput { prev_hydradx_pct: Some(fresh - 2), ..within.clone() };
+        assert_eq!(optimize(&edge).unwrap().hydradx_allocation_pct, fresh - 2);
+
+        let outside = OptimizerInput { prev_hydradx_pct: Some(fresh + 3), ..within };
+        let (rec, reason) = optimize_verbose(&outside).unwrap();
+        assert_eq!(reason, SplitReason::Proportional);
+        assert_eq!(Ok(rec), optimize(&default_input()));
+    }
+
+    /// Without a band, or at the same split, the optimum stands unchanged.
+    #[test]
+    fn test_hysteresis_noop_cases() {
+        let no_band = OptimizerInput { prev_hydradx_pct: Some(10), ..default_input() };
+        assert_eq!(optimize(&no_band), optimize(&default_input()));
+
+        let fresh = optimize(&default_input()).unwrap().hydradx_allocation_pct;
+        let same = OptimizerInput {
+            prev_hydradx_pct: Some(fresh),
+            hysteresis_bps: 500,
+            ..default_input()
+        };
+        assert_eq!(optimize_verbose(&same).unwrap().1, SplitReason::Proportional);
+    }
+
+    /// Dropping a max-risk leg is never held back, and an out-of-range
+    /// previous split is rejected.
+    #[test]
+    fn test_hysteresis_edges() {
+        let dropped = OptimizerInput {
+            interlay_risk_score: 10_000,
+            prev_hydradx_pct: Some(99),
+            hysteresis_bps: 500,
+            ..default_input()
+        };
+        assert_eq!(optimize(&dropped).unwrap().interlay_allocation_pct, 0);
+
+        let invalid = OptimizerInput { prev_hydradx_pct: Some(101), ..default_input() };
+        assert_eq!(optimize(&invalid), Err(OptimizerError::InvalidInput));
+    }
+
+    /// Total cost of ownership for a given input and its recommendation.
+    fn tco(input: &OptimizerInput) -> u128 {
+        total_cost_of_ownership(input, &optimize(input).unwrap()).unwrap()
+    }
+
+    /// With no fees and no withdrawal cost, holding is free.
+    #[test]
+    fn test_tco_zero_for_frictionless_input() {
+        let free = OptimizerInput { hydradx_fee_bps: 0, interlay_fee_bps: 0, ..default_input() };
+        assert_eq!(tco(&free), 0);
+    }
+
+    /// Each fee and the withdrawal cost raises the total on its own, and the
+    /// total is exactly the sum of its parts.
+    #[test]
+    fn test_tco_grows_with_each_component() {
+        let free = OptimizerInput { hydradx_fee_bps: 0, interlay_fee_bps: 0, ..default_input() };
+        let hydradx_fee = OptimizerInput { hydradx_fee_bps: 500, ..free.clone() };
+        let interlay_fee = OptimizerInput { interlay_fee_bps: 500, ..free.clone() };
+        let withdrawal = OptimizerInput { withdrawal_cost_dot: PRECISION, ..free.clone() };
+        assert!(tco(&hydradx_fee) > tco(&free));
+        assert!(tco(&interlay_fee) > tco(&free));
+        assert_eq!(tco(&withdrawal), PRECISION);
+
+        let all = OptimizerInput { withdrawal_cost_dot: PRECISION, ..default_input() };
+        let rec = optimize(&all).unwrap();
+        assert_eq!(tco(&all), rec.hydradx_fee_paid_dot + rec.interlay_fee_paid_dot + PRECISION);
+    }
+
+    /// A window return too large for u32 BPS fails with Overflow instead of
+    /// wrapping into a small net APY that step 5 would then compound.
+    #[test]
+    fn test_net_apy_beyond_u32_overflows() {
+        let input = OptimizerInput {
+            principal: PRECISION,
+            hydradx_apy_bps: u32::MAX,
+            hydradx_fee_bps: 0,
+            projection_periods: 2,
+            ..default_input()
+        };
+        // Step 1 alone copes: the gross compound fits in u128.
+        assert_eq!(validate_input(&input), Ok(()));
+        assert_eq!(optimize(&input), Err(OptimizerError::Math(MathError::Overflow)));
+    }
+
+    /// A net APY that fits u32 but compounds past u128 in step 5 also fails
+    /// cleanly with Overflow.
+    #[test]
+    fn test_step5_compound_overflows_cleanly() {
+        let input = OptimizerInput { hydradx_apy_bps: 50_000, interlay_risk_score: 10_000, ..default_input() };
+        assert_eq!(validate_input(&input), Ok(()));
+        assert_eq!(optimize(&input), Err(OptimizerError::Math(MathError::Overflow)));
+    }
+
+    /// optimize() rejects a destination validate_destination rejects.
+    #[test]
+    fn test_optimize_rejects_contradictory_destination() {
+        let dead = OptimizerInput {
+            interlay_apy_bps: 0,
+            interlay_fee_bps: 10_000,
+            ..default_input()
+        };
+        assert_eq!(optimize(&dead), Err(OptimizerError::Math(MathError::InvalidInput)));
+        assert!(optimize(&OptimizerInput { interlay_risk_score: 0, ..dead.clone() }).is_ok());
+
+        let over_risk = OptimizerInput { hydradx_risk_score: 10_001, ..default_input() };
+        assert_eq!(optimize(&over_risk), Err(OptimizerError::Math(MathError::InvalidInput)));
+    }
+
+    /// optimize_resilient clears the risk on a leg it clamps to a 100% fee,
+    /// so a dead leg still yields a recommendation for the other.
     #[test]
-    fn test_high_risk_reduces_allocation() {
-        // A: 2000 BPS but MAX risk (10000). B: 500 BPS, 0 risk.
-        // Risk-adjusted A: 2000 * (10000 - 10000) / 10000 = 0
-        // Risk-adjusted B: 500 * (10000 - 0) / 10000 = 500
-        // Split: A=0, B=100
-        let (a, b) = optimal_split(2_000, 500, 10_000, 0).unwrap();
-        assert_eq!(a, 0);
-        assert_eq!(b, 100);
+    fn test_resilient_clears_risk_on_dead_leg() {
+        let dead = OptimizerInput {
+            interlay_apy_bps: 0,
+            interlay_fee_bps: 50_000,
+            ..default_input()
+        };
+        let rec = optimize_resilient(&dead);
+        assert_eq!(rec.hydradx_allocation_pct, 100);
+        assert_eq!(rec.unallocated_pct, 0);
     }
+}
 
-    /// Both destinations at maximum risk should trigger the 50/50 fallback.
-    #[test]
-    fn test_both_max_risk_triggers_fifty_fifty_fallback() {
-        let (a, b) = optimal_split(1_000, 2_000, 10_000, 10_000).unwrap();
-        assert_eq!(a, 50);
-        assert_eq!(b, 50);
+// ---------------------------------------------------------------------------
+// Replay vectors
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod fixture_replay_tests {
+    use crate::yield_optimizer::{optimize, OptimizerInput};
+
+    /// Committed (input, expected recommendation) vectors, one per line.
+    const VECTORS: &str = include_str!("../fixtures/optimizer_vectors.txt");
+
+    /// The six ABI-visible recommendation fields a vector pins down.
+    #[derive(Debug, PartialEq)]
+    struct Expected {
+        use_hydradx: bool,
+        use_interlay: bool,
+        hydradx_allocation_pct: u64,
+        interlay_allocation_pct: u64,
+        projected_net_apy_bps: u32,
+        expected_yield_dot: u128,
     }
 
-    /// Zero yield on both destinations should trigger the 50/50 fallback.
-    #[test]
-    fn test_both_zero_yield_triggers_fifty_fifty_fallback() {
-        let (a, b) = optimal_split(0, 0, 500, 500).unwrap();
-        assert_eq!(a, 50);
-        assert_eq!(b, 50);
+    struct Vector {
+        name: String,
+        input: OptimizerInput,
+        expected: Expected,
     }
 
-    /// Risk score above MAX_RISK_SCORE must return InvalidInput.
+    /// Parse every non-blank, non-comment line. Panics with the line number on
+    /// any malformed entry so a bad edit to the fixture file is obvious.
+    fn load_vectors() -> Vec<Vector> {
+        VECTORS
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(idx, line)| {
+                let lineno = idx + 1;
+                let sections: Vec<&str> = line.split('|').map(str::trim).collect();
+                assert_eq!(sections.len(), 3, "line {lineno}: expected 3 sections");
+
+                let ins: Vec<&str> = sections[1].split_whitespace().collect();
+                let outs: Vec<&str> = sections[2].split_whitespace().collect();
+                assert_eq!(ins.len(), 8, "line {lineno}: expected 8 input fields");
+                assert_eq!(outs.len(), 6, "line {lineno}: expected 6 output fields");
+
+                let u32_at = |i: usize| -> u32 {
+                    ins[i].parse().unwrap_or_else(|_| panic!("line {lineno}: bad input field {i}"))
+                };
+
+                Vector {
+                    name: sections[0].to_string(),
+                    input: OptimizerInput {
+                        principal: ins[0].parse().expect("principal"),
+                        hydradx_apy_bps: u32_at(1),
+                        interlay_apy_bps: u32_at(2),
+                        hydradx_fee_bps: u32_at(3),
+                        interlay_fee_bps: u32_at(4),
+                        hydradx_risk_score: u32_at(5),
+                        interlay_risk_score: u32_at(6),
+                        projection_periods: u32_at(7),
+                        ..OptimizerInput::default()
+                    },
+                    expected: Expected {
+                        use_hydradx: outs[0].parse().expect("use_hydradx"),
+                        use_interlay: outs[1].parse().expect("use_interlay"),
+                        hydradx_allocation_pct: outs[2].parse().expect("hydradx_allocation_pct"),
+                        interlay_allocation_pct: outs[3].parse().expect("interlay_allocation_pct"),
+                        projected_net_apy_bps: outs[4].parse().expect("projected_net_apy_bps"),
+                        expected_yield_dot: outs[5].parse().expect("expected_yield_dot"),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// The fixture set must stay substantial and uniquely named.
     #[test]
-    fn test_risk_above_max_returns_invalid_input() {
-        let result = optimal_split(1_000, 1_000, 10_001, 0);
-        assert_eq!(result, Err(MathError::InvalidInput));
+    fn test_fixture_set_is_well_formed() {
+        let vectors = load_vectors();
+        assert!(vectors.len() >= 30, "expected at least 30 vectors, got {}", vectors.len());
+
+        let mut names: Vec<&str> = vectors.iter().map(|v| v.name.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), vectors.len(), "vector names must be unique");
     }
 
-    /// Zero risk on both sides means allocation is purely yield-proportional.
+    /// optimize must reproduce every committed vector exactly.
     #[test]
-    fn test_zero_risk_pure_yield_allocation() {
-        // 3000 vs 1000 BPS at zero risk → 75/25 split
-        let (a, b) = optimal_split(3_000, 1_000, 0, 0).unwrap();
-        assert_eq!(a, 75);
-        assert_eq!(b, 25);
+    fn test_optimizer_reproduces_all_vectors() {
+        for v in load_vectors() {
+            let rec = optimize(&v.input)
+                .unwrap_or_else(|e| panic!("{}: optimize failed with {:?}", v.name, e));
+            let actual = Expected {
+                use_hydradx: rec.use_hydradx,
+                use_interlay: rec.use_interlay,
+                hydradx_allocation_pct: rec.hydradx_allocation_pct,
+                interlay_allocation_pct: rec.interlay_allocation_pct,
+                projected_net_apy_bps: rec.projected_net_apy_bps,
+                expected_yield_dot: rec.expected_yield_dot,
+            };
+            assert_eq!(actual, v.expected, "vector `{}` diverged", v.name);
+        }
     }
 }
 
 // ---------------------------------------------------------------------------
-// yield_optimizer integration tests
+// JSON representation tests (serde feature)
 // ---------------------------------------------------------------------------
 
-#[cfg(test)]
-mod optimizer_tests {
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
     use crate::math_lib::PRECISION;
-    use crate::yield_optimizer::{optimize, OptimizerError, OptimizerInput};
+    use crate::yield_optimizer::{optimize, OptimizerInput, YieldRecommendation};
 
     fn default_input() -> OptimizerInput {
         OptimizerInput {
-            principal: 1_000 * PRECISION, // 1000 DOT
-            hydradx_apy_bps: 1_200,       // 12%
-            interlay_apy_bps: 900,         // 9%
-            hydradx_fee_bps: 50,           // 0.5%
-            interlay_fee_bps: 100,         // 1%
+            principal: 1_000 * PRECISION,
+            hydradx_apy_bps: 1_200,
+            interlay_apy_bps: 900,
+            hydradx_fee_bps: 50,
+            interlay_fee_bps: 100,
             hydradx_risk_score: 1_500,
             interlay_risk_score: 2_500,
             projection_periods: 365,
+            ..OptimizerInput::default()
         }
     }
 
-    /// Full pipeline with realistic inputs — confirm no error and sensible output.
+    /// Serialise → deserialise must reproduce the input exactly.
     #[test]
-    fn test_full_pipeline_realistic_inputs() {
-        let result = optimize(&default_input());
-        assert!(result.is_ok(), "Optimizer failed: {:?}", result);
-        let rec = result.unwrap();
+    fn test_input_json_round_trip() {
+        let input = default_input();
+        let json = serde_json::to_string(&input).unwrap();
+        let decoded: OptimizerInput = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, input);
+    }
 
-        // Allocation must sum to 100
+    /// A 1B DOT principal (1e27 units) exceeds f64's exact integer range and
+    /// must be written as a decimal string so no precision is lost.
+    #[test]
+    fn test_large_principal_serialised_as_string() {
+        let mut input = default_input();
+        input.principal = 1_000_000_000 * PRECISION;
+
+        let value = serde_json::to_value(&input).unwrap();
         assert_eq!(
-            rec.hydradx_allocation_pct + rec.interlay_allocation_pct,
-            100
+            value["principal"],
+            serde_json::Value::String("1000000000000000000000000000".into())
         );
-        // use_* flags must match allocation percentages
-        assert_eq!(rec.use_hydradx, rec.hydradx_allocation_pct > 0);
-        assert_eq!(rec.use_interlay, rec.interlay_allocation_pct > 0);
-        // Yield must be positive
-        assert!(rec.expected_yield_dot > 0);
-        // APY must be positive
-        assert!(rec.projected_net_apy_bps > 0);
+
+        let decoded: OptimizerInput = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.principal, 1_000_000_000 * PRECISION);
     }
 
-    /// Zero principal must return InvalidInput.
+    /// Recommendations round-trip too, including the u128 expected yield.
     #[test]
-    fn test_zero_principal_returns_error() {
-        let mut input = default_input();
-        input.principal = 0;
-        let result = optimize(&input);
-        assert_eq!(result, Err(OptimizerError::InvalidInput));
+    fn test_recommendation_json_round_trip() {
+        let rec = optimize(&default_input()).unwrap();
+        let json = serde_json::to_string(&rec).unwrap();
+        let decoded: YieldRecommendation = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, rec);
     }
 
-    /// Zero projection periods must return InvalidInput.
+    /// A numeric (non-string) principal is rejected rather than coerced.
     #[test]
-    fn test_zero_periods_returns_error() {
-        let mut input = default_input();
-        input.projection_periods = 0;
-        let result = optimize(&input);
-        assert_eq!(result, Err(OptimizerError::InvalidInput));
+    fn test_numeric_principal_rejected() {
+        let mut value = serde_json::to_value(default_input()).unwrap();
+        value["principal"] = serde_json::json!(1000);
+        assert!(serde_json::from_value::<OptimizerInput>(value).is_err());
     }
+}
 
-    /// Fee above 100% must return InvalidInput.
+#[cfg(test)]
+mod nth_root_tests {
+    use crate::math_lib::{nth_root, MathError, PRECISION};
+
+    /// Perfect cube: ∛(27e18) = 3e6 exactly.
     #[test]
-    fn test_fee_above_100pct_returns_error() {
-        let mut input = default_input();
-        input.hydradx_fee_bps = 10_001;
-        let result = optimize(&input);
-        assert_eq!(result, Err(OptimizerError::InvalidInput));
+    fn test_cube_root_perfect_power() {
+        assert_eq!(nth_root(27 * PRECISION, 3).unwrap(), 3_000_000);
     }
 
-    /// HydraDX clearly better (higher yield, lower risk) — should get majority allocation.
+    /// Perfect square on a PRECISION² value returns a PRECISION-scaled root.
     #[test]
-    fn test_hydradx_dominates_gets_majority_allocation() {
-        let input = OptimizerInput {
-            principal: 1_000 * PRECISION,
-            hydradx_apy_bps: 2_000, // 20%
-            interlay_apy_bps: 500,  // 5%
-            hydradx_fee_bps: 50,
-            interlay_fee_bps: 50,
-            hydradx_risk_score: 500,
-            interlay_risk_score: 4_000,
-            projection_periods: 365,
-        };
-        let rec = optimize(&input).unwrap();
-        assert!(
-            rec.hydradx_allocation_pct > rec.interlay_allocation_pct,
-            "HydraDX should dominate: got {}% vs {}%",
-            rec.hydradx_allocation_pct,
-            rec.interlay_allocation_pct
-        );
+    fn test_square_root_fixed_point() {
+        // √(4 × PRECISION²) = 2 × PRECISION
+        let x = 4 * PRECISION * PRECISION;
+        assert_eq!(nth_root(x, 2).unwrap(), 2 * PRECISION);
     }
 
-    /// Interlay clearly better (higher yield, lower risk) — should get majority allocation.
+    /// Non-perfect powers round down.
     #[test]
-    fn test_interlay_dominates_gets_majority_allocation() {
-        let input = OptimizerInput {
-            principal: 1_000 * PRECISION,
-            hydradx_apy_bps: 400,   // 4%
-            interlay_apy_bps: 2_500, // 25%
-            hydradx_fee_bps: 200,
-            interlay_fee_bps: 50,
-            hydradx_risk_score: 6_000,
-            interlay_risk_score: 800,
-            projection_periods: 365,
-        };
-        let rec = optimize(&input).unwrap();
-        assert!(
-            rec.interlay_allocation_pct > rec.hydradx_allocation_pct,
-            "Interlay should dominate: got {}% vs {}%",
-            rec.interlay_allocation_pct,
-            rec.hydradx_allocation_pct
-        );
+    fn test_floor_on_non_perfect_root() {
+        assert_eq!(nth_root(26, 3).unwrap(), 2);
+        assert_eq!(nth_root(27, 3).unwrap(), 3);
+        assert_eq!(nth_root(63, 3).unwrap(), 3);
+        assert_eq!(nth_root(99, 2).unwrap(), 9);
     }
 
-    /// Both yields at zero — optimizer should still return 50/50 without erroring.
+    /// Exhaustive floor check over a small range for several roots.
     #[test]
-    fn test_both_zero_apy_returns_fifty_fifty_no_error() {
-        let input = OptimizerInput {
-            principal: 1_000 * PRECISION,
-            hydradx_apy_bps: 0,
-            interlay_apy_bps: 0,
-            hydradx_fee_bps: 0,
-            interlay_fee_bps: 0,
-            hydradx_risk_score: 500,
-            interlay_risk_score: 500,
-            projection_periods: 365,
-        };
-        let rec = optimize(&input).unwrap();
-        assert_eq!(rec.hydradx_allocation_pct, 50);
-        assert_eq!(rec.interlay_allocation_pct, 50);
-        assert_eq!(rec.expected_yield_dot, 0);
+    fn test_floor_invariant_small_range() {
+        for n in 2u32..=6 {
+            for x in 0u128..5_000 {
+                let r = nth_root(x, n).unwrap();
+                assert!(r.pow(n) <= x, "{r}^{n} must not exceed {x}");
+                assert!((r + 1).pow(n) > x, "({r}+1)^{n} must exceed {x}");
+            }
+        }
     }
 
-    /// Determinism: identical inputs always produce identical outputs.
+    /// n = 0 is undefined and must be rejected.
     #[test]
-    fn test_full_pipeline_is_deterministic() {
-        let input = default_input();
-        let r1 = optimize(&input).unwrap();
-        let r2 = optimize(&input).unwrap();
+    fn test_zeroth_root_is_invalid() {
+        assert_eq!(nth_root(1_000, 0), Err(MathError::InvalidInput));
+    }
+
+    /// n = 1 is the identity.
+    #[test]
+    fn test_first_root_is_identity() {
+        assert_eq!(nth_root(123_456_789, 1).unwrap(), 123_456_789);
+        assert_eq!(nth_root(u128::MAX, 1).unwrap(), u128::MAX);
+    }
+
+    /// Zero and one are fixed points of every root.
+    #[test]
+    fn test_zero_and_one() {
+        assert_eq!(nth_root(0, 5).unwrap(), 0);
+        assert_eq!(nth_root(1, 5).unwrap(), 1);
+    }
+
+    /// u128 extremes must converge without overflow.
+    #[test]
+    fn test_u128_max_boundaries() {
+        assert_eq!(nth_root(u128::MAX, 2).unwrap(), u64::MAX as u128);
+        // 2^127 ≤ u128::MAX < 3^127
+        assert_eq!(nth_root(u128::MAX, 127).unwrap(), 2);
+        assert_eq!(nth_root(u128::MAX, 128).unwrap(), 1);
+        assert_eq!(nth_root(u128::MAX, u32::MAX).unwrap(), 1);
+    }
+
+    /// Determinism: same inputs always produce the same root.
+    #[test]
+    fn test_determinism() {
+        let r1 = nth_root(987_654_321 * PRECISION, 7).unwrap();
+        let r2 = nth_root(987_654_321 * PRECISION, 7).unwrap();
         assert_eq!(r1, r2);
     }
+}
 
-    /// Large principal (1B DOT) — verify no overflow through the full pipeline.
+// ---------------------------------------------------------------------------
+// Weight estimation tests (weights feature)
+// ---------------------------------------------------------------------------
+
+#[cfg(all(test, feature = "weights"))]
+mod optimize_weight_tests {
+    use crate::yield_optimizer::{
+        optimize_weight, OptimizerInput, OPTIMIZE_BASE_REF_TIME, OPTIMIZE_PROOF_SIZE,
+        OPTIMIZE_REF_TIME_PER_COMPOUND_STEP,
+    };
+
+    fn input_with_periods(projection_periods: u32) -> OptimizerInput {
+        OptimizerInput { projection_periods, ..OptimizerInput::default() }
+    }
+
+    /// ref_time grows linearly with the compound loop count.
     #[test]
-    fn test_large_principal_no_overflow() {
-        let input = OptimizerInput {
-            principal: 1_000_000_000 * PRECISION, // 1B DOT
-            hydradx_apy_bps: 1_000,
-            interlay_apy_bps: 800,
-            hydradx_fee_bps: 50,
-            interlay_fee_bps: 100,
-            hydradx_risk_score: 1_000,
-            interlay_risk_score: 2_000,
-            projection_periods: 365,
-        };
-        let result = optimize(&input);
-        assert!(
-            result.is_ok(),
-            "1B DOT pipeline should not overflow: {:?}",
-            result
+    fn test_ref_time_scales_with_periods() {
+        let w_1 = optimize_weight(&input_with_periods(1));
+        let w_365 = optimize_weight(&input_with_periods(365));
+        let w_730 = optimize_weight(&input_with_periods(730));
+
+        assert!(w_1.ref_time() < w_365.ref_time());
+        assert!(w_365.ref_time() < w_730.ref_time());
+        assert_eq!(
+            w_730.ref_time() - w_365.ref_time(),
+            4 * 365 * OPTIMIZE_REF_TIME_PER_COMPOUND_STEP
         );
     }
 
-    /// Single compounding period — ensure optimizer handles minimal periods correctly.
+    /// Zero periods is charged the base cost alone.
     #[test]
-    fn test_single_period_optimizer() {
-        let input = OptimizerInput {
-            principal: 10_000 * PRECISION,
-            hydradx_apy_bps: 1_000,
-            interlay_apy_bps: 500,
-            hydradx_fee_bps: 0,
-            interlay_fee_bps: 0,
-            hydradx_risk_score: 0,
-            interlay_risk_score: 0,
-            projection_periods: 1,
-        };
-        let rec = optimize(&input).unwrap();
-        assert!(rec.expected_yield_dot > 0);
-        assert_eq!(rec.hydradx_allocation_pct + rec.interlay_allocation_pct, 100);
+    fn test_zero_periods_is_base_weight() {
+        assert_eq!(optimize_weight(&input_with_periods(0)).ref_time(), OPTIMIZE_BASE_REF_TIME);
     }
 
-    /// Maximum risk on both sides — optimizer must return 50/50 and not error.
+    /// The optimizer reads no storage, so proof_size never varies.
     #[test]
-    fn test_max_risk_both_sides_fifty_fifty() {
-        let input = OptimizerInput {
-            principal: 500 * PRECISION,
-            hydradx_apy_bps: 2_000,
-            interlay_apy_bps: 1_500,
-            hydradx_fee_bps: 100,
-            interlay_fee_bps: 200,
-            hydradx_risk_score: 10_000,
-            interlay_risk_score: 10_000,
-            projection_periods: 365,
-        };
-        let rec = optimize(&input).unwrap();
-        assert_eq!(rec.hydradx_allocation_pct, 50);
-        assert_eq!(rec.interlay_allocation_pct, 50);
+    fn test_proof_size_is_constant() {
+        for periods in [0u32, 1, 365, u32::MAX] {
+            assert_eq!(optimize_weight(&input_with_periods(periods)).proof_size(), OPTIMIZE_PROOF_SIZE);
+        }
     }
 
-    /// use_hydradx flag must be false when hydradx gets 0% allocation.
+    /// u32::MAX periods must not overflow the ref_time computation.
     #[test]
-    fn test_use_flags_consistent_with_allocation() {
-        // Max risk on HydraDX forces its allocation to 0
-        let input = OptimizerInput {
-            principal: 1_000 * PRECISION,
-            hydradx_apy_bps: 5_000,
-            interlay_apy_bps: 1_000,
-            hydradx_fee_bps: 0,
-            interlay_fee_bps: 0,
-            hydradx_risk_score: 10_000, // Max risk — wipes adj yield to 0
-            interlay_risk_score: 0,
-            projection_periods: 365,
-        };
-        let rec = optimize(&input).unwrap();
-        assert!(!rec.use_hydradx, "use_hydradx should be false when pct = 0");
-        assert!(rec.use_interlay, "use_interlay should be true when pct = 100");
-        assert_eq!(rec.hydradx_allocation_pct, 0);
-        assert_eq!(rec.interlay_allocation_pct, 100);
+    fn test_max_periods_does_not_overflow() {
+        let w = optimize_weight(&input_with_periods(u32::MAX));
+        assert_eq!(
+            w.ref_time(),
+            OPTIMIZE_BASE_REF_TIME + 4 * u32::MAX as u64 * OPTIMIZE_REF_TIME_PER_COMPOUND_STEP
+        );
+    }
+
+    /// A leg compounding more often than projection_periods costs more.
+    #[test]
+    fn test_leg_compound_periods_add_weight() {
+        let base = optimize_weight(&input_with_periods(12));
+        let daily_leg = optimize_weight(&OptimizerInput {
+            hydradx_compound_periods: 365,
+            ..input_with_periods(12)
+        });
+        assert_eq!(
+            daily_leg.ref_time() - base.ref_time(),
+            (365 - 12) * OPTIMIZE_REF_TIME_PER_COMPOUND_STEP
+        );
     }
-}
\ No newline at end of file
+}
@@ -22,7 +22,14 @@
 /// the XCM dispatch and emits a FailedOptimization event rather than proceeding
 /// with a yield loop built on corrupt math.
 
-use crate::math_lib::{self, BPS_DENOMINATOR, MathError};
+use crate::math_lib::{self, BPS_DENOMINATOR, MAX_PRINCIPAL, MAX_RISK_SCORE, MathError, MathResult};
+use std::borrow::Cow;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "weights")]
+use frame_support::weights::Weight;
 
 // ---------------------------------------------------------------------------
 // Error type
@@ -35,6 +42,9 @@ pub enum OptimizerError {
     /// Logically invalid inputs that pass individual field validation but fail
     /// cross-field consistency checks (e.g. zero principal, zero periods).
     InvalidInput,
+    /// Every destination has zero risk-adjusted yield and the caller set
+    /// strict_viability, so the 50/50 fallback was refused.
+    NoViableDestination,
 }
 
 impl From<MathError> for OptimizerError {
@@ -55,9 +65,11 @@ pub type OptimizerResult<T> = Result<T, OptimizerError>;
 /// in [0, 10_000]. The principal is in fixed-point DOT with 18 decimal places.
 /// Projection periods define the number of compounding intervals the optimizer
 /// should simulate (typically 365 for daily compounding over one year).
-#[derive(Debug, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct OptimizerInput {
     /// Total DOT being allocated in this yield cycle (18 decimal fixed-point).
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
     pub principal: u128,
 
     /// HydraDX omnipool gross annual yield in basis points (e.g. 1200 = 12%).
@@ -81,6 +93,347 @@ pub struct OptimizerInput {
     /// Number of discrete compounding periods to project over.
     /// Use 365 for daily compounding, 12 for monthly, 52 for weekly.
     pub projection_periods: u32,
+
+    /// Minimum number of periods a HydraDX position must be held before its
+    /// yield is released. If projection_periods falls short, the leg is treated
+    /// as yielding nothing. 0 disables the check.
+    pub hydradx_min_hold_periods: u32,
+
+    /// Minimum number of periods an Interlay position must be held before its
+    /// yield is released. If projection_periods falls short, the leg is treated
+    /// as yielding nothing. 0 disables the check.
+    pub interlay_min_hold_periods: u32,
+
+    /// Absolute ceiling on DOT deployed to HydraDX (18 decimal fixed-point),
+    /// independent of principal size. u128::MAX disables the cap.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub hydradx_max_dot: u128,
+
+    /// Absolute ceiling on DOT deployed to Interlay (18 decimal fixed-point),
+    /// independent of principal size. u128::MAX disables the cap.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub interlay_max_dot: u128,
+
+    /// Minimum net return in basis points a destination must exceed to receive
+    /// capital, on the same projection-window basis as projected_net_apy_bps.
+    /// A leg at or below the hurdle has its share left undeployed in DOT.
+    /// 0 disables the check.
+    pub hurdle_rate_bps: u32,
+
+    /// Fraction of time the HydraDX destination is accepting deposits and
+    /// accruing yield, in basis points (10_000 = always up). Net yield is
+    /// scaled by this before the split.
+    pub hydradx_uptime_bps: u32,
+
+    /// Fraction of time the Interlay vault is accepting deposits and accruing
+    /// yield, in basis points (10_000 = always up). Net yield is scaled by
+    /// this before the split.
+    pub interlay_uptime_bps: u32,
+
+    /// Number of times HydraDX compounds over the projection window.
+    /// 0 uses projection_periods.
+    pub hydradx_compound_periods: u32,
+
+    /// Number of times Interlay compounds over the projection window.
+    /// 0 uses projection_periods.
+    pub interlay_compound_periods: u32,
+
+    /// If true, hydradx_fee_bps is charged on the balance at every HydraDX
+    /// compounding event (see math_lib::compound_with_step_fee) instead of
+    /// once on the window's gross yield.
+    pub hydradx_per_compound_fee: bool,
+
+    /// If true, interlay_fee_bps is charged on the balance at every Interlay
+    /// compounding event instead of once on the window's gross yield.
+    pub interlay_per_compound_fee: bool,
+
+    /// Sanity ceiling on either destination's gross APY in basis points. A
+    /// quote above it is treated as a data error and the whole input is
+    /// rejected before compounding. 0 or u32::MAX disables the check.
+    pub max_apy_bps: u32,
+
+    /// Gas cost of withdrawing the position at the end of the window, in DOT
+    /// (18 decimal fixed-point). Deducted only from net_expected_yield_dot.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub withdrawal_cost_dot: u128,
+
+    /// HydraDX pool depth in DOT (18 decimal fixed-point): the position size
+    /// at which marginal yield has halved. marginal_equalizing_split weighs
+    /// the split by it, and optimize() deploys no more than it to the leg.
+    /// u128::MAX models an infinitely deep pool.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub hydradx_liquidity_depth_dot: u128,
+
+    /// Interlay vault depth in DOT, on the same terms as
+    /// hydradx_liquidity_depth_dot. u128::MAX models infinite depth.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub interlay_liquidity_depth_dot: u128,
+
+    /// If true, an input where every destination has zero risk-adjusted yield
+    /// fails with OptimizerError::NoViableDestination instead of taking the
+    /// 50/50 fallback split.
+    pub strict_viability: bool,
+
+    /// How heavily risk scores weigh on the split, in basis points of the
+    /// standard penalty (see math_lib::scaled_risk). 10_000 is the standard
+    /// mean-variance penalty, 0 allocates on raw yield alone, and higher
+    /// values penalise risk more. blended_risk_score still reports raw scores.
+    pub risk_aversion_bps: u32,
+
+    /// Ceiling on HydraDX's gross APY in basis points. A higher quote is
+    /// clamped to the cap before any computation, including the max_apy_bps
+    /// check, rather than rejected. u32::MAX disables the cap.
+    pub hydradx_apy_cap_bps: u32,
+
+    /// Ceiling on Interlay's gross APY in basis points, on the same terms as
+    /// hydradx_apy_cap_bps. u32::MAX disables the cap.
+    pub interlay_apy_cap_bps: u32,
+
+    /// The HydraDX share (0–100) of the allocation currently held, if any.
+    /// Used with hysteresis_bps to keep the split stable between blocks.
+    pub prev_hydradx_pct: Option<u64>,
+
+    /// Band, in basis points of principal, within which a new optimal split
+    /// is ignored in favour of prev_hydradx_pct: the previous split is kept
+    /// unless the HydraDX share moves by more than the band, or the new split
+    /// drops a leg entirely. 100 BPS is one percentage point. 0, or no
+    /// previous split, disables the check.
+    pub hysteresis_bps: u32,
+}
+
+impl Default for OptimizerInput {
+    /// Zeroed market data with every optional constraint disabled.
+    ///
+    /// Not a valid optimizer input on its own (principal and periods are 0) —
+    /// callers set the market fields explicitly and spread the rest with
+    /// `..OptimizerInput::default()`, which keeps call sites stable as new
+    /// optional fields are added.
+    fn default() -> Self {
+        OptimizerInput {
+            principal: 0,
+            hydradx_apy_bps: 0,
+            interlay_apy_bps: 0,
+            hydradx_fee_bps: 0,
+            interlay_fee_bps: 0,
+            hydradx_risk_score: 0,
+            interlay_risk_score: 0,
+            projection_periods: 0,
+            hydradx_min_hold_periods: 0,
+            interlay_min_hold_periods: 0,
+            hydradx_max_dot: u128::MAX,
+            interlay_max_dot: u128::MAX,
+            hurdle_rate_bps: 0,
+            hydradx_uptime_bps: BPS_DENOMINATOR as u32,
+            interlay_uptime_bps: BPS_DENOMINATOR as u32,
+            hydradx_compound_periods: 0,
+            interlay_compound_periods: 0,
+            hydradx_per_compound_fee: false,
+            interlay_per_compound_fee: false,
+            max_apy_bps: 0,
+            withdrawal_cost_dot: 0,
+            hydradx_liquidity_depth_dot: u128::MAX,
+            interlay_liquidity_depth_dot: u128::MAX,
+            strict_viability: false,
+            risk_aversion_bps: BPS_DENOMINATOR as u32,
+            hydradx_apy_cap_bps: u32::MAX,
+            interlay_apy_cap_bps: u32::MAX,
+            prev_hydradx_pct: None,
+            hysteresis_bps: 0,
+        }
+    }
+}
+
+/// Named compounding schedules for projection_periods: one period per
+/// compounding event over a one-year window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CompoundingFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    /// Any other period count, passed through unchanged.
+    Custom(u32),
+}
+
+impl CompoundingFrequency {
+    /// The projection_periods value this schedule stands for.
+    pub fn periods(self) -> u32 {
+        match self {
+            CompoundingFrequency::Daily => 365,
+            CompoundingFrequency::Weekly => 52,
+            CompoundingFrequency::Monthly => 12,
+            CompoundingFrequency::Quarterly => 4,
+            CompoundingFrequency::Custom(n) => n,
+        }
+    }
+}
+
+/// Builds an OptimizerInput from its two required fields, with every other
+/// field at its OptimizerInput::default() value until set.
+///
+/// Each setter is named after the field it sets, except compounding_frequency(),
+/// a named alternative to the projection_periods argument. build() runs the
+/// same field validation as optimize(), so a built input is never rejected for
+/// a zero principal, zero periods, or an out-of-range fee, uptime or APY.
+#[derive(Debug, Clone)]
+pub struct OptimizerInputBuilder {
+    input: OptimizerInput,
+}
+
+impl OptimizerInputBuilder {
+    pub fn new(principal: u128, projection_periods: u32) -> Self {
+        OptimizerInputBuilder {
+            input: OptimizerInput { principal, projection_periods, ..OptimizerInput::default() },
+        }
+    }
+
+    /// Sets projection_periods from a named schedule, overriding the value
+    /// passed to new().
+    pub fn compounding_frequency(mut self, frequency: CompoundingFrequency) -> Self {
+        self.input.projection_periods = frequency.periods();
+        self
+    }
+
+    pub fn hydradx_apy_bps(mut self, hydradx_apy_bps: u32) -> Self {
+        self.input.hydradx_apy_bps = hydradx_apy_bps;
+        self
+    }
+
+    pub fn interlay_apy_bps(mut self, interlay_apy_bps: u32) -> Self {
+        self.input.interlay_apy_bps = interlay_apy_bps;
+        self
+    }
+
+    pub fn hydradx_fee_bps(mut self, hydradx_fee_bps: u32) -> Self {
+        self.input.hydradx_fee_bps = hydradx_fee_bps;
+        self
+    }
+
+    pub fn interlay_fee_bps(mut self, interlay_fee_bps: u32) -> Self {
+        self.input.interlay_fee_bps = interlay_fee_bps;
+        self
+    }
+
+    pub fn hydradx_risk_score(mut self, hydradx_risk_score: u32) -> Self {
+        self.input.hydradx_risk_score = hydradx_risk_score;
+        self
+    }
+
+    pub fn interlay_risk_score(mut self, interlay_risk_score: u32) -> Self {
+        self.input.interlay_risk_score = interlay_risk_score;
+        self
+    }
+
+    pub fn hydradx_min_hold_periods(mut self, hydradx_min_hold_periods: u32) -> Self {
+        self.input.hydradx_min_hold_periods = hydradx_min_hold_periods;
+        self
+    }
+
+    pub fn interlay_min_hold_periods(mut self, interlay_min_hold_periods: u32) -> Self {
+        self.input.interlay_min_hold_periods = interlay_min_hold_periods;
+        self
+    }
+
+    pub fn hydradx_max_dot(mut self, hydradx_max_dot: u128) -> Self {
+        self.input.hydradx_max_dot = hydradx_max_dot;
+        self
+    }
+
+    pub fn interlay_max_dot(mut self, interlay_max_dot: u128) -> Self {
+        self.input.interlay_max_dot = interlay_max_dot;
+        self
+    }
+
+    pub fn hurdle_rate_bps(mut self, hurdle_rate_bps: u32) -> Self {
+        self.input.hurdle_rate_bps = hurdle_rate_bps;
+        self
+    }
+
+    pub fn hydradx_uptime_bps(mut self, hydradx_uptime_bps: u32) -> Self {
+        self.input.hydradx_uptime_bps = hydradx_uptime_bps;
+        self
+    }
+
+    pub fn interlay_uptime_bps(mut self, interlay_uptime_bps: u32) -> Self {
+        self.input.interlay_uptime_bps = interlay_uptime_bps;
+        self
+    }
+
+    pub fn hydradx_compound_periods(mut self, hydradx_compound_periods: u32) -> Self {
+        self.input.hydradx_compound_periods = hydradx_compound_periods;
+        self
+    }
+
+    pub fn interlay_compound_periods(mut self, interlay_compound_periods: u32) -> Self {
+        self.input.interlay_compound_periods = interlay_compound_periods;
+        self
+    }
+
+    pub fn hydradx_per_compound_fee(mut self, hydradx_per_compound_fee: bool) -> Self {
+        self.input.hydradx_per_compound_fee = hydradx_per_compound_fee;
+        self
+    }
+
+    pub fn interlay_per_compound_fee(mut self, interlay_per_compound_fee: bool) -> Self {
+        self.input.interlay_per_compound_fee = interlay_per_compound_fee;
+        self
+    }
+
+    pub fn max_apy_bps(mut self, max_apy_bps: u32) -> Self {
+        self.input.max_apy_bps = max_apy_bps;
+        self
+    }
+
+    pub fn withdrawal_cost_dot(mut self, withdrawal_cost_dot: u128) -> Self {
+        self.input.withdrawal_cost_dot = withdrawal_cost_dot;
+        self
+    }
+
+    pub fn hydradx_liquidity_depth_dot(mut self, hydradx_liquidity_depth_dot: u128) -> Self {
+        self.input.hydradx_liquidity_depth_dot = hydradx_liquidity_depth_dot;
+        self
+    }
+
+    pub fn interlay_liquidity_depth_dot(mut self, interlay_liquidity_depth_dot: u128) -> Self {
+        self.input.interlay_liquidity_depth_dot = interlay_liquidity_depth_dot;
+        self
+    }
+
+    pub fn strict_viability(mut self, strict_viability: bool) -> Self {
+        self.input.strict_viability = strict_viability;
+        self
+    }
+
+    pub fn risk_aversion_bps(mut self, risk_aversion_bps: u32) -> Self {
+        self.input.risk_aversion_bps = risk_aversion_bps;
+        self
+    }
+
+    pub fn hydradx_apy_cap_bps(mut self, hydradx_apy_cap_bps: u32) -> Self {
+        self.input.hydradx_apy_cap_bps = hydradx_apy_cap_bps;
+        self
+    }
+
+    pub fn interlay_apy_cap_bps(mut self, interlay_apy_cap_bps: u32) -> Self {
+        self.input.interlay_apy_cap_bps = interlay_apy_cap_bps;
+        self
+    }
+
+    pub fn prev_hydradx_pct(mut self, prev_hydradx_pct: Option<u64>) -> Self {
+        self.input.prev_hydradx_pct = prev_hydradx_pct;
+        self
+    }
+
+    pub fn hysteresis_bps(mut self, hysteresis_bps: u32) -> Self {
+        self.input.hysteresis_bps = hysteresis_bps;
+        self
+    }
+
+    pub fn build(self) -> OptimizerResult<OptimizerInput> {
+        check_input_fields(&self.input)?;
+        Ok(self.input)
+    }
 }
 
 /// The recommendation struct returned to AtomicYieldExecutor.sol.
@@ -90,7 +443,12 @@ pub struct OptimizerInput {
 ///   - *_allocation_pct: how to split the principal across two XCM dispatch calls
 ///   - projected_net_apy_bps: logged in the YieldLoopExecuted event on-chain
 ///   - expected_yield_dot: used for minimum-output slippage checks in the XCM Transact
-#[derive(Debug, PartialEq, Clone)]
+///
+/// Fields after expected_yield_dot are off-chain reporting figures. They are
+/// not part of the ABI payload — encode_yield_recommendation carries only the
+/// six fields above, which is all the Solidity decoder reads.
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct YieldRecommendation {
     /// Whether to dispatch a HydraDX XCM leg.
     pub use_hydradx: bool,
@@ -102,7 +460,8 @@ pub struct YieldRecommendation {
     pub hydradx_allocation_pct: u64,
 
     /// Percentage of principal to allocate to Interlay (0–100).
-    /// hydradx_allocation_pct + interlay_allocation_pct == 100 always.
+    /// hydradx_allocation_pct + interlay_allocation_pct == 100 whenever
+    /// unallocated_dot is 0.
     pub interlay_allocation_pct: u64,
 
     /// Blended net APY across both destinations in basis points.
@@ -111,7 +470,202 @@ pub struct YieldRecommendation {
     /// Expected absolute DOT yield over the projection window (18 decimal fixed-point).
     /// This is the total return, not annualised — it corresponds directly to the
     /// `projection_periods` window the caller specified.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
     pub expected_yield_dot: u128,
+
+    /// expected_yield_dot as basis points of principal over the same window
+    /// (1000 = a 10% total return), for comparing positions of different size.
+    pub expected_yield_bps: u32,
+
+    /// expected_yield_dot less the input's withdrawal_cost_dot, floored at 0 —
+    /// what the user actually pockets (18 decimal fixed-point).
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub net_expected_yield_dot: u128,
+
+    /// Whether net_expected_yield_dot is positive, i.e. the projected yield
+    /// more than covers the cost of withdrawing.
+    pub profitable: bool,
+
+    /// Exact DOT deployed to HydraDX after any absolute cap (18 decimal fixed-point).
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub hydradx_allocation_dot: u128,
+
+    /// Exact DOT deployed to Interlay after any absolute cap (18 decimal fixed-point).
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub interlay_allocation_dot: u128,
+
+    /// DOT left undeployed, either because a leg failed the hurdle rate or
+    /// because both legs hit their absolute caps. When non-zero the two
+    /// allocation percentages sum to less than 100.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub unallocated_dot: u128,
+
+    /// Percentage of principal left undeployed (0–100). Together with the two
+    /// allocation percentages it always sums to 100.
+    pub unallocated_pct: u64,
+
+    /// DOT paid in HydraDX fees over the projection window: the gross yield
+    /// on hydradx_allocation_dot less its fee-adjusted yield (18 decimal
+    /// fixed-point). 0 for a zero-fee or unallocated leg.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub hydradx_fee_paid_dot: u128,
+
+    /// DOT paid in Interlay fees, computed the same way as hydradx_fee_paid_dot.
+    #[cfg_attr(feature = "serde", serde(with = "u128_as_string"))]
+    pub interlay_fee_paid_dot: u128,
+
+    /// Capital-weighted risk score of the allocation in [0, 10_000], weighted
+    /// the same way as projected_net_apy_bps.
+    pub blended_risk_score: u32,
+
+    /// Yield earned per unit of risk taken, higher is better:
+    ///   projected_net_apy_bps × BPS_DENOMINATOR ÷ (blended_risk_score + 1)
+    /// The +1 keeps a riskless allocation finite; the figure saturates at
+    /// u32::MAX.
+    pub capital_efficiency_bps: u32,
+
+    /// How clear-cut the split decision was, in [0, 10_000]: the gap between
+    /// the two risk-adjusted yields relative to their sum. 10_000 means one
+    /// leg was fully dominated; 0 means a dead tie, including the both-zero
+    /// 50/50 fallback.
+    pub confidence_bps: u32,
+
+    /// The limit that overrode the proportional risk-adjusted split, if any.
+    /// None means the allocation is optimal_split's result unmodified.
+    pub binding_constraint: Option<Constraint>,
+}
+
+/// A limit that can move the allocation away from the proportional split.
+///
+/// When several bind, the one applied last in optimize() is reported — the
+/// same precedence as SplitReason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Constraint {
+    /// An absolute per-leg DOT cap (hydradx_max_dot / interlay_max_dot).
+    MaxAllocation,
+    /// A leg's pool depth (hydradx_liquidity_depth_dot /
+    /// interlay_liquidity_depth_dot), tighter than its DOT cap, bound.
+    Liquidity,
+    /// The new split was within hysteresis_bps of prev_hydradx_pct, which
+    /// was kept instead.
+    Hysteresis,
+    /// A leg failed hurdle_rate_bps and its share was left in cash.
+    Hurdle,
+}
+
+/// Which stage of optimize() determined the final allocation, for auditing.
+///
+/// Stages run in the order listed; when several apply, the last one that
+/// changed the split is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SplitReason {
+    /// optimal_split's proportional risk-adjusted allocation, unmodified.
+    Proportional,
+    /// Both risk-adjusted yields were zero, so optimal_split fell back to 50/50.
+    ZeroTotalFallback,
+    /// The new split was within hysteresis_bps of prev_hydradx_pct, which
+    /// was kept instead.
+    HysteresisHeld,
+    /// At least one leg failed hurdle_rate_bps and its share was left in cash.
+    HurdleExcluded,
+    /// An absolute per-leg DOT cap or pool depth bound and moved capital off
+    /// the capped leg.
+    CapBound,
+}
+
+// ---------------------------------------------------------------------------
+// JSON representation
+// ---------------------------------------------------------------------------
+
+/// Serialises u128 fields as decimal strings.
+///
+/// JSON numbers are IEEE-754 doubles in most consumers, so a fixed-point DOT
+/// amount above 2^53 units (≈ 0.009 DOT) would silently lose precision if
+/// written as a bare number. Strings round-trip exactly.
+#[cfg(feature = "serde")]
+mod u128_as_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Market data source
+// ---------------------------------------------------------------------------
+
+/// Anything optimize() can read a position and its market data from.
+///
+/// OptimizerInput implements it directly; oracle adapters and test mocks
+/// implement it to drive the optimizer without building the calldata struct
+/// themselves. The required methods are the eight values the ABI carries;
+/// every other OptimizerInput field defaults as in OptimizerInput::default()
+/// unless to_input() is overridden.
+pub trait MarketData {
+    /// Total DOT being allocated (18 decimal fixed-point).
+    fn principal(&self) -> u128;
+    fn hydradx_apy_bps(&self) -> u32;
+    fn interlay_apy_bps(&self) -> u32;
+    fn hydradx_fee_bps(&self) -> u32;
+    fn interlay_fee_bps(&self) -> u32;
+    fn hydradx_risk_score(&self) -> u32;
+    fn interlay_risk_score(&self) -> u32;
+    fn projection_periods(&self) -> u32;
+
+    /// The full OptimizerInput the pipeline runs on.
+    fn to_input(&self) -> Cow<'_, OptimizerInput> {
+        Cow::Owned(OptimizerInput {
+            principal: self.principal(),
+            hydradx_apy_bps: self.hydradx_apy_bps(),
+            interlay_apy_bps: self.interlay_apy_bps(),
+            hydradx_fee_bps: self.hydradx_fee_bps(),
+            interlay_fee_bps: self.interlay_fee_bps(),
+            hydradx_risk_score: self.hydradx_risk_score(),
+            interlay_risk_score: self.interlay_risk_score(),
+            projection_periods: self.projection_periods(),
+            ..OptimizerInput::default()
+        })
+    }
+}
+
+impl MarketData for OptimizerInput {
+    fn principal(&self) -> u128 {
+        self.principal
+    }
+    fn hydradx_apy_bps(&self) -> u32 {
+        self.hydradx_apy_bps
+    }
+    fn interlay_apy_bps(&self) -> u32 {
+        self.interlay_apy_bps
+    }
+    fn hydradx_fee_bps(&self) -> u32 {
+        self.hydradx_fee_bps
+    }
+    fn interlay_fee_bps(&self) -> u32 {
+        self.interlay_fee_bps
+    }
+    fn hydradx_risk_score(&self) -> u32 {
+        self.hydradx_risk_score
+    }
+    fn interlay_risk_score(&self) -> u32 {
+        self.interlay_risk_score
+    }
+    fn projection_periods(&self) -> u32 {
+        self.projection_periods
+    }
+
+    /// Already an OptimizerInput: borrowed as-is, optional fields included.
+    fn to_input(&self) -> Cow<'_, OptimizerInput> {
+        Cow::Borrowed(self)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -120,15 +674,999 @@ pub struct YieldRecommendation {
 
 /// Entry point called by AtomicYieldExecutor.sol.
 ///
-/// Accepts an OptimizerInput and returns a YieldRecommendation or an error.
-/// Every intermediate value is computed with checked arithmetic — no step can
-/// silently overflow or underflow. On any error, return immediately; the Solidity
-/// caller will abort the XCM dispatch.
-pub fn optimize(input: &OptimizerInput) -> OptimizerResult<YieldRecommendation> {
-    // --- Input validation ---
+/// Accepts an OptimizerInput, or any other MarketData source, and returns a
+/// YieldRecommendation or an error. Every intermediate value is computed with
+/// checked arithmetic — no step can silently overflow or underflow. On any
+/// error, return immediately; the Solidity caller will abort the XCM dispatch.
+pub fn optimize<M: MarketData + ?Sized>(data: &M) -> OptimizerResult<YieldRecommendation> {
+    optimize_with_hook(&data.to_input(), |_, _| {})
+}
+
+/// optimize() with a debugging hook: `hook` is called with a step label and
+/// that step's intermediate value as the pipeline runs, in this order:
+///
+///   "step1_hydradx_gross_yield", "step1_interlay_gross_yield"  (DOT)
+///   "step2_hydradx_net_yield",   "step2_interlay_net_yield"    (DOT)
+///   "step3_hydradx_net_apy_bps", "step3_interlay_net_apy_bps"  (BPS)
+///   "step4_hydradx_pct"                                        (split, 0–100)
+///   "step5_expected_yield_dot"                                 (DOT)
+///
+/// Step 2's figures are after fees, min-hold and uptime — what step 3 turns
+/// into BPS — and step 4's is the split after hysteresis but before any
+/// hurdle or cap. If a step fails the hook has seen every step before it.
+/// The recommendation is identical to optimize()'s.
+pub fn optimize_with_hook<F: FnMut(&str, u128)>(
+    input: &OptimizerInput,
+    mut hook: F,
+) -> OptimizerResult<YieldRecommendation> {
+    optimize_with_split(input, None, &mut hook).map(|(rec, _)| rec)
+}
+
+/// optimize() with an audit trail: also returns the SplitReason recording
+/// which stage determined the final allocation. The recommendation is
+/// identical to optimize()'s for the same input.
+pub fn optimize_verbose(
+    input: &OptimizerInput,
+) -> OptimizerResult<(YieldRecommendation, SplitReason)> {
+    optimize_with_split(input, None, &mut |_, _| {})
+}
+
+/// Evaluate a caller-chosen split instead of the optimizer's: hydradx_pct of
+/// the principal to HydraDX and the rest to Interlay.
+///
+/// Runs every stage optimize() does — fees, compounding, hurdle, caps and the
+/// blended projections — except optimal_split, so passing the split optimize()
+/// chose (its hydradx_allocation_pct, whenever no cap or hurdle binds)
+/// reproduces its recommendation. strict_viability is not applied: a manual
+/// split is not the 50/50 fallback it guards against.
+///
+/// Returns OptimizerError::InvalidInput if hydradx_pct > 100, otherwise the
+/// same errors as optimize().
+pub fn evaluate_split(
+    input: &OptimizerInput,
+    hydradx_pct: u64,
+) -> OptimizerResult<YieldRecommendation> {
+    if hydradx_pct > 100 {
+        return Err(OptimizerError::InvalidInput);
+    }
+    optimize_with_split(input, Some(hydradx_pct), &mut |_, _| {}).map(|(rec, _)| rec)
+}
+
+/// The optimize() pipeline, taking the step-4 split from optimal_split, or
+/// from the caller when `fixed_hydradx_pct` is set. `hook` sees each step's
+/// value as described on optimize_with_hook.
+fn optimize_with_split(
+    input: &OptimizerInput,
+    fixed_hydradx_pct: Option<u64>,
+    hook: &mut dyn FnMut(&str, u128),
+) -> OptimizerResult<(YieldRecommendation, SplitReason)> {
+    // --- Input validation and steps 1–3 (see net_apys) ---
+    let (hydradx_net_apy_bps, interlay_net_apy_bps) = net_apys_with_hook(input, hook)?;
+
+    // --- Step 4: Optimal risk-adjusted split ---
+    //
+    // Calls math_lib::optimal_split which applies mean-variance penalisation
+    // and returns allocation percentages that sum to exactly 100. The risk
+    // scores it penalises are first scaled by the input's risk aversion.
+    // evaluate_split supplies its own split instead.
+    let (hydradx_risk, interlay_risk) = split_risks(input)?;
+    let (hydradx_pct, interlay_pct) = match fixed_hydradx_pct {
+        Some(pct) => (pct, 100u64.checked_sub(pct).ok_or(MathError::Underflow)?),
+        None => math_lib::optimal_split(
+            hydradx_net_apy_bps,
+            interlay_net_apy_bps,
+            hydradx_risk,
+            interlay_risk,
+        )?,
+    };
+    // Every later stage — the principal split and the blended weighted
+    // averages — relies on this pair being a whole distribution.
+    ensure_split_complete(hydradx_pct, interlay_pct)?;
+
+    // Hysteresis: a move of hysteresis_bps or less from the split already
+    // held is noise, not a reason to rebalance. A split that drops a leg
+    // entirely (e.g. one at max risk) is never held back, and a manual split
+    // is taken as is.
+    let held_pct = match (fixed_hydradx_pct, input.prev_hydradx_pct) {
+        (None, Some(prev))
+            if prev != hydradx_pct
+                && hydradx_pct != 0
+                && hydradx_pct != 100
+                && prev.abs_diff(hydradx_pct) * 100 <= input.hysteresis_bps as u64 =>
+        {
+            Some(prev)
+        }
+        _ => None,
+    };
+    let (hydradx_pct, interlay_pct) = match held_pct {
+        Some(prev) => (prev, 100 - prev),
+        None => (hydradx_pct, interlay_pct),
+    };
+    hook("step4_hydradx_pct", hydradx_pct as u128);
+    let hydradx_adjusted = math_lib::risk_adjusted_yield(hydradx_net_apy_bps, hydradx_risk)?;
+    let interlay_adjusted = math_lib::risk_adjusted_yield(interlay_net_apy_bps, interlay_risk)?;
+    let no_viable_destination = hydradx_adjusted == 0 && interlay_adjusted == 0;
+    if no_viable_destination && input.strict_viability && fixed_hydradx_pct.is_none() {
+        return Err(OptimizerError::NoViableDestination);
+    }
+    let mut reason = if held_pct.is_some() {
+        SplitReason::HysteresisHeld
+    } else if no_viable_destination {
+        SplitReason::ZeroTotalFallback
+    } else {
+        SplitReason::Proportional
+    };
+
+    // Confidence = |adj_h - adj_i| / (adj_h + adj_i), in BPS. The both-zero
+    // fallback is a tie and reads 0.
+    let adjusted_total = hydradx_adjusted
+        .checked_add(interlay_adjusted)
+        .ok_or(MathError::Overflow)?;
+    let confidence_bps = if adjusted_total == 0 {
+        0
+    } else {
+        math_lib::mul_fraction(
+            hydradx_adjusted.abs_diff(interlay_adjusted),
+            BPS_DENOMINATOR,
+            adjusted_total,
+        )? as u32
+    };
+
+    // --- Step 5: Blended APY and expected absolute yield ---
+    //
+    // Split the principal according to the recommended percentages, compound each
+    // leg independently at its net APY, and compute total expected yield.
+    // The blended APY is the capital-weighted average of both net APYs.
+
+    let hydradx_principal = math_lib::mul_fraction(input.principal, hydradx_pct as u128, 100)?;
+
+    // Interlay gets the remainder to ensure principal_h + principal_i == principal
+    // exactly, eliminating rounding drift from integer division.
+    let interlay_principal = input
+        .principal
+        .checked_sub(hydradx_principal)
+        .ok_or(MathError::Underflow)?;
+
+    // Hurdle rate: a leg whose net return does not exceed the hurdle keeps its
+    // share in cash rather than handing it to the other leg.
+    let hydradx_clears = clears_hurdle(hydradx_net_apy_bps, input.hurdle_rate_bps);
+    let interlay_clears = clears_hurdle(interlay_net_apy_bps, input.hurdle_rate_bps);
+    let (hydradx_principal, hydradx_pct) =
+        if hydradx_clears { (hydradx_principal, hydradx_pct) } else { (0, 0) };
+    let (interlay_principal, interlay_pct) =
+        if interlay_clears { (interlay_principal, interlay_pct) } else { (0, 0) };
+    if !hydradx_clears || !interlay_clears {
+        reason = SplitReason::HurdleExcluded;
+    }
+    let hurdle_cash_dot = input
+        .principal
+        .checked_sub(hydradx_principal)
+        .and_then(|v| v.checked_sub(interlay_principal))
+        .ok_or(MathError::Underflow)?;
+
+    // Absolute per-leg ceilings override the percentage split. Whatever a
+    // capped leg cannot take spills to the other leg; if both are capped the
+    // residual stays undeployed in DOT. A leg that failed the hurdle accepts
+    // no spill, and a max-risk leg keeps no more than optimal_split gave it.
+    // A leg's ceiling is its DOT cap or its pool depth, whichever is lower.
+    let hydradx_ceiling = input.hydradx_max_dot.min(input.hydradx_liquidity_depth_dot);
+    let interlay_ceiling = input.interlay_max_dot.min(input.interlay_liquidity_depth_dot);
+    let (capped_hydradx, capped_interlay, capped_residual) = apply_leg_caps(
+        hydradx_principal,
+        interlay_principal,
+        spill_cap(hydradx_clears, hydradx_risk, hydradx_ceiling, hydradx_principal),
+        spill_cap(interlay_clears, interlay_risk, interlay_ceiling, interlay_principal),
+    )?;
+    let caps_bound = capped_hydradx != hydradx_principal || capped_interlay != interlay_principal;
+    // The bound is reported as Liquidity when a leg sits at a depth that is
+    // tighter than its DOT cap.
+    let liquidity_bound = caps_bound
+        && ((capped_hydradx == hydradx_ceiling && hydradx_ceiling < input.hydradx_max_dot)
+            || (capped_interlay == interlay_ceiling && interlay_ceiling < input.interlay_max_dot));
+    let (hydradx_principal, interlay_principal) = (capped_hydradx, capped_interlay);
+    if caps_bound {
+        reason = SplitReason::CapBound;
+    }
+    let unallocated_dot = hurdle_cash_dot
+        .checked_add(capped_residual)
+        .ok_or(MathError::Overflow)?;
+
+    // Re-derive the reported percentages from the capped DOT amounts. With
+    // nothing left undeployed Interlay keeps the remainder so the pair still
+    // sums to 100; otherwise both are floored and the DOT fields are exact.
+    let (hydradx_pct, interlay_pct) = if caps_bound {
+        let h_pct = math_lib::mul_fraction(hydradx_principal, 100, input.principal)? as u64;
+        let i_pct = if unallocated_dot == 0 {
+            100u64.checked_sub(h_pct).ok_or(MathError::Underflow)?
+        } else {
+            math_lib::mul_fraction(interlay_principal, 100, input.principal)? as u64
+        };
+        (h_pct, i_pct)
+    } else {
+        (hydradx_pct, interlay_pct)
+    };
+    let unallocated_pct = 100u64
+        .checked_sub(hydradx_pct)
+        .and_then(|v| v.checked_sub(interlay_pct))
+        .ok_or(MathError::Underflow)?;
+
+    let hydradx_final =
+        math_lib::compound(hydradx_principal, hydradx_net_apy_bps, input.projection_periods)?;
+    let interlay_final =
+        math_lib::compound(interlay_principal, interlay_net_apy_bps, input.projection_periods)?;
+
+    // Undeployed DOT earns nothing but is still part of the position.
+    let total_final = hydradx_final
+        .checked_add(interlay_final)
+        .ok_or(MathError::Overflow)?
+        .checked_add(unallocated_dot)
+        .ok_or(MathError::Overflow)?;
+
+    let expected_yield_dot = total_final
+        .checked_sub(input.principal)
+        .ok_or(MathError::Underflow)?;
+    hook("step5_expected_yield_dot", expected_yield_dot);
+
+    // Reporting figure only: an off-the-scale return saturates rather than
+    // failing a recommendation that is otherwise valid.
+    let expected_yield_bps =
+        math_lib::mul_fraction(expected_yield_dot, BPS_DENOMINATOR, input.principal)
+            .map_or(u32::MAX, math_lib::saturating_u32);
+
+    let net_expected_yield_dot = expected_yield_dot.saturating_sub(input.withdrawal_cost_dot);
+
+    let (hydradx_apy_bps, interlay_apy_bps) = capped_apys(input);
+    let hydradx_fee_paid_dot = fee_paid_dot(
+        hydradx_principal,
+        hydradx_apy_bps,
+        input.hydradx_fee_bps,
+        leg_periods(input.hydradx_compound_periods, input.projection_periods),
+        input.hydradx_per_compound_fee,
+    )?;
+    let interlay_fee_paid_dot = fee_paid_dot(
+        interlay_principal,
+        interlay_apy_bps,
+        input.interlay_fee_bps,
+        leg_periods(input.interlay_compound_periods, input.projection_periods),
+        input.interlay_per_compound_fee,
+    )?;
+
+    // Blended figures are weighted by the reported percentages; once a cap
+    // binds those are floored, so the exact DOT amounts are used instead.
+    let weights = if caps_bound {
+        [hydradx_principal, interlay_principal]
+    } else {
+        [hydradx_pct as u128, interlay_pct as u128]
+    };
+    let nothing_deployed = weights == [0, 0];
+
+    let blended_apy_bps = if nothing_deployed {
+        0
+    } else {
+        math_lib::weighted_average(
+            &[hydradx_net_apy_bps as u128, interlay_net_apy_bps as u128],
+            &weights,
+        )? as u32
+    };
+
+    // Capital-weighted risk of the allocation, on the same weights as the APY.
+    let blended_risk_score = if nothing_deployed {
+        0
+    } else {
+        math_lib::weighted_average(
+            &[input.hydradx_risk_score as u128, input.interlay_risk_score as u128],
+            &weights,
+        )? as u32
+    };
+
+    let capital_efficiency_bps = math_lib::mul_fraction(
+        blended_apy_bps as u128,
+        BPS_DENOMINATOR,
+        blended_risk_score as u128 + 1,
+    )
+    .map_or(u32::MAX, math_lib::saturating_u32);
+
+    // The reported shares must always form a complete distribution.
+    math_lib::validate_allocation(&[hydradx_pct, interlay_pct, unallocated_pct])?;
+
+    let rec = YieldRecommendation {
+        use_hydradx: hydradx_pct > 0 || hydradx_principal > 0,
+        use_interlay: interlay_pct > 0 || interlay_principal > 0,
+        hydradx_allocation_pct: hydradx_pct,
+        interlay_allocation_pct: interlay_pct,
+        projected_net_apy_bps: blended_apy_bps,
+        expected_yield_dot,
+        expected_yield_bps,
+        net_expected_yield_dot,
+        profitable: net_expected_yield_dot > 0,
+        hydradx_allocation_dot: hydradx_principal,
+        interlay_allocation_dot: interlay_principal,
+        unallocated_dot,
+        unallocated_pct,
+        hydradx_fee_paid_dot,
+        interlay_fee_paid_dot,
+        blended_risk_score,
+        capital_efficiency_bps,
+        confidence_bps,
+        binding_constraint: match reason {
+            SplitReason::Proportional | SplitReason::ZeroTotalFallback => None,
+            SplitReason::HysteresisHeld => Some(Constraint::Hysteresis),
+            SplitReason::HurdleExcluded => Some(Constraint::Hurdle),
+            SplitReason::CapBound if liquidity_bound => Some(Constraint::Liquidity),
+            SplitReason::CapBound => Some(Constraint::MaxAllocation),
+        },
+    };
+    Ok((rec, reason))
+}
+
+// ---------------------------------------------------------------------------
+// Validation probe
+// ---------------------------------------------------------------------------
+
+/// Cheap "would optimize() fail?" check for batch screening of candidate inputs.
+///
+/// Runs the same field validation as optimize() followed by the step-1 gross
+/// compound of both legs — the step where oversized principals and APYs
+/// overflow — and stops there, skipping the split and projection entirely.
+/// Returns the same OptimizerError variant optimize() would return for each
+/// of those failures; Ok(()) means the input clears both checks.
+pub fn validate_input(input: &OptimizerInput) -> OptimizerResult<()> {
+    check_input_fields(input)?;
+    compound_legs(input)?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Resilient mode
+// ---------------------------------------------------------------------------
+
+/// Infallible optimize() for callers that prefer an approximate answer to a
+/// hard failure.
+///
+/// Out-of-range fields are first clamped into range: principal to
+/// [1, MAX_PRINCIPAL], projection_periods to at least 1, fees and uptimes to
+/// at most BPS_DENOMINATOR. If optimize() then overflows, the projection
+/// window (and any explicit per-leg compound counts) is halved and the call
+/// retried, down to a single period. Fewer periods means coarser compounding
+/// and may trip a min-hold check, so the result is an approximation of the
+/// requested window, never an extrapolation beyond it.
+///
+/// If no retry succeeds the whole principal is reported as unallocated — a
+/// recommendation that deploys nothing is always safe to act on.
+///
+/// For inputs optimize() accepts, the result is identical to optimize().
+pub fn optimize_resilient(input: &OptimizerInput) -> YieldRecommendation {
+    let bps = BPS_DENOMINATOR as u32;
+    let mut input = OptimizerInput {
+        principal: input.principal.clamp(1, MAX_PRINCIPAL),
+        projection_periods: input.projection_periods.max(1),
+        hydradx_fee_bps: input.hydradx_fee_bps.min(bps),
+        interlay_fee_bps: input.interlay_fee_bps.min(bps),
+        hydradx_uptime_bps: input.hydradx_uptime_bps.min(bps),
+        interlay_uptime_bps: input.interlay_uptime_bps.min(bps),
+        ..input.clone()
+    };
+    // A leg clamped to a 100% fee nets nothing, so its risk score carries no
+    // signal; clear it rather than let validate_destination reject it.
+    if input.hydradx_fee_bps == bps {
+        input.hydradx_risk_score = 0;
+    }
+    if input.interlay_fee_bps == bps {
+        input.interlay_risk_score = 0;
+    }
+
+    // Halve a period count, never below 1. A compound count of 0 (use
+    // projection_periods) stays 0 and follows the window down.
+    let halve = |periods: u32| if periods > 1 { periods / 2 } else { periods };
+
+    loop {
+        match optimize(&input) {
+            Ok(rec) => return rec,
+            Err(OptimizerError::Math(MathError::Overflow))
+                if input.projection_periods > 1
+                    || input.hydradx_compound_periods > 1
+                    || input.interlay_compound_periods > 1 =>
+            {
+                input.projection_periods = halve(input.projection_periods);
+                input.hydradx_compound_periods = halve(input.hydradx_compound_periods);
+                input.interlay_compound_periods = halve(input.interlay_compound_periods);
+            }
+            Err(_) => break,
+        }
+    }
+
+    YieldRecommendation {
+        unallocated_dot: input.principal,
+        unallocated_pct: 100,
+        ..YieldRecommendation::default()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Stress testing
+// ---------------------------------------------------------------------------
+
+/// optimize() under an adverse market shock, for risk reporting.
+///
+/// Both destinations' gross APYs are cut by `apy_shock_bps` of their value
+/// (5_000 halves them) and both risk scores rise by `risk_shock`, clamped to
+/// MAX_RISK_SCORE; the shocked input then runs through the normal pipeline.
+/// A uniform risk shock weighs more on the already riskier leg, so the split
+/// moves toward the destination whose risk-adjusted yield is less shocked.
+/// Zero shocks reproduce optimize() exactly.
+///
+/// Returns OptimizerError::InvalidInput if apy_shock_bps > BPS_DENOMINATOR,
+/// otherwise the same errors as optimize() on the shocked input.
+pub fn optimize_stressed(
+    input: &OptimizerInput,
+    apy_shock_bps: u32,
+    risk_shock: u32,
+) -> OptimizerResult<YieldRecommendation> {
+    if apy_shock_bps as u128 > BPS_DENOMINATOR {
+        return Err(OptimizerError::InvalidInput);
+    }
+    let shock_apy = |apy_bps: u32| -> MathResult<u32> {
+        let cut =
+            math_lib::mul_fraction(apy_bps as u128, apy_shock_bps as u128, BPS_DENOMINATOR)?;
+        Ok(apy_bps - cut as u32)
+    };
+    let shock_risk = |risk: u32| risk.saturating_add(risk_shock).min(MAX_RISK_SCORE as u32);
+
+    optimize(&OptimizerInput {
+        hydradx_apy_bps: shock_apy(input.hydradx_apy_bps)?,
+        interlay_apy_bps: shock_apy(input.interlay_apy_bps)?,
+        hydradx_risk_score: shock_risk(input.hydradx_risk_score),
+        interlay_risk_score: shock_risk(input.interlay_risk_score),
+        ..input.clone()
+    })
+}
+
+// ---------------------------------------------------------------------------
+// What-if override
+// ---------------------------------------------------------------------------
+
+/// optimize() with either destination's gross APY replaced, for scenario
+/// analysis. `None` keeps the input's own APY, so two `None`s reproduce
+/// optimize() exactly. Overrides pass through the same caps and validation
+/// as any other APY.
+pub fn optimize_with_override(
+    input: &OptimizerInput,
+    hydradx_apy_override: Option<u32>,
+    interlay_apy_override: Option<u32>,
+) -> OptimizerResult<YieldRecommendation> {
+    optimize(&OptimizerInput {
+        hydradx_apy_bps: hydradx_apy_override.unwrap_or(input.hydradx_apy_bps),
+        interlay_apy_bps: interlay_apy_override.unwrap_or(input.interlay_apy_bps),
+        ..input.clone()
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Fee sensitivity
+// ---------------------------------------------------------------------------
+
+/// How far expected_yield_dot moves if both fees are `fee_delta_bps` higher
+/// than assumed: optimize() on the raised fees minus optimize() as given.
+///
+/// Higher fees cut both legs' net yield, so the result is negative for a
+/// positive delta on a yielding input, and a zero delta returns 0. The split
+/// is re-optimised on the raised fees, so this is the change a user would
+/// see, not the fees' arithmetic drag on a fixed split.
+///
+/// Returns the same errors as optimize() on either input, including
+/// OptimizerError::InvalidInput if a raised fee exceeds 100%.
+pub fn yield_fee_sensitivity(input: &OptimizerInput, fee_delta_bps: u32) -> OptimizerResult<i128> {
+    let base = optimize(input)?;
+    let raised = optimize(&OptimizerInput {
+        hydradx_fee_bps: input.hydradx_fee_bps.saturating_add(fee_delta_bps),
+        interlay_fee_bps: input.interlay_fee_bps.saturating_add(fee_delta_bps),
+        ..input.clone()
+    })?;
+    Ok(signed_delta_u128(base.expected_yield_dot, raised.expected_yield_dot))
+}
+
+// ---------------------------------------------------------------------------
+// Reverse optimizer
+// ---------------------------------------------------------------------------
+
+/// Find an allocation whose blended net APY meets or exceeds `target_apy_bps`.
+///
+/// Uses the same per-leg net APYs as optimize() and blends them by whole
+/// percentages exactly as projected_net_apy_bps does. Caps and the hurdle
+/// rate are not applied — this answers whether the two markets can reach the
+/// target at all. The blend moves monotonically from one leg's APY to the
+/// other's as the split shifts, so a binary search over 0–100 finds the split
+/// that reaches the target with the least weight on the higher-yielding leg,
+/// keeping as much diversification as the target allows.
+///
+/// Returns Ok(None) if even 100% in the better destination falls short, and
+/// the same errors as optimize() for an invalid input.
+pub fn allocation_for_target_apy(
+    input: &OptimizerInput,
+    target_apy_bps: u32,
+) -> OptimizerResult<Option<(u64, u64)>> {
+    let (hydradx_net_apy_bps, interlay_net_apy_bps) = net_apys(input)?;
+    let (high_bps, low_bps) = if hydradx_net_apy_bps >= interlay_net_apy_bps {
+        (hydradx_net_apy_bps, interlay_net_apy_bps)
+    } else {
+        (interlay_net_apy_bps, hydradx_net_apy_bps)
+    };
+    if high_bps < target_apy_bps {
+        return Ok(None);
+    }
+
+    let blended = |high_pct: u64| {
+        math_lib::weighted_average(
+            &[high_bps as u128, low_bps as u128],
+            &[high_pct as u128, 100 - high_pct as u128],
+        )
+    };
+
+    // Smallest share of the higher leg whose blend reaches the target; 100
+    // always does, having passed the check above.
+    let (mut lo, mut hi) = (0u64, 100u64);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if blended(mid)? >= target_apy_bps as u128 {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    let split = if hydradx_net_apy_bps >= interlay_net_apy_bps {
+        (lo, 100 - lo)
+    } else {
+        (100 - lo, lo)
+    };
+    Ok(Some(split))
+}
+
+// ---------------------------------------------------------------------------
+// Marginal-equalizing split
+// ---------------------------------------------------------------------------
+
+/// The split at which both destinations' marginal risk-adjusted yield is
+/// equal — the optimum when returns diminish with position size.
+///
+/// Each leg's marginal value at a share of s% (x DOT) is modelled as
+///   marginal(s) = r ÷ (s × (1 + x ÷ depth))
+/// where r is the leg's risk-adjusted net APY exactly as optimize() step 4
+/// computes it. The 1 ÷ s factor is the diminishing return whose optimum is
+/// the proportional split; the depth factor halves marginal yield once the
+/// position reaches the pool's depth. With both depths at u128::MAX the
+/// depth factor vanishes and the result is optimal_split's. A shallow leg's
+/// marginal yield falls faster, so capital shifts toward the deeper one.
+///
+/// marginal is decreasing in s, so a binary search over whole percentages
+/// returns the largest HydraDX share whose marginal still matches or beats
+/// Interlay's, flooring like optimal_split. Both legs at zero risk-adjusted
+/// yield split 50/50.
+///
+/// Returns the same errors as optimize() for an invalid input, and
+/// OptimizerError::InvalidInput for a zero depth.
+pub fn marginal_equalizing_split(input: &OptimizerInput) -> OptimizerResult<(u64, u64)> {
+    if input.hydradx_liquidity_depth_dot == 0 || input.interlay_liquidity_depth_dot == 0 {
+        return Err(OptimizerError::InvalidInput);
+    }
+    let (hydradx_net_apy_bps, interlay_net_apy_bps) = net_apys(input)?;
+    let (hydradx_risk, interlay_risk) = split_risks(input)?;
+    let hydradx_adjusted = math_lib::risk_adjusted_yield(hydradx_net_apy_bps, hydradx_risk)?;
+    let interlay_adjusted = math_lib::risk_adjusted_yield(interlay_net_apy_bps, interlay_risk)?;
+    if hydradx_adjusted == 0 && interlay_adjusted == 0 {
+        return Ok((50, 50));
+    }
+
+    // marginal(s) in PRECISION fixed point; the first sliver of capital is
+    // worth infinitely much to any leg that yields at all.
+    let marginal = |adjusted: u128, depth: u128, pct: u64| -> OptimizerResult<u128> {
+        if pct == 0 {
+            return Ok(if adjusted == 0 { 0 } else { u128::MAX });
+        }
+        let dot = math_lib::mul_fraction(input.principal, pct as u128, 100)?;
+        let depth_penalty_bps = math_lib::mul_fraction(dot, BPS_DENOMINATOR, depth)?;
+        let size = (pct as u128)
+            .checked_mul(BPS_DENOMINATOR.checked_add(depth_penalty_bps).ok_or(MathError::Overflow)?)
+            .ok_or(MathError::Overflow)?;
+        Ok(math_lib::mul_fraction(adjusted, math_lib::PRECISION, size)?)
+    };
+    let hydradx_holds = |pct: u64| -> OptimizerResult<bool> {
+        Ok(marginal(hydradx_adjusted, input.hydradx_liquidity_depth_dot, pct)?
+            >= marginal(interlay_adjusted, input.interlay_liquidity_depth_dot, 100 - pct)?)
+    };
+
+    // hydradx_holds(lo) is true throughout, and false above hi.
+    let (mut lo, mut hi) = (0u64, 100u64);
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if hydradx_holds(mid)? {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    Ok((lo, 100 - lo))
+}
+
+// ---------------------------------------------------------------------------
+// Max-loss constrained split
+// ---------------------------------------------------------------------------
+
+/// The highest-yield split whose worst-case loss stays within `max_loss_bps`.
+///
+/// Each leg's risk score stands in for the share of it that could be lost,
+/// so a split's worst-case loss is the allocation-weighted risk
+///   loss = (h% × hydradx_risk + i% × interlay_risk) ÷ 100
+/// and its yield the allocation-weighted net APY from optimize() step 3.
+/// Every whole-percentage split with loss ≤ max_loss_bps is a candidate; the
+/// one with the highest yield wins, and among equal yields the lower loss.
+/// Caps, the hurdle rate and risk aversion are not applied — the loss cap
+/// is the mandate here.
+///
+/// Returns OptimizerError::InvalidInput if no split satisfies the cap, and
+/// the same errors as optimize() for an invalid input.
+pub fn max_loss_constrained_split(input: &OptimizerInput, max_loss_bps: u32) -> OptimizerResult<(u64, u64)> {
+    let (hydradx_net_apy_bps, interlay_net_apy_bps) = net_apys(input)?;
+    // Both sides scaled by 100 so every comparison stays exact.
+    let weighted = |hydradx: u32, interlay: u32, hydradx_pct: u64| {
+        hydradx as u128 * hydradx_pct as u128 + interlay as u128 * (100 - hydradx_pct) as u128
+    };
+    let loss_cap = max_loss_bps as u128 * 100;
+
+    let mut best: Option<(u64, u128, u128)> = None;
+    for hydradx_pct in 0..=100u64 {
+        let loss = weighted(input.hydradx_risk_score, input.interlay_risk_score, hydradx_pct);
+        if loss > loss_cap {
+            continue;
+        }
+        let yield_bps = weighted(hydradx_net_apy_bps, interlay_net_apy_bps, hydradx_pct);
+        let better = match best {
+            None => true,
+            Some((_, best_yield, best_loss)) => {
+                yield_bps > best_yield || (yield_bps == best_yield && loss < best_loss)
+            }
+        };
+        if better {
+            best = Some((hydradx_pct, yield_bps, loss));
+        }
+    }
+
+    let (hydradx_pct, _, _) = best.ok_or(OptimizerError::InvalidInput)?;
+    Ok((hydradx_pct, 100 - hydradx_pct))
+}
+
+// ---------------------------------------------------------------------------
+// Break-even fee
+// ---------------------------------------------------------------------------
+
+/// The highest fee one destination could charge, all else equal, while its
+/// risk-adjusted net yield still matches or beats the other's.
+///
+/// `for_hydradx` picks the leg whose fee is varied; the other leg is held at
+/// its input values. Risk-adjusted net yield only falls as the fee rises, so
+/// a binary search over 0–10_000 BPS finds the crossover, where optimal_split
+/// would move to an even 50/50 split. Returns u32::MAX if the two never cross
+/// — the leg is already worse at zero fee, or still no worse at 100%.
+///
+/// Returns the same errors as optimize() for an invalid input; the input's
+/// own fee on the varied leg is ignored.
+pub fn break_even_fee_bps(input: &OptimizerInput, for_hydradx: bool) -> OptimizerResult<u32> {
+    // (varied leg, other leg) risk-adjusted net yield with the varied fee set.
+    let adjusted_at = |fee_bps: u32| -> OptimizerResult<(u128, u128)> {
+        let mut probe = input.clone();
+        if for_hydradx {
+            probe.hydradx_fee_bps = fee_bps;
+        } else {
+            probe.interlay_fee_bps = fee_bps;
+        }
+        let (hydradx_net, interlay_net) = net_apys(&probe)?;
+        let (hydradx_risk, interlay_risk) = split_risks(&probe)?;
+        let hydradx = math_lib::risk_adjusted_yield(hydradx_net, hydradx_risk)?;
+        let interlay = math_lib::risk_adjusted_yield(interlay_net, interlay_risk)?;
+        Ok(if for_hydradx { (hydradx, interlay) } else { (interlay, hydradx) })
+    };
+
+    let max_fee = BPS_DENOMINATOR as u32;
+    // At a 100% fee the leg nets nothing, so it never crosses a zero yield.
+    let (leg_free, other) = adjusted_at(0)?;
+    if leg_free < other || other == 0 {
+        return Ok(u32::MAX);
+    }
+
+    // Largest fee at which the leg still holds its own: fee `lo` always does,
+    // and fee `hi + 1` never does.
+    let (mut lo, mut hi) = (0u32, max_fee - 1);
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        if adjusted_at(mid)?.0 >= other {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    Ok(lo)
+}
+
+// ---------------------------------------------------------------------------
+// Winning spread
+// ---------------------------------------------------------------------------
+
+/// The smallest APY spread, HydraDX minus Interlay in BPS, at which HydraDX's
+/// risk-adjusted net yield strictly exceeds Interlay's.
+///
+/// Interlay's APY is held at its input value and HydraDX's APY is varied,
+/// so both fees, both risks and the compounding schedule all count. A
+/// positive result is the premium HydraDX must pay to overcome its fee and
+/// risk disadvantage; a negative one means HydraDX wins even while yielding
+/// less. Risk-adjusted net yield only rises with APY, so a doubling search
+/// brackets the crossover and a binary search pins it to the BPS.
+///
+/// Returns i32::MAX if no APY wins — HydraDX is at MAX_RISK_SCORE, capped
+/// below the crossover, or would need an APY the optimizer rejects — and
+/// the same errors as optimize() for an invalid input.
+pub fn winning_spread_bps(input: &OptimizerInput) -> OptimizerResult<i32> {
+    let wins = |hydradx_apy_bps: u32| -> OptimizerResult<bool> {
+        let probe = OptimizerInput { hydradx_apy_bps, ..input.clone() };
+        let (hydradx_net, interlay_net) = net_apys(&probe)?;
+        let (hydradx_risk, interlay_risk) = split_risks(&probe)?;
+        Ok(math_lib::risk_adjusted_yield(hydradx_net, hydradx_risk)?
+            > math_lib::risk_adjusted_yield(interlay_net, interlay_risk)?)
+    };
+    // Surface an invalid input as itself before probing other APYs.
+    net_apys(input)?;
+
+    // Double until HydraDX wins. A probe that fails on the way up is an APY
+    // past what the input allows (max_apy_bps, or overflow), so none wins.
+    let mut hi = input.interlay_apy_bps.max(1);
+    loop {
+        match wins(hi) {
+            Ok(true) => break,
+            Ok(false) if hi < u32::MAX => hi = hi.saturating_mul(2),
+            _ => return Ok(i32::MAX),
+        }
+    }
+
+    // wins(hi) holds throughout, and fails below lo.
+    let mut lo = 0u32;
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if wins(mid)? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    let spread = lo as i64 - input.interlay_apy_bps as i64;
+    Ok(spread.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+}
+
+// ---------------------------------------------------------------------------
+// Split threshold
+// ---------------------------------------------------------------------------
+
+/// The smallest principal at which splitting across both destinations pays.
+///
+/// Splitting opens a second position, and unwinding it costs one more
+/// withdrawal_cost_dot. The split is worthwhile once the smaller leg's
+/// projected yield exceeds that cost:
+///   principal × minority_pct ÷ 100 × minority_net_apy_bps ÷ BPS_DENOMINATOR > cost
+/// Both the split and the net APYs are the ones optimize() derives from the
+/// input, and neither depends on principal, so the threshold is solved
+/// directly; the input's own principal only has to pass validation.
+///
+/// Returns 0 when withdrawal_cost_dot is 0, since any split is then free, and
+/// u128::MAX when splitting never pays: the split is one-sided, the smaller
+/// leg earns nothing, or the threshold exceeds u128.
+///
+/// Returns the same errors as optimize() for an invalid input.
+pub fn min_principal_for_split(input: &OptimizerInput) -> OptimizerResult<u128> {
+    let (hydradx_net_apy_bps, interlay_net_apy_bps) = net_apys(input)?;
+    if input.withdrawal_cost_dot == 0 {
+        return Ok(0);
+    }
+    let (hydradx_risk, interlay_risk) = split_risks(input)?;
+    let (hydradx_pct, interlay_pct) = math_lib::optimal_split(
+        hydradx_net_apy_bps,
+        interlay_net_apy_bps,
+        hydradx_risk,
+        interlay_risk,
+    )?;
+    let (minority_pct, minority_apy_bps) = if hydradx_pct <= interlay_pct {
+        (hydradx_pct, hydradx_net_apy_bps)
+    } else {
+        (interlay_pct, interlay_net_apy_bps)
+    };
+
+    // Yield per DOT of principal is minority_pct × apy ÷ (100 × BPS), so the
+    // break-even principal is cost × 100 × BPS ÷ (minority_pct × apy); the
+    // first principal strictly above it is the answer.
+    let per_dot_denominator = (minority_pct as u128) * (minority_apy_bps as u128);
+    if per_dot_denominator == 0 {
+        return Ok(u128::MAX);
+    }
+    Ok(math_lib::mul_fraction(
+        input.withdrawal_cost_dot,
+        100 * BPS_DENOMINATOR,
+        per_dot_denominator,
+    )
+    .ok()
+    .and_then(|break_even| break_even.checked_add(1))
+    .unwrap_or(u128::MAX))
+}
+
+// ---------------------------------------------------------------------------
+// Recommendation diff
+// ---------------------------------------------------------------------------
+
+/// What changed between two recommendations, as seen by a rebalancer.
+///
+/// Every delta is `new - old`: a positive allocation delta means that leg
+/// gains capital. Percentage and APY deltas are exact; yield_delta_dot
+/// saturates at the i128 bounds, which no realistic position approaches.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct RecommendationDiff {
+    /// Change in hydradx_allocation_pct (percentage points).
+    pub hydradx_pct_delta: i64,
+
+    /// Change in interlay_allocation_pct (percentage points).
+    pub interlay_pct_delta: i64,
+
+    /// Change in unallocated_pct (percentage points).
+    pub unallocated_pct_delta: i64,
+
+    /// Change in projected_net_apy_bps.
+    pub apy_delta_bps: i64,
+
+    /// Change in expected_yield_dot (18 decimal fixed-point).
+    pub yield_delta_dot: i128,
+
+    /// True if use_hydradx or use_interlay flipped — a leg is being opened or
+    /// closed, not just resized.
+    pub active_legs_changed: bool,
+}
+
+/// Compare two recommendations and report what the switch from `old` to `new`
+/// implies for the position.
+pub fn diff_recommendations(old: &YieldRecommendation, new: &YieldRecommendation) -> RecommendationDiff {
+    RecommendationDiff {
+        hydradx_pct_delta: signed_delta(old.hydradx_allocation_pct, new.hydradx_allocation_pct),
+        interlay_pct_delta: signed_delta(old.interlay_allocation_pct, new.interlay_allocation_pct),
+        unallocated_pct_delta: signed_delta(old.unallocated_pct, new.unallocated_pct),
+        apy_delta_bps: new.projected_net_apy_bps as i64 - old.projected_net_apy_bps as i64,
+        yield_delta_dot: signed_delta_u128(old.expected_yield_dot, new.expected_yield_dot),
+        active_legs_changed: old.use_hydradx != new.use_hydradx
+            || old.use_interlay != new.use_interlay,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Decision code
+// ---------------------------------------------------------------------------
+
+/// One-byte summary of which legs a recommendation dispatches, for compact
+/// event logging:
+///   0 = both legs, 1 = HydraDX only, 2 = Interlay only, 3 = neither (abstain)
+///
+/// Derived from the use_* flags alone. A leg's flag is false only when its
+/// allocation is 0, so code 1 implies interlay_allocation_pct == 0, code 2
+/// implies hydradx_allocation_pct == 0, and code 3 means the whole principal
+/// is unallocated.
+pub fn decision_code(rec: &YieldRecommendation) -> u8 {
+    match (rec.use_hydradx, rec.use_interlay) {
+        (true, true) => 0,
+        (true, false) => 1,
+        (false, true) => 2,
+        (false, false) => 3,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Yield attribution
+// ---------------------------------------------------------------------------
+
+/// Each leg's share of the total yield in BPS, as (hydradx, interlay).
+///
+/// Both shares are floored and the leftover dust goes to the larger
+/// contributor (HydraDX on a tie), so the pair always sums to exactly
+/// 10_000. A leg that earned nothing gets 0; if neither did the split is
+/// reported as (5000, 5000).
+pub fn yield_attribution(hydradx_yield: u128, interlay_yield: u128) -> (u64, u64) {
+    // Halving both keeps the ratio (to within one unit) when the sum would
+    // not fit in u128.
+    let (hydradx_yield, interlay_yield) = if hydradx_yield.checked_add(interlay_yield).is_some() {
+        (hydradx_yield, interlay_yield)
+    } else {
+        (hydradx_yield / 2, interlay_yield / 2)
+    };
+    let total = hydradx_yield + interlay_yield;
+    if total == 0 {
+        return (5_000, 5_000);
+    }
+
+    // Each share is at most BPS_DENOMINATOR, so mul_fraction cannot fail.
+    let share = |y: u128| math_lib::mul_fraction(y, BPS_DENOMINATOR, total).map_or(0, |v| v as u64);
+    let (hydradx_bps, interlay_bps) = (share(hydradx_yield), share(interlay_yield));
+    let dust = BPS_DENOMINATOR as u64 - hydradx_bps - interlay_bps;
+    if hydradx_yield >= interlay_yield {
+        (hydradx_bps + dust, interlay_bps)
+    } else {
+        (hydradx_bps, interlay_bps + dust)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Total cost of ownership
+// ---------------------------------------------------------------------------
+
+/// Every friction of holding `rec` to the end of the window, in DOT (18
+/// decimal fixed-point): the fees each leg pays (hydradx_fee_paid_dot and
+/// interlay_fee_paid_dot) plus the input's withdrawal_cost_dot.
+///
+/// The optimizer models no entry fee or rebalance cost, so neither
+/// contributes. `rec` should be optimize()'s recommendation for `input`.
+///
+/// Returns MathError::Overflow if the sum exceeds u128.
+pub fn total_cost_of_ownership(input: &OptimizerInput, rec: &YieldRecommendation) -> MathResult<u128> {
+    math_lib::checked_sum(&[
+        rec.hydradx_fee_paid_dot,
+        rec.interlay_fee_paid_dot,
+        input.withdrawal_cost_dot,
+    ])
+}
+
+// ---------------------------------------------------------------------------
+// Weight estimation
+// ---------------------------------------------------------------------------
+
+/// Fixed ref_time charged for every optimize call: ABI decode, validation,
+/// the split computation and result encoding.
+#[cfg(feature = "weights")]
+pub const OPTIMIZE_BASE_REF_TIME: u64 = 50_000_000;
+
+/// ref_time charged per compound loop iteration. optimize() runs two gross
+/// loops (one per leg, over that leg's compounding schedule) and two net
+/// loops of `projection_periods` iterations each.
+#[cfg(feature = "weights")]
+pub const OPTIMIZE_REF_TIME_PER_COMPOUND_STEP: u64 = 25_000;
+
+/// proof_size charged for every optimize call. The optimizer reads no
+/// storage, so nothing is added to the PoV.
+#[cfg(feature = "weights")]
+pub const OPTIMIZE_PROOF_SIZE: u64 = 0;
+
+/// Estimate the runtime Weight of an optimize() call for the given input.
+///
+/// ref_time = OPTIMIZE_BASE_REF_TIME + compound steps × OPTIMIZE_REF_TIME_PER_COMPOUND_STEP
+/// proof_size = OPTIMIZE_PROOF_SIZE (constant — the function is stateless)
+///
+/// where compound steps = hydradx periods + interlay periods + 2 × projection_periods.
+///
+/// The estimate does not validate the input; a call that would be rejected is
+/// charged as if it ran to completion, which is the safe upper bound.
+#[cfg(feature = "weights")]
+pub fn optimize_weight(input: &OptimizerInput) -> Weight {
+    let steps = (leg_periods(input.hydradx_compound_periods, input.projection_periods) as u64)
+        .saturating_add(leg_periods(input.interlay_compound_periods, input.projection_periods) as u64)
+        .saturating_add((input.projection_periods as u64).saturating_mul(2));
+    let ref_time = OPTIMIZE_REF_TIME_PER_COMPOUND_STEP
+        .saturating_mul(steps)
+        .saturating_add(OPTIMIZE_BASE_REF_TIME);
+    Weight::from_parts(ref_time, OPTIMIZE_PROOF_SIZE)
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Field-level checks shared by optimize() and validate_input().
+fn check_input_fields(input: &OptimizerInput) -> OptimizerResult<()> {
     if input.principal == 0 {
         return Err(OptimizerError::InvalidInput);
     }
+    // Unit-confusion guard: more DOT than can exist means the caller scaled wrong.
+    if input.principal > MAX_PRINCIPAL {
+        return Err(OptimizerError::InvalidInput);
+    }
     if input.projection_periods == 0 {
         return Err(OptimizerError::InvalidInput);
     }
@@ -138,30 +1676,98 @@ pub fn optimize(input: &OptimizerInput) -> OptimizerResult<YieldRecommendation>
     {
         return Err(OptimizerError::InvalidInput);
     }
+    // Uptime is a fraction: a destination cannot be up more than 100% of the time.
+    if input.hydradx_uptime_bps as u128 > BPS_DENOMINATOR
+        || input.interlay_uptime_bps as u128 > BPS_DENOMINATOR
+    {
+        return Err(OptimizerError::InvalidInput);
+    }
+    // A previous split is a share of principal like any other.
+    if input.prev_hydradx_pct.is_some_and(|pct| pct > 100) {
+        return Err(OptimizerError::InvalidInput);
+    }
+    // An implausible APY is a data error, not a market to optimise against.
+    // Per-leg caps clamp first, so only an uncapped spike is rejected here.
+    let (hydradx_apy_bps, interlay_apy_bps) = capped_apys(input);
+    math_lib::validate_destination(hydradx_apy_bps, input.hydradx_fee_bps, input.hydradx_risk_score)?;
+    math_lib::validate_destination(interlay_apy_bps, input.interlay_fee_bps, input.interlay_risk_score)?;
+    if input.max_apy_bps != 0
+        && input.max_apy_bps != u32::MAX
+        && (hydradx_apy_bps > input.max_apy_bps || interlay_apy_bps > input.max_apy_bps)
+    {
+        return Err(OptimizerError::InvalidInput);
+    }
+    Ok(())
+}
+
+/// Input validation plus steps 1–3 of optimize(): each leg's net return over
+/// the projection window in BPS of principal, as (hydradx, interlay). This is
+/// the figure the split compares and the blended APY averages.
+fn net_apys(input: &OptimizerInput) -> OptimizerResult<(u32, u32)> {
+    net_apys_with_hook(input, &mut |_, _| {})
+}
+
+/// net_apys() reporting each leg's step 1–3 figures to `hook`.
+fn net_apys_with_hook(
+    input: &OptimizerInput,
+    hook: &mut dyn FnMut(&str, u128),
+) -> OptimizerResult<(u32, u32)> {
+    check_input_fields(input)?;
 
     // --- Step 1: Gross compound yield for each destination ---
     //
-    // Compound the full principal at each destination's gross APY over
-    // projection_periods. Subtracting principal gives the gross yield in DOT.
-    let hydradx_compounded =
-        math_lib::compound(input.principal, input.hydradx_apy_bps, input.projection_periods)?;
+    // Compound the full principal at each destination's gross APY over its
+    // own compounding schedule. Subtracting principal gives the gross yield in
+    // DOT. A per-compound fee is charged inside this loop rather than in step 2.
+    let (hydradx_compounded, interlay_compounded) = compound_legs(input)?;
     let hydradx_gross_yield = hydradx_compounded
         .checked_sub(input.principal)
         .ok_or(MathError::Underflow)?;
-
-    let interlay_compounded =
-        math_lib::compound(input.principal, input.interlay_apy_bps, input.projection_periods)?;
     let interlay_gross_yield = interlay_compounded
         .checked_sub(input.principal)
         .ok_or(MathError::Underflow)?;
+    hook("step1_hydradx_gross_yield", hydradx_gross_yield);
+    hook("step1_interlay_gross_yield", interlay_gross_yield);
 
     // --- Step 2: Apply fee deduction ---
     //
-    // Fees are applied to the yield only, not to the principal.
-    let hydradx_net_yield =
-        math_lib::fee_adjusted_yield(hydradx_gross_yield, input.hydradx_fee_bps)?;
-    let interlay_net_yield =
-        math_lib::fee_adjusted_yield(interlay_gross_yield, input.interlay_fee_bps)?;
+    // Fees are applied to the yield only, not to the principal. Legs with a
+    // per-compound fee have already paid it in step 1.
+    let mut hydradx_net_yield = if input.hydradx_per_compound_fee {
+        hydradx_gross_yield
+    } else {
+        math_lib::fee_adjusted_yield(hydradx_gross_yield, input.hydradx_fee_bps)?
+    };
+    let mut interlay_net_yield = if input.interlay_per_compound_fee {
+        interlay_gross_yield
+    } else {
+        math_lib::fee_adjusted_yield(interlay_gross_yield, input.interlay_fee_bps)?
+    };
+
+    // Early-exit penalty: a leg that cannot be held for its minimum period
+    // forfeits its yield, so it enters the split with a zero net figure and
+    // is deprioritised accordingly.
+    if input.projection_periods < input.hydradx_min_hold_periods {
+        hydradx_net_yield = 0;
+    }
+    if input.projection_periods < input.interlay_min_hold_periods {
+        interlay_net_yield = 0;
+    }
+
+    // Downtime: no yield accrues while a destination is paused, so each net
+    // yield is scaled by its uptime fraction. 10_000 leaves it unchanged.
+    let hydradx_net_yield = math_lib::mul_fraction(
+        hydradx_net_yield,
+        input.hydradx_uptime_bps as u128,
+        BPS_DENOMINATOR,
+    )?;
+    let interlay_net_yield = math_lib::mul_fraction(
+        interlay_net_yield,
+        input.interlay_uptime_bps as u128,
+        BPS_DENOMINATOR,
+    )?;
+    hook("step2_hydradx_net_yield", hydradx_net_yield);
+    hook("step2_interlay_net_yield", interlay_net_yield);
 
     // --- Step 3: Derive net APY BPS from net yield ---
     //
@@ -172,73 +1778,173 @@ pub fn optimize(input: &OptimizerInput) -> OptimizerResult<YieldRecommendation>
     // projection_periods == 365 with daily compounding. The optimizer compares
     // these figures on a like-for-like basis (same projection window), so
     // annualisation is not required for the comparison to be valid.
-    let hydradx_net_apy_bps = (hydradx_net_yield
-        .checked_mul(BPS_DENOMINATOR)
-        .ok_or(MathError::Overflow)?
-        .checked_div(input.principal)
-        .ok_or(MathError::DivisionByZero)?) as u32;
+    //
+    // Step 5 compounds each leg at this figure, so a return too large for u32
+    // is an overflow, never a truncated (wrapped) rate.
+    let net_apy_bps = |net_yield: u128| -> MathResult<u32> {
+        let bps = net_yield
+            .checked_mul(BPS_DENOMINATOR)
+            .ok_or(MathError::Overflow)?
+            .checked_div(input.principal)
+            .ok_or(MathError::DivisionByZero)?;
+        u32::try_from(bps).map_err(|_| MathError::Overflow)
+    };
+    let hydradx_net_apy_bps = net_apy_bps(hydradx_net_yield)?;
+    let interlay_net_apy_bps = net_apy_bps(interlay_net_yield)?;
+    hook("step3_hydradx_net_apy_bps", hydradx_net_apy_bps as u128);
+    hook("step3_interlay_net_apy_bps", interlay_net_apy_bps as u128);
 
-    let interlay_net_apy_bps = (interlay_net_yield
-        .checked_mul(BPS_DENOMINATOR)
-        .ok_or(MathError::Overflow)?
-        .checked_div(input.principal)
-        .ok_or(MathError::DivisionByZero)?) as u32;
+    Ok((hydradx_net_apy_bps, interlay_net_apy_bps))
+}
 
-    // --- Step 4: Optimal risk-adjusted split ---
-    //
-    // Calls math_lib::optimal_split which applies mean-variance penalisation
-    // and returns allocation percentages that sum to exactly 100.
-    let (hydradx_pct, interlay_pct) = math_lib::optimal_split(
-        hydradx_net_apy_bps,
-        interlay_net_apy_bps,
-        input.hydradx_risk_score,
-        input.interlay_risk_score,
-    )?;
+/// Each leg's gross APY after its apy cap, as (hydradx, interlay). Every
+/// computation reads the APYs through this rather than the raw fields.
+fn capped_apys(input: &OptimizerInput) -> (u32, u32) {
+    (
+        input.hydradx_apy_bps.min(input.hydradx_apy_cap_bps),
+        input.interlay_apy_bps.min(input.interlay_apy_cap_bps),
+    )
+}
 
-    // --- Step 5: Blended APY and expected absolute yield ---
-    //
-    // Split the principal according to the recommended percentages, compound each
-    // leg independently at its net APY, and compute total expected yield.
-    // The blended APY is the capital-weighted average of both net APYs.
+/// A leg's compounding count over the window; 0 falls back to projection_periods.
+fn leg_periods(compound_periods: u32, projection_periods: u32) -> u32 {
+    if compound_periods == 0 { projection_periods } else { compound_periods }
+}
 
-    let hydradx_principal = input
-        .principal
-        .checked_mul(hydradx_pct as u128)
-        .ok_or(MathError::Overflow)?
-        .checked_div(100)
-        .ok_or(MathError::DivisionByZero)?;
+/// Step-1 gross compound of the full principal at each leg, returned as
+/// (hydradx_compounded, interlay_compounded). Legs with a per-compound fee
+/// pay it on every step here.
+fn compound_legs(input: &OptimizerInput) -> MathResult<(u128, u128)> {
+    let (hydradx_apy_bps, interlay_apy_bps) = capped_apys(input);
+    let leg = |apy_bps: u32, fee_bps: u32, compound_periods: u32, per_compound_fee: bool| {
+        let periods = leg_periods(compound_periods, input.projection_periods);
+        if per_compound_fee {
+            math_lib::compound_with_step_fee(input.principal, apy_bps, periods, fee_bps)
+        } else {
+            math_lib::compound(input.principal, apy_bps, periods)
+        }
+    };
+    Ok((
+        leg(
+            hydradx_apy_bps,
+            input.hydradx_fee_bps,
+            input.hydradx_compound_periods,
+            input.hydradx_per_compound_fee,
+        )?,
+        leg(
+            interlay_apy_bps,
+            input.interlay_fee_bps,
+            input.interlay_compound_periods,
+            input.interlay_per_compound_fee,
+        )?,
+    ))
+}
 
-    // Interlay gets the remainder to ensure principal_h + principal_i == principal
-    // exactly, eliminating rounding drift from integer division.
-    let interlay_principal = input
-        .principal
-        .checked_sub(hydradx_principal)
+/// DOT paid in fees by one leg's deployed principal over `periods`: its
+/// fee-free gross yield less the yield actually kept, whether the fee is taken
+/// once from the total or on every compounding step.
+fn fee_paid_dot(
+    principal: u128,
+    apy_bps: u32,
+    fee_bps: u32,
+    periods: u32,
+    per_compound_fee: bool,
+) -> MathResult<u128> {
+    if principal == 0 || fee_bps == 0 {
+        return Ok(0);
+    }
+    let gross_yield = math_lib::compound(principal, apy_bps, periods)?
+        .checked_sub(principal)
         .ok_or(MathError::Underflow)?;
+    let net_yield = if per_compound_fee {
+        math_lib::compound_with_step_fee(principal, apy_bps, periods, fee_bps)?
+            .checked_sub(principal)
+            .ok_or(MathError::Underflow)?
+    } else {
+        math_lib::fee_adjusted_yield(gross_yield, fee_bps)?
+    };
+    gross_yield.checked_sub(net_yield).ok_or(MathError::Underflow)
+}
 
-    let hydradx_final =
-        math_lib::compound(hydradx_principal, hydradx_net_apy_bps, input.projection_periods)?;
-    let interlay_final =
-        math_lib::compound(interlay_principal, interlay_net_apy_bps, input.projection_periods)?;
+/// Clamp each leg to its absolute DOT ceiling, spilling any excess to the other
+/// leg. Returns (hydradx_dot, interlay_dot, unallocated_dot); the three always
+/// sum to hydradx + interlay.
+fn apply_leg_caps(
+    hydradx: u128,
+    interlay: u128,
+    hydradx_cap: u128,
+    interlay_cap: u128,
+) -> MathResult<(u128, u128, u128)> {
+    let hydradx_kept = hydradx.min(hydradx_cap);
+    let hydradx_spill = hydradx.checked_sub(hydradx_kept).ok_or(MathError::Underflow)?;
 
-    let total_final = hydradx_final
-        .checked_add(interlay_final)
-        .ok_or(MathError::Overflow)?;
+    let interlay_wanted = interlay.checked_add(hydradx_spill).ok_or(MathError::Overflow)?;
+    let interlay_kept = interlay_wanted.min(interlay_cap);
+    let interlay_spill = interlay_wanted
+        .checked_sub(interlay_kept)
+        .ok_or(MathError::Underflow)?;
 
-    let expected_yield_dot = total_final
-        .checked_sub(input.principal)
+    let hydradx_wanted = hydradx_kept.checked_add(interlay_spill).ok_or(MathError::Overflow)?;
+    let hydradx_final = hydradx_wanted.min(hydradx_cap);
+    let unallocated = hydradx_wanted
+        .checked_sub(hydradx_final)
         .ok_or(MathError::Underflow)?;
 
-    let blended_apy_bps = math_lib::weighted_average(
-        &[hydradx_net_apy_bps as u128, interlay_net_apy_bps as u128],
-        &[hydradx_pct as u128, interlay_pct as u128],
-    )? as u32;
+    Ok((hydradx_final, interlay_kept, unallocated))
+}
 
-    Ok(YieldRecommendation {
-        use_hydradx: hydradx_pct > 0,
-        use_interlay: interlay_pct > 0,
-        hydradx_allocation_pct: hydradx_pct,
-        interlay_allocation_pct: interlay_pct,
-        projected_net_apy_bps: blended_apy_bps,
-        expected_yield_dot,
-    })
-}
\ No newline at end of file
+/// Both risk scores scaled by the input's risk_aversion_bps, as (hydradx,
+/// interlay): the figures optimal_split and every risk-adjusted comparison use.
+fn split_risks(input: &OptimizerInput) -> OptimizerResult<(u32, u32)> {
+    Ok((
+        math_lib::scaled_risk(input.hydradx_risk_score, input.risk_aversion_bps)?,
+        math_lib::scaled_risk(input.interlay_risk_score, input.risk_aversion_bps)?,
+    ))
+}
+
+/// Guard on optimal_split's output: the two percentages must sum to exactly
+/// 100 before anything is weighted by them. A violation is a bug in the split
+/// logic, so debug builds panic; release builds fail the call instead of
+/// dividing by a wrong total.
+pub(crate) fn ensure_split_complete(hydradx_pct: u64, interlay_pct: u64) -> OptimizerResult<()> {
+    let check = math_lib::validate_allocation(&[hydradx_pct, interlay_pct]);
+    debug_assert!(
+        check.is_ok(),
+        "optimal_split returned {hydradx_pct} + {interlay_pct}, expected a sum of 100"
+    );
+    Ok(check?)
+}
+
+/// The ceiling apply_leg_caps enforces on one leg. A leg that failed the
+/// hurdle takes nothing. A leg at MAX_RISK_SCORE has zero risk-adjusted yield,
+/// so it may keep its own optimal_split share (non-zero only when both legs
+/// are max-risk) but never absorbs the other leg's capped excess.
+fn spill_cap(clears_hurdle: bool, risk_score: u32, max_dot: u128, principal: u128) -> u128 {
+    if !clears_hurdle {
+        0
+    } else if risk_score as u128 >= MAX_RISK_SCORE {
+        max_dot.min(principal)
+    } else {
+        max_dot
+    }
+}
+
+/// Whether a leg's net return clears the hurdle. A zero hurdle admits every leg,
+/// including one that yields nothing, so the split is unchanged when disabled.
+fn clears_hurdle(net_apy_bps: u32, hurdle_rate_bps: u32) -> bool {
+    hurdle_rate_bps == 0 || net_apy_bps > hurdle_rate_bps
+}
+
+/// `new - old` for u64 percentages, saturating at the i64 bounds.
+fn signed_delta(old: u64, new: u64) -> i64 {
+    (new as i128 - old as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// `new - old` for u128 DOT amounts, saturating at the i128 bounds.
+fn signed_delta_u128(old: u128, new: u128) -> i128 {
+    if new >= old {
+        i128::try_from(new - old).unwrap_or(i128::MAX)
+    } else {
+        i128::try_from(old - new).map(|d| -d).unwrap_or(i128::MIN)
+    }
+}
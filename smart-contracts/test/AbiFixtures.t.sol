@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+
+/// @title AbiFixturesTest
+/// @notice Pins the abi.encode output for the static tuples AtomicYieldExecutor
+///         sends to and decodes from the YieldOptimizer precompile.
+///
+/// @dev    The expected bytes are the SOL_* blobs in pvm-modules/src/abi.rs,
+///         which the Rust decoder and encoder are tested against. This test
+///         checks them against solc's own abi.encode, so a layout change on
+///         either side fails one of the two suites. Keep both in sync.
+///
+///         Run with `npx hardhat test solidity` (solc 0.8.20, the default
+///         profile in hardhat.config.ts).
+contract AbiFixturesTest is Test {
+
+    // -------------------------------------------------------------------------
+    // OptimizerInput: (uint128, uint32 × 7)
+    // -------------------------------------------------------------------------
+
+    function encodeInput(
+        uint128 principal,
+        uint32 hydraDXApyBps,
+        uint32 interlayApyBps,
+        uint32 hydraDXFeeBps,
+        uint32 interlayFeeBps,
+        uint32 hydraDXRiskScore,
+        uint32 interlayRiskScore,
+        uint32 projectionPeriods
+    ) internal pure returns (bytes memory) {
+        return abi.encode(
+            principal,
+            hydraDXApyBps,
+            interlayApyBps,
+            hydraDXFeeBps,
+            interlayFeeBps,
+            hydraDXRiskScore,
+            interlayRiskScore,
+            projectionPeriods
+        );
+    }
+
+    function test_InputMin() public pure {
+        assertEq(
+            encodeInput(0, 0, 0, 0, 0, 0, 0, 0),
+            hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+        );
+    }
+
+    function test_InputTypical() public pure {
+        assertEq(
+            encodeInput(1_000 ether, 1_200, 900, 50, 100, 1_500, 2_500, 365),
+            hex"00000000000000000000000000000000000000000000003635c9adc5dea00000"
+                hex"00000000000000000000000000000000000000000000000000000000000004b0"
+                hex"0000000000000000000000000000000000000000000000000000000000000384"
+                hex"0000000000000000000000000000000000000000000000000000000000000032"
+                hex"0000000000000000000000000000000000000000000000000000000000000064"
+                hex"00000000000000000000000000000000000000000000000000000000000005dc"
+                hex"00000000000000000000000000000000000000000000000000000000000009c4"
+                hex"000000000000000000000000000000000000000000000000000000000000016d"
+        );
+    }
+
+    function test_InputMax() public pure {
+        uint32 m = type(uint32).max;
+        assertEq(
+            encodeInput(type(uint128).max, m, m, m, m, m, m, m),
+            hex"00000000000000000000000000000000ffffffffffffffffffffffffffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+        );
+    }
+
+    // -------------------------------------------------------------------------
+    // OptimizerResult: (bool, bool, bool, uint64, uint64, uint32, uint128)
+    // -------------------------------------------------------------------------
+
+    function test_OutputMin() public pure {
+        assertEq(
+            abi.encode(true, false, false, uint64(0), uint64(0), uint32(0), uint128(0)),
+            hex"0000000000000000000000000000000000000000000000000000000000000001"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+                hex"0000000000000000000000000000000000000000000000000000000000000000"
+        );
+    }
+
+    function test_OutputTypical() public pure {
+        assertEq(
+            abi.encode(
+                true, true, true, uint64(60), uint64(40), uint32(1_133),
+                uint128(120_166_252_604_411_974_789)
+            ),
+            hex"0000000000000000000000000000000000000000000000000000000000000001"
+                hex"0000000000000000000000000000000000000000000000000000000000000001"
+                hex"0000000000000000000000000000000000000000000000000000000000000001"
+                hex"000000000000000000000000000000000000000000000000000000000000003c"
+                hex"0000000000000000000000000000000000000000000000000000000000000028"
+                hex"000000000000000000000000000000000000000000000000000000000000046d"
+                hex"00000000000000000000000000000000000000000000000683a44a135bd59485"
+        );
+    }
+
+    function test_OutputMax() public pure {
+        assertEq(
+            abi.encode(
+                true, true, true, type(uint64).max, type(uint64).max, type(uint32).max,
+                type(uint128).max
+            ),
+            hex"0000000000000000000000000000000000000000000000000000000000000001"
+                hex"0000000000000000000000000000000000000000000000000000000000000001"
+                hex"0000000000000000000000000000000000000000000000000000000000000001"
+                hex"000000000000000000000000000000000000000000000000ffffffffffffffff"
+                hex"000000000000000000000000000000000000000000000000ffffffffffffffff"
+                hex"00000000000000000000000000000000000000000000000000000000ffffffff"
+                hex"00000000000000000000000000000000ffffffffffffffffffffffffffffffff"
+        );
+    }
+}